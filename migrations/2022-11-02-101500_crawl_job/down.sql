@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+DROP TABLE crawl_job;
+DROP TYPE PQ_CRAWL_STATUS;
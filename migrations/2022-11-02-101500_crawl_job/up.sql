@@ -0,0 +1,13 @@
+-- Your SQL goes here
+CREATE TYPE PQ_CRAWL_STATUS AS ENUM ('done', 'empty', 'failed');
+
+CREATE TABLE crawl_job (
+  "endpoint" TEXT NOT NULL,
+  "entity_id" INT NOT NULL,
+  "status" PQ_CRAWL_STATUS NOT NULL,
+  "attempts" INT NOT NULL,
+  "last_error" TEXT,
+  "http_status" INT,
+  "fetched_at" TIMESTAMP NOT NULL,
+  PRIMARY KEY ("endpoint", "entity_id")
+);
@@ -0,0 +1,15 @@
+-- Your SQL goes here
+CREATE TABLE speech_text (
+  "meeting_id" INT NOT NULL,
+  "position" INT NOT NULL,
+  "speaker" TEXT NOT NULL,
+  "faction" TEXT,
+  "chair" BOOLEAN NOT NULL,
+  "person_id" INT,
+  "speech_id" INT,
+  "text" TEXT NOT NULL,
+  PRIMARY KEY ("meeting_id", "position")
+);
+
+CREATE INDEX speech_text_person_id ON speech_text ("person_id");
+CREATE INDEX speech_text_speech_id ON speech_text ("speech_id");
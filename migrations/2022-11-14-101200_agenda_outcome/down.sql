@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+DROP TABLE agenda_outcome;
+DROP TYPE PQ_AGENDA_OUTCOME;
@@ -0,0 +1,18 @@
+-- Your SQL goes here
+CREATE TYPE PQ_AGENDA_OUTCOME AS ENUM ('approved', 'rejected', 'returned_for_improvement', 'postponed', 'other');
+
+CREATE TABLE agenda_outcome (
+  "meeting_id" INT NOT NULL,
+  "position" INT NOT NULL,
+  "nr" TEXT,
+  "agenda_item_id" INT,
+  "document_key" INT,
+  "title" TEXT NOT NULL,
+  "outcome" PQ_AGENDA_OUTCOME,
+  "decision" TEXT,
+  "presenters" TEXT[] NOT NULL,
+  "document_numbers" TEXT[] NOT NULL,
+  PRIMARY KEY ("meeting_id", "position")
+);
+
+CREATE INDEX agenda_outcome_agenda_item_id ON agenda_outcome ("agenda_item_id");
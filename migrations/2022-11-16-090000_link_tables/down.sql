@@ -0,0 +1,60 @@
+-- This file should undo anything in `up.sql`
+DROP VIEW MISSING_REGISTRATION_IDS;
+DROP VIEW MISSING_VOTE_IDS;
+
+ALTER TABLE meeting_data ADD COLUMN "agenda" INT[] NOT NULL DEFAULT '{}', ADD COLUMN "registrations" INT[] NOT NULL DEFAULT '{}';
+ALTER TABLE agenda_item ADD COLUMN "speeches" INT[] NOT NULL DEFAULT '{}', ADD COLUMN "voting" INT[] NOT NULL DEFAULT '{}';
+ALTER TABLE politician ADD COLUMN "offices" INT[] NOT NULL DEFAULT '{}';
+
+UPDATE meeting_data SET "agenda" = links.ids
+  FROM (SELECT meeting_id, ARRAY_AGG(agenda_item_id ORDER BY position) AS ids FROM meeting_agenda_item GROUP BY meeting_id) links
+  WHERE meeting_data.id = links.meeting_id;
+UPDATE meeting_data SET "registrations" = links.ids
+  FROM (SELECT meeting_id, ARRAY_AGG(registration_id ORDER BY position) AS ids FROM meeting_registration GROUP BY meeting_id) links
+  WHERE meeting_data.id = links.meeting_id;
+UPDATE agenda_item SET "voting" = links.ids
+  FROM (SELECT agenda_item_id, ARRAY_AGG(vote_id ORDER BY position) AS ids FROM agenda_vote GROUP BY agenda_item_id) links
+  WHERE agenda_item.id = links.agenda_item_id;
+UPDATE agenda_item SET "speeches" = links.ids
+  FROM (SELECT agenda_item_id, ARRAY_AGG(speech_id ORDER BY position) AS ids FROM agenda_speech GROUP BY agenda_item_id) links
+  WHERE agenda_item.id = links.agenda_item_id;
+UPDATE politician SET "offices" = links.ids
+  FROM (SELECT politician_id, parliament, ARRAY_AGG(office_id ORDER BY position) AS ids FROM politician_office GROUP BY politician_id, parliament) links
+  WHERE politician.id = links.politician_id AND politician.parliament = links.parliament;
+
+ALTER TABLE meeting_data ALTER COLUMN "agenda" DROP DEFAULT, ALTER COLUMN "registrations" DROP DEFAULT;
+ALTER TABLE agenda_item ALTER COLUMN "speeches" DROP DEFAULT, ALTER COLUMN "voting" DROP DEFAULT;
+ALTER TABLE politician ALTER COLUMN "offices" DROP DEFAULT;
+
+DROP TABLE politician_office;
+DROP TABLE agenda_speech;
+DROP TABLE agenda_vote;
+DROP TABLE meeting_registration;
+DROP TABLE meeting_agenda_item;
+
+CREATE VIEW MISSING_REGISTRATION_IDS AS (select rid from registration_data right join (select distinct unnest(registrations) as rid from meeting_data) md on rid = registration_data.id where registration_data.id is null);
+CREATE VIEW MISSING_VOTE_IDS AS (select vid from vote_data right join (select distinct unnest(voting) as vid from agenda_item) md on vid = vote_data.id where vote_data.id is null);
+
+CREATE OR REPLACE FUNCTION GETAGENDAS(session_id integer) RETURNS TABLE (agenda_id integer) AS $func$
+    #variable_conflict use_variable
+    BEGIN
+        RETURN QUERY (
+            SELECT UNNEST(meeting_data.agenda) AS agenda_id
+                FROM meeting_data, (
+                    SELECT meetings.id AS meetings_id
+                    FROM meetings
+                    WHERE meetings."session" = session_id) meetings_ids
+                WHERE meeting_data.id = meetings_ids.meetings_id);
+    END;
+$func$ LANGUAGE plpgsql;
+
+
+CREATE OR REPLACE FUNCTION GETVOTINGS(agenda_id integer) RETURNS TABLE (vote_id integer) AS $func$
+    #variable_conflict use_variable
+    BEGIN
+        RETURN QUERY (
+            SELECT UNNEST(agenda_item.voting) AS vote_id
+                FROM agenda_item
+                WHERE agenda_item.id = agenda_id);
+    END;
+$func$ LANGUAGE plpgsql;
@@ -0,0 +1,113 @@
+-- Your SQL goes here
+CREATE TABLE meeting_agenda_item (
+  "meeting_id" INT NOT NULL REFERENCES meeting_data ("id") ON DELETE CASCADE,
+  "agenda_item_id" INT NOT NULL REFERENCES agenda_item ("id") ON DELETE CASCADE,
+  "position" INT NOT NULL,
+  PRIMARY KEY ("meeting_id", "agenda_item_id")
+);
+
+CREATE TABLE meeting_registration (
+  "meeting_id" INT NOT NULL REFERENCES meeting_data ("id") ON DELETE CASCADE,
+  "registration_id" INT NOT NULL REFERENCES registration ("id") ON DELETE CASCADE,
+  "position" INT NOT NULL,
+  PRIMARY KEY ("meeting_id", "registration_id")
+);
+
+CREATE TABLE agenda_vote (
+  "agenda_item_id" INT NOT NULL REFERENCES agenda_item ("id") ON DELETE CASCADE,
+  "vote_id" INT NOT NULL REFERENCES vote ("id") ON DELETE CASCADE,
+  "position" INT NOT NULL,
+  PRIMARY KEY ("agenda_item_id", "vote_id")
+);
+
+CREATE TABLE agenda_speech (
+  "agenda_item_id" INT NOT NULL REFERENCES agenda_item ("id") ON DELETE CASCADE,
+  "speech_id" INT NOT NULL REFERENCES speech ("id") ON DELETE CASCADE,
+  "position" INT NOT NULL,
+  PRIMARY KEY ("agenda_item_id", "speech_id")
+);
+
+CREATE TABLE politician_office (
+  "politician_id" INT NOT NULL,
+  "parliament" INT NOT NULL,
+  "office_id" INT NOT NULL REFERENCES office ("id") ON DELETE CASCADE,
+  "position" INT NOT NULL,
+  PRIMARY KEY ("politician_id", "parliament", "office_id"),
+  FOREIGN KEY ("politician_id", "parliament") REFERENCES politician ("id", "parliament") ON DELETE CASCADE
+);
+
+CREATE INDEX meeting_agenda_item_agenda_item_id ON meeting_agenda_item ("agenda_item_id");
+CREATE INDEX meeting_registration_registration_id ON meeting_registration ("registration_id");
+CREATE INDEX agenda_vote_vote_id ON agenda_vote ("vote_id");
+CREATE INDEX agenda_speech_speech_id ON agenda_speech ("speech_id");
+CREATE INDEX politician_office_office_id ON politician_office ("office_id");
+
+-- Carry the array contents over, skipping IDs whose rows were never saved.
+INSERT INTO meeting_agenda_item ("meeting_id", "agenda_item_id", "position")
+  SELECT DISTINCT ON (meeting_data.id, item.id) meeting_data.id, item.id, item.position - 1
+  FROM meeting_data
+  CROSS JOIN LATERAL UNNEST(meeting_data.agenda) WITH ORDINALITY AS item("id", "position")
+  JOIN agenda_item ON agenda_item.id = item.id
+  ORDER BY meeting_data.id, item.id, item.position;
+
+INSERT INTO meeting_registration ("meeting_id", "registration_id", "position")
+  SELECT DISTINCT ON (meeting_data.id, item.id) meeting_data.id, item.id, item.position - 1
+  FROM meeting_data
+  CROSS JOIN LATERAL UNNEST(meeting_data.registrations) WITH ORDINALITY AS item("id", "position")
+  JOIN registration ON registration.id = item.id
+  ORDER BY meeting_data.id, item.id, item.position;
+
+INSERT INTO agenda_vote ("agenda_item_id", "vote_id", "position")
+  SELECT DISTINCT ON (agenda_item.id, item.id) agenda_item.id, item.id, item.position - 1
+  FROM agenda_item
+  CROSS JOIN LATERAL UNNEST(agenda_item.voting) WITH ORDINALITY AS item("id", "position")
+  JOIN vote ON vote.id = item.id
+  ORDER BY agenda_item.id, item.id, item.position;
+
+INSERT INTO agenda_speech ("agenda_item_id", "speech_id", "position")
+  SELECT DISTINCT ON (agenda_item.id, item.id) agenda_item.id, item.id, item.position - 1
+  FROM agenda_item
+  CROSS JOIN LATERAL UNNEST(agenda_item.speeches) WITH ORDINALITY AS item("id", "position")
+  JOIN speech ON speech.id = item.id
+  ORDER BY agenda_item.id, item.id, item.position;
+
+INSERT INTO politician_office ("politician_id", "parliament", "office_id", "position")
+  SELECT DISTINCT ON (politician.id, politician.parliament, item.id) politician.id, politician.parliament, item.id, item.position - 1
+  FROM politician
+  CROSS JOIN LATERAL UNNEST(politician.offices) WITH ORDINALITY AS item("id", "position")
+  JOIN office ON office.id = item.id
+  ORDER BY politician.id, politician.parliament, item.id, item.position;
+
+DROP VIEW MISSING_REGISTRATION_IDS;
+DROP VIEW MISSING_VOTE_IDS;
+
+ALTER TABLE meeting_data DROP COLUMN "agenda", DROP COLUMN "registrations";
+ALTER TABLE agenda_item DROP COLUMN "speeches", DROP COLUMN "voting";
+ALTER TABLE politician DROP COLUMN "offices";
+
+CREATE VIEW MISSING_REGISTRATION_IDS AS (select distinct registration_id as rid from meeting_registration left join registration_data on registration_id = registration_data.id where registration_data.id is null);
+CREATE VIEW MISSING_VOTE_IDS AS (select distinct vote_id as vid from agenda_vote left join vote_data on vote_id = vote_data.id where vote_data.id is null);
+
+CREATE OR REPLACE FUNCTION GETAGENDAS(session_id integer) RETURNS TABLE (agenda_id integer) AS $func$
+    #variable_conflict use_variable
+    BEGIN
+        RETURN QUERY (
+            SELECT meeting_agenda_item.agenda_item_id AS agenda_id
+                FROM meeting_agenda_item
+                JOIN meetings ON meetings.id = meeting_agenda_item.meeting_id
+                WHERE meetings."session" = session_id
+                ORDER BY meetings.id, meeting_agenda_item.position);
+    END;
+$func$ LANGUAGE plpgsql;
+
+
+CREATE OR REPLACE FUNCTION GETVOTINGS(agenda_id integer) RETURNS TABLE (vote_id integer) AS $func$
+    #variable_conflict use_variable
+    BEGIN
+        RETURN QUERY (
+            SELECT agenda_vote.vote_id AS vote_id
+                FROM agenda_vote
+                WHERE agenda_vote.agenda_item_id = agenda_id
+                ORDER BY agenda_vote.position);
+    END;
+$func$ LANGUAGE plpgsql;
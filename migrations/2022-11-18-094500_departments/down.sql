@@ -0,0 +1,13 @@
+-- This file should undo anything in `up.sql`
+DROP INDEX office_department_id;
+
+ALTER TABLE office DROP CONSTRAINT office_department_id_fkey,
+  ADD COLUMN "department_name" TEXT;
+
+UPDATE office SET "department_name" = department.name
+  FROM department
+  WHERE department.id = office.department_id;
+
+DROP TABLE department_member;
+DROP TABLE department;
+DROP TYPE PQ_DEPARTMENT_KIND;
@@ -0,0 +1,35 @@
+-- Your SQL goes here
+CREATE TYPE PQ_DEPARTMENT_KIND AS ENUM ('committee', 'commission', 'faction', 'parliamentary_group', 'other');
+
+CREATE TABLE department (
+  "id" INT PRIMARY KEY,
+  "name" TEXT NOT NULL,
+  "short_name" TEXT,
+  "kind" PQ_DEPARTMENT_KIND NOT NULL
+);
+
+CREATE TABLE department_member (
+  "department_id" INT NOT NULL REFERENCES department ("id") ON DELETE CASCADE,
+  "parliament" INT NOT NULL REFERENCES parliament ("id") ON DELETE CASCADE,
+  "position" INT NOT NULL,
+  "politician_id" INT NOT NULL,
+  "duties" TEXT,
+  "from" DATE,
+  "to" DATE,
+  PRIMARY KEY ("department_id", "parliament", "position")
+);
+
+CREATE INDEX department_member_politician_id ON department_member ("politician_id", "parliament");
+
+-- Departments known so far only from the offices of members, until their own endpoints are crawled.
+INSERT INTO department ("id", "name", "kind")
+  SELECT DISTINCT ON ("department_id") "department_id", COALESCE("department_name", ''),
+    CASE "department_type" WHEN 'group' THEN 'parliamentary_group' ELSE 'other' END::PQ_DEPARTMENT_KIND
+  FROM office
+  WHERE "department_id" IS NOT NULL
+  ORDER BY "department_id", "id" DESC;
+
+ALTER TABLE office DROP COLUMN "department_name",
+  ADD FOREIGN KEY ("department_id") REFERENCES department ("id") ON DELETE SET NULL;
+
+CREATE INDEX office_department_id ON office ("department_id");
@@ -0,0 +1,5 @@
+-- This file should undo anything in `up.sql`
+DROP VIEW VOTE_FACTION;
+DROP VIEW VOTE_DAY;
+DROP TABLE faction_membership;
+DROP TABLE faction;
@@ -0,0 +1,61 @@
+-- Your SQL goes here
+CREATE TABLE faction (
+  "id" INT PRIMARY KEY REFERENCES department ("id") ON DELETE CASCADE,
+  "parliament" INT NOT NULL REFERENCES parliament ("id") ON DELETE CASCADE,
+  "name" TEXT NOT NULL,
+  "short_name" TEXT
+);
+
+CREATE TABLE faction_membership (
+  "politician_id" INT NOT NULL,
+  "parliament" INT NOT NULL,
+  "faction_id" INT NOT NULL REFERENCES faction ("id") ON DELETE CASCADE,
+  "from" DATE NOT NULL,
+  "to" DATE,
+  "duties" TEXT,
+  PRIMARY KEY ("politician_id", "parliament", "faction_id", "from")
+);
+
+CREATE INDEX faction_parliament ON faction ("parliament");
+CREATE INDEX faction_membership_faction_id ON faction_membership ("faction_id");
+
+-- Day of each vote, from the vote itself or else from the meeting it was held in.
+CREATE VIEW VOTE_DAY AS (
+  SELECT vote.id AS vote_id, COALESCE(vote."from", (
+    SELECT meeting_data."from"
+    FROM agenda_vote
+    JOIN meeting_agenda_item ON meeting_agenda_item.agenda_item_id = agenda_vote.agenda_item_id
+    JOIN meeting_data ON meeting_data.id = meeting_agenda_item.meeting_id
+    WHERE agenda_vote.vote_id = vote.id
+    LIMIT 1))::DATE AS "day"
+  FROM vote);
+
+-- Faction each member belonged to on the day of each vote they took part in.
+CREATE VIEW VOTE_FACTION AS (
+  SELECT vote_data.id AS vote_id, vote_data.person_id, faction_membership.faction_id
+  FROM vote_data
+  JOIN VOTE_DAY ON VOTE_DAY.vote_id = vote_data.id
+  JOIN faction_membership ON faction_membership.politician_id = vote_data.person_id
+    AND faction_membership."from" <= VOTE_DAY."day"
+    AND (faction_membership."to" IS NULL OR faction_membership."to" >= VOTE_DAY."day"));
+
+-- Factions recorded so far only as offices of members.
+UPDATE department SET "kind" = 'faction'
+  WHERE "kind" = 'other' AND (LOWER("name") LIKE '%frakcij%' OR LOWER("name") LIKE 'mišri seimo narių grupė%');
+
+INSERT INTO faction ("id", "parliament", "name", "short_name")
+  SELECT DISTINCT ON (department.id) department.id, politician_office.parliament, department.name, department.short_name
+  FROM department
+  JOIN office ON office.department_id = department.id
+  JOIN politician_office ON politician_office.office_id = office.id
+  WHERE department.kind = 'faction'
+  ORDER BY department.id, politician_office.parliament DESC;
+
+INSERT INTO faction_membership ("politician_id", "parliament", "faction_id", "from", "to", "duties")
+  SELECT DISTINCT ON (politician_office.politician_id, politician_office.parliament, office.department_id, office."from")
+    politician_office.politician_id, politician_office.parliament, office.department_id, office."from", office."to", office.duties
+  FROM politician_office
+  JOIN office ON office.id = politician_office.office_id
+  JOIN faction ON faction.id = office.department_id
+  WHERE office."from" IS NOT NULL
+  ORDER BY politician_office.politician_id, politician_office.parliament, office.department_id, office."from", office.id DESC;
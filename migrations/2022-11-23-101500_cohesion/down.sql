@@ -0,0 +1,7 @@
+-- This file should undo anything in `up.sql`
+DROP TABLE member_cohesion;
+DROP TABLE faction_cohesion;
+DROP VIEW FACTION_TALLY;
+DROP VIEW BALLOT_FACTION;
+DROP VIEW VOTE_SCOPE;
+DROP TYPE PQ_PERIOD;
@@ -0,0 +1,78 @@
+-- Your SQL goes here
+CREATE TYPE PQ_PERIOD AS ENUM ('parliament', 'session');
+
+-- Session and parliament each vote was held in, with its day.
+CREATE VIEW VOTE_SCOPE AS (
+  SELECT DISTINCT ON (agenda_vote.vote_id)
+    agenda_vote.vote_id, VOTE_DAY."day", sessions.id AS session_id, sessions.parliament AS parliament_id
+  FROM agenda_vote
+  JOIN meeting_agenda_item ON meeting_agenda_item.agenda_item_id = agenda_vote.agenda_item_id
+  JOIN meetings ON meetings.id = meeting_agenda_item.meeting_id
+  JOIN sessions ON sessions.id = meetings."session"
+  JOIN VOTE_DAY ON VOTE_DAY.vote_id = agenda_vote.vote_id
+  ORDER BY agenda_vote.vote_id, meetings.id);
+
+-- Ballots cast by members of the parliament, with the faction of the member on the day of the vote.
+-- A member switching factions that day counts for the newer one.
+CREATE VIEW BALLOT_FACTION AS (
+  SELECT DISTINCT ON (vote_data.id, vote_data.person_id)
+    vote_data.id AS vote_id, vote_data.person_id, vote_data.vote, faction_membership.faction_id
+  FROM vote_data
+  JOIN VOTE_SCOPE ON VOTE_SCOPE.vote_id = vote_data.id
+  JOIN politician ON politician.id = vote_data.person_id AND politician.parliament = VOTE_SCOPE.parliament_id
+  JOIN faction_membership ON faction_membership.politician_id = vote_data.person_id
+    AND faction_membership.parliament = VOTE_SCOPE.parliament_id
+    AND faction_membership."from" <= VOTE_SCOPE."day"
+    AND (faction_membership."to" IS NULL OR faction_membership."to" >= VOTE_SCOPE."day")
+  WHERE vote_data.vote IS NOT NULL
+  ORDER BY vote_data.id, vote_data.person_id, faction_membership."from" DESC);
+
+-- Ballots of each faction in each vote with its Rice index |for - against| / (for + against)
+-- and agreement index (largest bloc - half of the others) / ballots, both 1 for a united faction.
+-- Ballots outside the largest bloc are defections, nobody defects from a tie.
+CREATE VIEW FACTION_TALLY AS (
+  SELECT tally.*,
+    (CASE WHEN tally.for_count < tally.majority THEN tally.for_count ELSE 0 END
+      + CASE WHEN tally.against_count < tally.majority THEN tally.against_count ELSE 0 END
+      + CASE WHEN tally.abstain_count < tally.majority THEN tally.abstain_count ELSE 0 END) AS defections,
+    ABS(tally.for_count - tally.against_count)::FLOAT8 / NULLIF(tally.for_count + tally.against_count, 0) AS rice,
+    (tally.majority - 0.5 * (tally.ballots - tally.majority))::FLOAT8 / tally.ballots AS agreement
+  FROM (
+    SELECT counts.*,
+      counts.for_count + counts.against_count + counts.abstain_count AS ballots,
+      GREATEST(counts.for_count, counts.against_count, counts.abstain_count) AS majority
+    FROM (
+      SELECT BALLOT_FACTION.vote_id, BALLOT_FACTION.faction_id,
+        COUNT(*) FILTER (WHERE BALLOT_FACTION.vote = 'for') AS for_count,
+        COUNT(*) FILTER (WHERE BALLOT_FACTION.vote = 'against') AS against_count,
+        COUNT(*) FILTER (WHERE BALLOT_FACTION.vote = 'abstain') AS abstain_count
+      FROM BALLOT_FACTION
+      GROUP BY BALLOT_FACTION.vote_id, BALLOT_FACTION.faction_id) counts) tally);
+
+-- Summaries per parliament and per session, rebuilt after each crawl.
+CREATE TABLE faction_cohesion (
+  "period" PQ_PERIOD NOT NULL,
+  "period_id" INT NOT NULL,
+  "faction_id" INT NOT NULL REFERENCES faction ("id") ON DELETE CASCADE,
+  "votes" BIGINT NOT NULL,
+  "ballots" BIGINT NOT NULL,
+  "rice" FLOAT8,
+  "agreement" FLOAT8 NOT NULL,
+  "defection_rate" FLOAT8 NOT NULL,
+  PRIMARY KEY ("period", "period_id", "faction_id")
+);
+
+CREATE TABLE member_cohesion (
+  "period" PQ_PERIOD NOT NULL,
+  "period_id" INT NOT NULL,
+  "politician_id" INT NOT NULL,
+  "faction_id" INT NOT NULL REFERENCES faction ("id") ON DELETE CASCADE,
+  "votes" BIGINT NOT NULL,
+  "defections" BIGINT NOT NULL,
+  "defection_rate" FLOAT8 NOT NULL,
+  "rice" FLOAT8,
+  "agreement" FLOAT8 NOT NULL,
+  PRIMARY KEY ("period", "period_id", "politician_id", "faction_id")
+);
+
+CREATE INDEX member_cohesion_politician_id ON member_cohesion ("politician_id");
@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+DROP TABLE cutting_line;
+DROP TABLE ideal_point;
@@ -0,0 +1,33 @@
+-- Your SQL goes here
+-- Coordinates of each member from scaling the votes of a parliament or a session in one or two dimensions.
+CREATE TABLE ideal_point (
+  "period" PQ_PERIOD NOT NULL,
+  "period_id" INT NOT NULL,
+  "dimensions" INT NOT NULL,
+  "politician_id" INT NOT NULL,
+  "votes" INT NOT NULL,
+  "coord1" FLOAT8 NOT NULL,
+  "coord2" FLOAT8,
+  "se1" FLOAT8 NOT NULL,
+  "se2" FLOAT8,
+  PRIMARY KEY ("period", "period_id", "dimensions", "politician_id")
+);
+
+-- Line splitting the members expected to vote for from those expected to vote against, at signed distance
+-- "offset" from the origin along the direction "angle" (degrees), with the share of ballots it classifies correctly.
+CREATE TABLE cutting_line (
+  "period" PQ_PERIOD NOT NULL,
+  "period_id" INT NOT NULL,
+  "dimensions" INT NOT NULL,
+  "vote_id" INT NOT NULL REFERENCES vote ("id") ON DELETE CASCADE,
+  "alpha" FLOAT8 NOT NULL,
+  "beta1" FLOAT8 NOT NULL,
+  "beta2" FLOAT8,
+  "offset" FLOAT8 NOT NULL,
+  "angle" FLOAT8 NOT NULL,
+  "correct" FLOAT8 NOT NULL,
+  PRIMARY KEY ("period", "period_id", "dimensions", "vote_id")
+);
+
+CREATE INDEX ideal_point_politician_id ON ideal_point ("politician_id");
+CREATE INDEX cutting_line_vote_id ON cutting_line ("vote_id");
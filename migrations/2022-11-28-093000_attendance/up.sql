@@ -0,0 +1,38 @@
+-- Your SQL goes here
+-- Registration and voting of each member in each sitting held during the mandate of the member,
+-- for the sittings whose registration results were downloaded.
+CREATE TABLE sitting_attendance (
+  "meeting_id" INT NOT NULL REFERENCES meetings ("id") ON DELETE CASCADE,
+  "politician_id" INT NOT NULL,
+  "day" DATE,
+  "registrations" BIGINT NOT NULL,
+  "registered" BIGINT NOT NULL,
+  "votes" BIGINT NOT NULL,
+  "votes_cast" BIGINT NOT NULL,
+  "votes_while_registered" BIGINT NOT NULL,
+  "votes_cast_while_registered" BIGINT NOT NULL,
+  PRIMARY KEY ("meeting_id", "politician_id")
+);
+
+-- The same summed over a parliament or a session.
+CREATE TABLE member_attendance (
+  "period" PQ_PERIOD NOT NULL,
+  "period_id" INT NOT NULL,
+  "politician_id" INT NOT NULL,
+  "sittings" BIGINT NOT NULL,
+  "sittings_attended" BIGINT NOT NULL,
+  "days" BIGINT NOT NULL,
+  "days_absent" BIGINT NOT NULL,
+  "registrations" BIGINT NOT NULL,
+  "registered" BIGINT NOT NULL,
+  "registration_rate" FLOAT8,
+  "votes" BIGINT NOT NULL,
+  "votes_cast" BIGINT NOT NULL,
+  "votes_while_registered" BIGINT NOT NULL,
+  "votes_cast_while_registered" BIGINT NOT NULL,
+  "voting_share" FLOAT8,
+  PRIMARY KEY ("period", "period_id", "politician_id")
+);
+
+CREATE INDEX sitting_attendance_politician_id ON sitting_attendance ("politician_id");
+CREATE INDEX member_attendance_politician_id ON member_attendance ("politician_id");
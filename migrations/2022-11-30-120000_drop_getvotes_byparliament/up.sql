@@ -0,0 +1,3 @@
+-- Your SQL goes here
+-- Replaced by analysis::member_votes, which takes its scope and filters as parameters.
+DROP FUNCTION GETVOTES_BYPARLIAMENT;
@@ -0,0 +1,5 @@
+-- This file should undo anything in `up.sql`
+DROP VIEW EXPORT_AGENDA;
+DROP VIEW EXPORT_MEMBER_REGISTRATION;
+DROP VIEW EXPORT_VOTE;
+DROP VIEW EXPORT_MEMBER_VOTE;
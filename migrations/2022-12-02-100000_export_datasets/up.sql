@@ -0,0 +1,76 @@
+-- Your SQL goes here
+-- Denormalised datasets written by the `export` command next to the tables, each with the parliament it belongs to.
+
+-- Ballot of each member in each vote, with the name of the member and the faction of the member on the day of the vote.
+-- A member switching factions that day counts for the newer one, as in BALLOT_FACTION.
+CREATE VIEW EXPORT_MEMBER_VOTE AS (
+  SELECT vote_data.id AS vote_id, VOTE_SCOPE."day", VOTE_SCOPE.session_id, VOTE_SCOPE.parliament_id,
+    vote_data.person_id AS politician_id, politician.name, politician.surname, politician.party,
+    faction.id AS faction_id, COALESCE(faction.short_name, faction.name) AS faction, vote_data.vote
+  FROM vote_data
+  JOIN VOTE_SCOPE ON VOTE_SCOPE.vote_id = vote_data.id
+  LEFT JOIN politician ON politician.id = vote_data.person_id AND politician.parliament = VOTE_SCOPE.parliament_id
+  LEFT JOIN LATERAL (
+    SELECT faction_membership.faction_id
+    FROM faction_membership
+    WHERE faction_membership.politician_id = vote_data.person_id
+      AND faction_membership.parliament = VOTE_SCOPE.parliament_id
+      AND faction_membership."from" <= VOTE_SCOPE."day"
+      AND (faction_membership."to" IS NULL OR faction_membership."to" >= VOTE_SCOPE."day")
+    ORDER BY faction_membership."from" DESC
+    LIMIT 1) membership ON TRUE
+  LEFT JOIN faction ON faction.id = membership.faction_id);
+
+-- Each vote with the agenda item and sitting it was held in and its ballots counted.
+CREATE VIEW EXPORT_VOTE AS (
+  SELECT DISTINCT ON (vote.id)
+    vote.id AS vote_id, VOTE_SCOPE."day", VOTE_SCOPE.session_id, VOTE_SCOPE.parliament_id,
+    meeting_agenda_item.meeting_id, agenda_vote.agenda_item_id, agenda_item.nr, agenda_item.name AS agenda_item,
+    vote.summary, vote.result, vote."from", vote."to",
+    (SELECT COUNT(*) FROM vote_data WHERE vote_data.id = vote.id AND vote_data.vote = 'for') AS for_count,
+    (SELECT COUNT(*) FROM vote_data WHERE vote_data.id = vote.id AND vote_data.vote = 'against') AS against_count,
+    (SELECT COUNT(*) FROM vote_data WHERE vote_data.id = vote.id AND vote_data.vote = 'abstain') AS abstain_count,
+    (SELECT COUNT(*) FROM vote_data WHERE vote_data.id = vote.id AND vote_data.vote IS NULL) AS none_count
+  FROM vote
+  JOIN VOTE_SCOPE ON VOTE_SCOPE.vote_id = vote.id
+  JOIN agenda_vote ON agenda_vote.vote_id = vote.id
+  JOIN meeting_agenda_item ON meeting_agenda_item.agenda_item_id = agenda_vote.agenda_item_id
+  JOIN agenda_item ON agenda_item.id = agenda_vote.agenda_item_id
+  ORDER BY vote.id, meeting_agenda_item.meeting_id);
+
+-- Whether each member registered at each registration, with the sitting it was held in.
+CREATE VIEW EXPORT_MEMBER_REGISTRATION AS (
+  SELECT DISTINCT ON (registration_data.id, registration_data.person_id)
+    registration_data.id AS registration_id, meetings.id AS meeting_id,
+    COALESCE(registration."from", meeting_data."from", meetings."from")::DATE AS "day",
+    sessions.id AS session_id, sessions.parliament AS parliament_id,
+    registration_data.person_id AS politician_id, politician.name, politician.surname, politician.party,
+    registration_data.registered
+  FROM registration_data
+  JOIN registration ON registration.id = registration_data.id
+  JOIN meeting_registration ON meeting_registration.registration_id = registration_data.id
+  JOIN meetings ON meetings.id = meeting_registration.meeting_id
+  LEFT JOIN meeting_data ON meeting_data.id = meetings.id
+  JOIN sessions ON sessions.id = meetings."session"
+  LEFT JOIN politician ON politician.id = registration_data.person_id AND politician.parliament = sessions.parliament
+  ORDER BY registration_data.id, registration_data.person_id, meetings.id);
+
+-- Agenda of each sitting, with the outcome and the number of votes and speeches on each item.
+CREATE VIEW EXPORT_AGENDA AS (
+  SELECT meeting_agenda_item.meeting_id, meeting_agenda_item.position,
+    COALESCE(meeting_data."from", meetings."from")::DATE AS "day", sessions.id AS session_id, sessions.parliament AS parliament_id,
+    agenda_item.id AS agenda_item_id, agenda_item.nr, agenda_item.name, agenda_item.agenda_type, agenda_item.document_key,
+    agenda_item."from", agenda_item."to", agenda_outcome.outcome, agenda_outcome.decision,
+    (SELECT COUNT(*) FROM agenda_vote WHERE agenda_vote.agenda_item_id = agenda_item.id) AS votes,
+    (SELECT COUNT(*) FROM agenda_speech WHERE agenda_speech.agenda_item_id = agenda_item.id) AS speeches
+  FROM meeting_agenda_item
+  JOIN agenda_item ON agenda_item.id = meeting_agenda_item.agenda_item_id
+  JOIN meetings ON meetings.id = meeting_agenda_item.meeting_id
+  LEFT JOIN meeting_data ON meeting_data.id = meetings.id
+  JOIN sessions ON sessions.id = meetings."session"
+  LEFT JOIN LATERAL (
+    SELECT agenda_outcome.outcome, agenda_outcome.decision
+    FROM agenda_outcome
+    WHERE agenda_outcome.meeting_id = meeting_agenda_item.meeting_id AND agenda_outcome.agenda_item_id = agenda_item.id
+    ORDER BY agenda_outcome.position
+    LIMIT 1) agenda_outcome ON TRUE);
@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+-- Nothing to undo, the counts of earlier successful fetches are not kept.
+SELECT 1;
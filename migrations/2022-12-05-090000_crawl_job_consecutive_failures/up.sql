@@ -0,0 +1,3 @@
+-- Your SQL goes here
+-- `attempts` counts consecutive failures and is reset by a successful fetch.
+UPDATE crawl_job SET "attempts" = 0 WHERE "status" <> 'failed';
@@ -0,0 +1,6 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE speech_text
+  DROP CONSTRAINT speech_text_meeting_id_fkey,
+  DROP CONSTRAINT speech_text_speech_id_fkey,
+  DROP CONSTRAINT speech_text_person_id_parliament_fkey,
+  DROP COLUMN "parliament";
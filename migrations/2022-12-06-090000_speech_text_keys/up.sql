@@ -0,0 +1,20 @@
+-- Your SQL goes here
+-- Turns left over from meetings and speeches that no longer exist.
+DELETE FROM speech_text WHERE "meeting_id" NOT IN (SELECT "id" FROM meetings);
+UPDATE speech_text SET "speech_id" = NULL WHERE "speech_id" NOT IN (SELECT "id" FROM speech);
+
+-- Members are stored per parliament, so the speaker is keyed by the parliament of the meeting.
+ALTER TABLE speech_text ADD COLUMN "parliament" INT;
+UPDATE speech_text SET "parliament" = sessions.parliament
+  FROM meetings
+  JOIN sessions ON sessions.id = meetings."session"
+  WHERE meetings.id = speech_text.meeting_id;
+ALTER TABLE speech_text ALTER COLUMN "parliament" SET NOT NULL;
+UPDATE speech_text SET "person_id" = NULL
+  WHERE "person_id" IS NOT NULL
+    AND NOT EXISTS (SELECT 1 FROM politician WHERE politician.id = speech_text.person_id AND politician.parliament = speech_text.parliament);
+
+ALTER TABLE speech_text
+  ADD FOREIGN KEY ("meeting_id") REFERENCES meetings ("id") ON DELETE CASCADE,
+  ADD FOREIGN KEY ("speech_id") REFERENCES speech ("id") ON DELETE SET NULL,
+  ADD FOREIGN KEY ("person_id", "parliament") REFERENCES politician ("id", "parliament");
@@ -0,0 +1,27 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE agenda_outcome
+  ADD COLUMN "presenters" TEXT[] NOT NULL DEFAULT '{}',
+  ADD COLUMN "document_numbers" TEXT[] NOT NULL DEFAULT '{}';
+
+UPDATE agenda_outcome SET "presenters" = presenters.names
+  FROM (
+    SELECT "meeting_id", "outcome_position", ARRAY_AGG("name" ORDER BY "position") AS names
+    FROM agenda_outcome_presenter
+    GROUP BY "meeting_id", "outcome_position") presenters
+  WHERE presenters.meeting_id = agenda_outcome.meeting_id AND presenters.outcome_position = agenda_outcome.position;
+
+UPDATE agenda_outcome SET "document_numbers" = documents.numbers
+  FROM (
+    SELECT "meeting_id", "outcome_position", ARRAY_AGG("document_number" ORDER BY "position") AS numbers
+    FROM agenda_outcome_document
+    GROUP BY "meeting_id", "outcome_position") documents
+  WHERE documents.meeting_id = agenda_outcome.meeting_id AND documents.outcome_position = agenda_outcome.position;
+
+ALTER TABLE agenda_outcome ALTER COLUMN "presenters" DROP DEFAULT, ALTER COLUMN "document_numbers" DROP DEFAULT;
+
+DROP TABLE agenda_outcome_document;
+DROP TABLE agenda_outcome_presenter;
+
+ALTER TABLE agenda_outcome
+  DROP CONSTRAINT agenda_outcome_meeting_id_fkey,
+  DROP CONSTRAINT agenda_outcome_agenda_item_id_fkey;
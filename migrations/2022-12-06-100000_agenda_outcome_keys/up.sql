@@ -0,0 +1,43 @@
+-- Your SQL goes here
+-- Outcomes left over from meetings and agenda items that no longer exist.
+DELETE FROM agenda_outcome WHERE "meeting_id" NOT IN (SELECT "id" FROM meetings);
+UPDATE agenda_outcome SET "agenda_item_id" = NULL WHERE "agenda_item_id" NOT IN (SELECT "id" FROM agenda_item);
+
+ALTER TABLE agenda_outcome
+  ADD FOREIGN KEY ("meeting_id") REFERENCES meetings ("id") ON DELETE CASCADE,
+  ADD FOREIGN KEY ("agenda_item_id") REFERENCES agenda_item ("id") ON DELETE SET NULL;
+
+-- Presenters and documents of each question, in the order the protocol names them.
+CREATE TABLE agenda_outcome_presenter (
+  "meeting_id" INT NOT NULL,
+  "outcome_position" INT NOT NULL,
+  "position" INT NOT NULL,
+  "name" TEXT NOT NULL,
+  PRIMARY KEY ("meeting_id", "outcome_position", "position"),
+  FOREIGN KEY ("meeting_id", "outcome_position") REFERENCES agenda_outcome ("meeting_id", "position") ON DELETE CASCADE
+);
+
+CREATE TABLE agenda_outcome_document (
+  "meeting_id" INT NOT NULL,
+  "outcome_position" INT NOT NULL,
+  "position" INT NOT NULL,
+  "document_number" TEXT NOT NULL,
+  PRIMARY KEY ("meeting_id", "outcome_position", "position"),
+  FOREIGN KEY ("meeting_id", "outcome_position") REFERENCES agenda_outcome ("meeting_id", "position") ON DELETE CASCADE
+);
+
+CREATE INDEX agenda_outcome_document_document_number ON agenda_outcome_document ("document_number");
+
+INSERT INTO agenda_outcome_presenter ("meeting_id", "outcome_position", "position", "name")
+  SELECT agenda_outcome.meeting_id, agenda_outcome.position, item.position - 1, item.name
+  FROM agenda_outcome
+  CROSS JOIN LATERAL UNNEST(agenda_outcome.presenters) WITH ORDINALITY AS item("name", "position")
+  WHERE item.name IS NOT NULL;
+
+INSERT INTO agenda_outcome_document ("meeting_id", "outcome_position", "position", "document_number")
+  SELECT agenda_outcome.meeting_id, agenda_outcome.position, item.position - 1, item.document_number
+  FROM agenda_outcome
+  CROSS JOIN LATERAL UNNEST(agenda_outcome.document_numbers) WITH ORDINALITY AS item("document_number", "position")
+  WHERE item.document_number IS NOT NULL;
+
+ALTER TABLE agenda_outcome DROP COLUMN "presenters", DROP COLUMN "document_numbers";
@@ -0,0 +1,8 @@
+-- This file should undo anything in `up.sql`
+CREATE OR REPLACE VIEW VOTE_FACTION AS (
+  SELECT vote_data.id AS vote_id, vote_data.person_id, faction_membership.faction_id
+  FROM vote_data
+  JOIN VOTE_DAY ON VOTE_DAY.vote_id = vote_data.id
+  JOIN faction_membership ON faction_membership.politician_id = vote_data.person_id
+    AND faction_membership."from" <= VOTE_DAY."day"
+    AND (faction_membership."to" IS NULL OR faction_membership."to" >= VOTE_DAY."day"));
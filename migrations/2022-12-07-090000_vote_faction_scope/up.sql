@@ -0,0 +1,14 @@
+-- Your SQL goes here
+-- Faction each member of the parliament belonged to on the day of each vote they took part in.
+-- A member switching factions that day counts for the newer one.
+CREATE OR REPLACE VIEW VOTE_FACTION AS (
+  SELECT DISTINCT ON (vote_data.id, vote_data.person_id)
+    vote_data.id AS vote_id, vote_data.person_id, faction_membership.faction_id
+  FROM vote_data
+  JOIN VOTE_SCOPE ON VOTE_SCOPE.vote_id = vote_data.id
+  JOIN politician ON politician.id = vote_data.person_id AND politician.parliament = VOTE_SCOPE.parliament_id
+  JOIN faction_membership ON faction_membership.politician_id = vote_data.person_id
+    AND faction_membership.parliament = VOTE_SCOPE.parliament_id
+    AND faction_membership."from" <= VOTE_SCOPE."day"
+    AND (faction_membership."to" IS NULL OR faction_membership."to" >= VOTE_SCOPE."day")
+  ORDER BY vote_data.id, vote_data.person_id, faction_membership."from" DESC);
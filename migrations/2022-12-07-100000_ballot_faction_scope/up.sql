@@ -0,0 +1,7 @@
+-- Your SQL goes here
+-- Ballots cast by members of the parliament, with the faction of the member on the day of the vote as in VOTE_FACTION.
+CREATE OR REPLACE VIEW BALLOT_FACTION AS (
+  SELECT VOTE_FACTION.vote_id, VOTE_FACTION.person_id, vote_data.vote, VOTE_FACTION.faction_id
+  FROM VOTE_FACTION
+  JOIN vote_data ON vote_data.id = VOTE_FACTION.vote_id AND vote_data.person_id = VOTE_FACTION.person_id
+  WHERE vote_data.vote IS NOT NULL);
@@ -0,0 +1,18 @@
+-- This file should undo anything in `up.sql`
+CREATE OR REPLACE VIEW EXPORT_MEMBER_VOTE AS (
+  SELECT vote_data.id AS vote_id, VOTE_SCOPE."day", VOTE_SCOPE.session_id, VOTE_SCOPE.parliament_id,
+    vote_data.person_id AS politician_id, politician.name, politician.surname, politician.party,
+    faction.id AS faction_id, COALESCE(faction.short_name, faction.name) AS faction, vote_data.vote
+  FROM vote_data
+  JOIN VOTE_SCOPE ON VOTE_SCOPE.vote_id = vote_data.id
+  LEFT JOIN politician ON politician.id = vote_data.person_id AND politician.parliament = VOTE_SCOPE.parliament_id
+  LEFT JOIN LATERAL (
+    SELECT faction_membership.faction_id
+    FROM faction_membership
+    WHERE faction_membership.politician_id = vote_data.person_id
+      AND faction_membership.parliament = VOTE_SCOPE.parliament_id
+      AND faction_membership."from" <= VOTE_SCOPE."day"
+      AND (faction_membership."to" IS NULL OR faction_membership."to" >= VOTE_SCOPE."day")
+    ORDER BY faction_membership."from" DESC
+    LIMIT 1) membership ON TRUE
+  LEFT JOIN faction ON faction.id = membership.faction_id);
@@ -0,0 +1,11 @@
+-- Your SQL goes here
+-- Ballot of each member in each vote, with the faction of the member on the day of the vote as in VOTE_FACTION.
+CREATE OR REPLACE VIEW EXPORT_MEMBER_VOTE AS (
+  SELECT vote_data.id AS vote_id, VOTE_SCOPE."day", VOTE_SCOPE.session_id, VOTE_SCOPE.parliament_id,
+    vote_data.person_id AS politician_id, politician.name, politician.surname, politician.party,
+    faction.id AS faction_id, COALESCE(faction.short_name, faction.name) AS faction, vote_data.vote
+  FROM vote_data
+  JOIN VOTE_SCOPE ON VOTE_SCOPE.vote_id = vote_data.id
+  LEFT JOIN politician ON politician.id = vote_data.person_id AND politician.parliament = VOTE_SCOPE.parliament_id
+  LEFT JOIN VOTE_FACTION ON VOTE_FACTION.vote_id = vote_data.id AND VOTE_FACTION.person_id = vote_data.person_id
+  LEFT JOIN faction ON faction.id = VOTE_FACTION.faction_id);
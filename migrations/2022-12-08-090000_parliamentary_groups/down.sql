@@ -0,0 +1,13 @@
+-- This file should undo anything in `up.sql`
+ALTER TYPE PQ_DEPARTMENT_KIND RENAME TO PQ_DEPARTMENT_KIND_NEW;
+CREATE TYPE PQ_DEPARTMENT_KIND AS ENUM ('committee', 'commission', 'faction', 'parliamentary_group', 'other');
+ALTER TABLE department ALTER COLUMN "kind" TYPE PQ_DEPARTMENT_KIND USING "kind"::TEXT::PQ_DEPARTMENT_KIND;
+DROP TYPE PQ_DEPARTMENT_KIND_NEW;
+
+INSERT INTO department ("id", "name", "kind")
+  SELECT "id", "name", 'parliamentary_group' FROM parliamentary_group
+  ON CONFLICT ("id") DO NOTHING;
+UPDATE office SET "department_id" = "group_id" WHERE "group_id" IS NOT NULL;
+
+ALTER TABLE office DROP COLUMN "group_id";
+DROP TABLE parliamentary_group;
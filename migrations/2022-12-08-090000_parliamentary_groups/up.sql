@@ -0,0 +1,28 @@
+-- Your SQL goes here
+-- Parliamentary groups are numbered apart from departments, so they get a table of their own.
+CREATE TABLE parliamentary_group (
+  "id" INT PRIMARY KEY,
+  "name" TEXT NOT NULL
+);
+
+-- A group whose department row was taken by a unit with the same ID gets its name back on the next member crawl.
+INSERT INTO parliamentary_group ("id", "name")
+  SELECT DISTINCT ON (office.department_id) office.department_id, COALESCE(department.name, '')
+  FROM office
+  LEFT JOIN department ON department.id = office.department_id AND department."kind" = 'parliamentary_group'
+  WHERE office.department_type = 'group' AND office.department_id IS NOT NULL
+  ORDER BY office.department_id, department.name IS NULL;
+
+ALTER TABLE office ADD COLUMN "group_id" INT REFERENCES parliamentary_group ("id") ON DELETE SET NULL;
+UPDATE office SET "group_id" = "department_id", "department_id" = NULL WHERE "department_type" = 'group';
+CREATE INDEX office_group_id ON office ("group_id");
+
+-- Rows registered by a group that other offices link to belong to those offices' unit.
+UPDATE department SET "kind" = 'other'
+  WHERE "kind" = 'parliamentary_group' AND EXISTS (SELECT 1 FROM office WHERE office.department_id = department.id);
+DELETE FROM department WHERE "kind" = 'parliamentary_group';
+
+ALTER TYPE PQ_DEPARTMENT_KIND RENAME TO PQ_DEPARTMENT_KIND_OLD;
+CREATE TYPE PQ_DEPARTMENT_KIND AS ENUM ('committee', 'commission', 'faction', 'other');
+ALTER TABLE department ALTER COLUMN "kind" TYPE PQ_DEPARTMENT_KIND USING "kind"::TEXT::PQ_DEPARTMENT_KIND;
+DROP TYPE PQ_DEPARTMENT_KIND_OLD;
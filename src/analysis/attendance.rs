@@ -0,0 +1,200 @@
+use chrono::NaiveDate;
+use diesel::prelude::*;
+use diesel::sql_types::{BigInt, Date, Double, Int4, Nullable};
+use log::info;
+use serde::Serialize;
+
+use crate::analysis::cohesion::{Period, PeriodKind};
+use crate::database::context::Context;
+use crate::error::Result;
+use crate::export::{write_rows, ExportOptions};
+use crate::schema::{member_attendance, sitting_attendance};
+
+/// Registration and voting of a member in one sitting.
+///
+/// A vote counts as held while registered when the member was registered at the last
+/// registration of the sitting before the vote, or at the last one when times are unknown.
+#[derive(Debug, Clone, Queryable, Serialize)]
+pub struct SittingAttendance {
+    pub meeting_id: i32,
+    pub politician_id: i32,
+    pub day: Option<NaiveDate>,
+    pub registrations: i64,
+    pub registered: i64,
+    pub votes: i64,
+    pub votes_cast: i64,
+    pub votes_while_registered: i64,
+    pub votes_cast_while_registered: i64,
+}
+
+/// Attendance of a member over a period, counting only the sittings held during the mandate of the member.
+///
+/// `days_absent` are sitting days on which the member registered at none of the registrations,
+/// `voting_share` is the share of the votes held while registered in which the member cast a ballot.
+#[derive(Debug, Clone, Queryable, QueryableByName, Serialize)]
+pub struct MemberAttendance {
+    #[diesel(sql_type = Int4)]
+    pub politician_id: i32,
+    #[diesel(sql_type = BigInt)]
+    pub sittings: i64,
+    #[diesel(sql_type = BigInt)]
+    pub sittings_attended: i64,
+    #[diesel(sql_type = BigInt)]
+    pub days: i64,
+    #[diesel(sql_type = BigInt)]
+    pub days_absent: i64,
+    #[diesel(sql_type = BigInt)]
+    pub registrations: i64,
+    #[diesel(sql_type = BigInt)]
+    pub registered: i64,
+    #[diesel(sql_type = Nullable<Double>)]
+    pub registration_rate: Option<f64>,
+    #[diesel(sql_type = BigInt)]
+    pub votes: i64,
+    #[diesel(sql_type = BigInt)]
+    pub votes_cast: i64,
+    #[diesel(sql_type = BigInt)]
+    pub votes_while_registered: i64,
+    #[diesel(sql_type = BigInt)]
+    pub votes_cast_while_registered: i64,
+    #[diesel(sql_type = Nullable<Double>)]
+    pub voting_share: Option<f64>,
+}
+
+const SITTING_ATTENDANCE_QUERY: &str = r#"
+INSERT INTO sitting_attendance
+WITH checks AS (
+    SELECT meeting_registration.meeting_id, registration.id AS registration_id, registration."from"
+    FROM meeting_registration
+    JOIN registration ON registration.id = meeting_registration.registration_id
+    WHERE EXISTS (SELECT 1 FROM registration_data WHERE registration_data.id = registration.id)
+), votes AS (
+    SELECT DISTINCT meeting_agenda_item.meeting_id, vote.id AS vote_id, vote."from"
+    FROM meeting_agenda_item
+    JOIN agenda_vote ON agenda_vote.agenda_item_id = meeting_agenda_item.agenda_item_id
+    JOIN vote ON vote.id = agenda_vote.vote_id
+    WHERE EXISTS (SELECT 1 FROM vote_data WHERE vote_data.id = vote.id)
+), members AS (
+    SELECT meetings.id AS meeting_id, politician.id AS politician_id, COALESCE(meeting_data."from", meetings."from")::DATE AS "day"
+    FROM meetings
+    JOIN sessions ON sessions.id = meetings."session"
+    LEFT JOIN meeting_data ON meeting_data.id = meetings.id
+    JOIN politician ON politician.parliament = sessions.parliament
+        AND (politician."from" IS NULL OR politician."from" <= COALESCE(meeting_data."from", meetings."from")::DATE)
+        AND (politician."to" IS NULL OR politician."to" >= COALESCE(meeting_data."from", meetings."from")::DATE)
+    WHERE EXISTS (SELECT 1 FROM checks WHERE checks.meeting_id = meetings.id)
+)
+SELECT
+    members.meeting_id,
+    members.politician_id,
+    members."day",
+    (SELECT COUNT(*) FROM checks WHERE checks.meeting_id = members.meeting_id) AS registrations,
+    (SELECT COUNT(*) FROM checks
+        JOIN registration_data ON registration_data.id = checks.registration_id AND registration_data.person_id = members.politician_id
+        WHERE checks.meeting_id = members.meeting_id AND registration_data.registered) AS registered,
+    COUNT(held.vote_id) AS votes,
+    COUNT(held.vote_id) FILTER (WHERE held.cast) AS votes_cast,
+    COUNT(held.vote_id) FILTER (WHERE held.registered) AS votes_while_registered,
+    COUNT(held.vote_id) FILTER (WHERE held.registered AND held.cast) AS votes_cast_while_registered
+FROM members
+LEFT JOIN LATERAL (
+    SELECT votes.vote_id,
+        EXISTS (SELECT 1 FROM vote_data
+            WHERE vote_data.id = votes.vote_id AND vote_data.person_id = members.politician_id AND vote_data.vote IS NOT NULL) AS cast,
+        COALESCE((SELECT registration_data.registered FROM checks
+            LEFT JOIN registration_data ON registration_data.id = checks.registration_id AND registration_data.person_id = members.politician_id
+            WHERE checks.meeting_id = members.meeting_id AND (checks."from" IS NULL OR votes."from" IS NULL OR checks."from" <= votes."from")
+            ORDER BY checks."from" DESC NULLS LAST
+            LIMIT 1), FALSE) AS registered
+    FROM votes
+    WHERE votes.meeting_id = members.meeting_id
+) held ON TRUE
+GROUP BY members.meeting_id, members.politician_id, members."day"
+"#;
+
+const MEMBER_AGGREGATES: &str = r#"
+    sitting_attendance.politician_id,
+    COUNT(*) AS sittings,
+    COUNT(*) FILTER (WHERE sitting_attendance.registered > 0) AS sittings_attended,
+    COUNT(DISTINCT sitting_attendance."day") AS days,
+    COUNT(DISTINCT sitting_attendance."day") - COUNT(DISTINCT sitting_attendance."day") FILTER (WHERE sitting_attendance.registered > 0) AS days_absent,
+    SUM(sitting_attendance.registrations)::INT8 AS registrations,
+    SUM(sitting_attendance.registered)::INT8 AS registered,
+    SUM(sitting_attendance.registered)::FLOAT8 / NULLIF(SUM(sitting_attendance.registrations), 0) AS registration_rate,
+    SUM(sitting_attendance.votes)::INT8 AS votes,
+    SUM(sitting_attendance.votes_cast)::INT8 AS votes_cast,
+    SUM(sitting_attendance.votes_while_registered)::INT8 AS votes_while_registered,
+    SUM(sitting_attendance.votes_cast_while_registered)::INT8 AS votes_cast_while_registered,
+    SUM(sitting_attendance.votes_cast_while_registered)::FLOAT8 / NULLIF(SUM(sitting_attendance.votes_while_registered), 0) AS voting_share
+FROM sitting_attendance
+JOIN meetings ON meetings.id = sitting_attendance.meeting_id
+JOIN sessions ON sessions.id = meetings."session""#;
+
+/// Rebuilds the per sitting attendance, then its sums per parliament and per session.
+pub fn refresh_attendance(conn: &mut PgConnection) -> Result<(usize, usize), diesel::result::Error> {
+    conn.transaction(|conn| {
+        diesel::delete(sitting_attendance::table).execute(conn)?;
+        diesel::delete(member_attendance::table).execute(conn)?;
+        let sittings = diesel::sql_query(SITTING_ATTENDANCE_QUERY).execute(conn)?;
+        let members = diesel::sql_query(format!(
+            "INSERT INTO member_attendance SELECT period.\"period\", period.\"period_id\", {} \
+            CROSS JOIN LATERAL (VALUES ('parliament'::PQ_PERIOD, sessions.parliament), ('session'::PQ_PERIOD, sessions.id)) AS period (\"period\", \"period_id\") \
+            GROUP BY 1, 2, 3",
+            MEMBER_AGGREGATES
+        )).execute(conn)?;
+        Ok((sittings, members))
+    })
+}
+
+pub fn get_sitting_attendance(conn: &mut PgConnection, meeting_id: i32) -> Result<Vec<SittingAttendance>, diesel::result::Error> {
+    sitting_attendance::table
+        .filter(sitting_attendance::meeting_id.eq(meeting_id))
+        .order(sitting_attendance::politician_id)
+        .load::<SittingAttendance>(conn)
+}
+
+pub fn get_member_attendance(conn: &mut PgConnection, period: Period) -> Result<Vec<MemberAttendance>, diesel::result::Error> {
+    let (kind, id) = match period {
+        Period::Parliament(id) => (PeriodKind::Parliament, id),
+        Period::Session(id) => (PeriodKind::Session, id),
+        Period::Range(from, to) => {
+            return diesel::sql_query(format!("SELECT {} WHERE sitting_attendance.\"day\" BETWEEN $1 AND $2 GROUP BY 1 ORDER BY 1", MEMBER_AGGREGATES))
+                .bind::<Date, _>(from)
+                .bind::<Date, _>(to)
+                .load::<MemberAttendance>(conn);
+        }
+    };
+    member_attendance::table
+        .filter(member_attendance::period.eq(kind).and(member_attendance::period_id.eq(id)))
+        .select((
+            member_attendance::politician_id,
+            member_attendance::sittings,
+            member_attendance::sittings_attended,
+            member_attendance::days,
+            member_attendance::days_absent,
+            member_attendance::registrations,
+            member_attendance::registered,
+            member_attendance::registration_rate,
+            member_attendance::votes,
+            member_attendance::votes_cast,
+            member_attendance::votes_while_registered,
+            member_attendance::votes_cast_while_registered,
+            member_attendance::voting_share,
+        ))
+        .order(member_attendance::politician_id)
+        .load::<MemberAttendance>(conn)
+}
+
+/// Writes the attendance of each member in one sitting.
+pub async fn export_sitting_attendance(ctx: &Context, meeting_id: i32, opts: &ExportOptions) -> Result<()> {
+    let rows = ctx.run(move |conn| get_sitting_attendance(conn, meeting_id)).await?;
+    info!("Writing attendance of {} member(s) in meeting {}", rows.len(), meeting_id);
+    write_rows(&rows, opts)
+}
+
+/// Writes the attendance of each member over a period.
+pub async fn export_member_attendance(ctx: &Context, period: Period, opts: &ExportOptions) -> Result<()> {
+    let rows = ctx.run(move |conn| get_member_attendance(conn, period)).await?;
+    info!("Writing attendance of {} member(s) over {:?}", rows.len(), period);
+    write_rows(&rows, opts)
+}
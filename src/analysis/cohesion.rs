@@ -0,0 +1,171 @@
+use chrono::NaiveDate;
+use diesel::prelude::*;
+use diesel::sql_types::{BigInt, Date, Double, Int4, Nullable};
+use diesel_derive_enum::DbEnum;
+use log::info;
+use serde::Serialize;
+
+use crate::database::context::Context;
+use crate::error::Result;
+use crate::export::{write_rows, ExportOptions};
+use crate::schema::{faction_cohesion, member_cohesion};
+
+/// Votes the cohesion figures are computed over.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Period {
+    Parliament(i32),
+    Session(i32),
+    /// Votes held between the two days, both included. Not stored, computed on request.
+    Range(NaiveDate, NaiveDate),
+}
+
+/// Periods the summary tables are kept for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, DbEnum, Serialize)]
+#[DieselTypePath = "crate::schema::sql_types::PqPeriod"]
+#[serde(rename_all = "snake_case")]
+pub enum PeriodKind {
+    Parliament,
+    Session,
+}
+
+/// Cohesion of a faction over a period.
+///
+/// `rice` and `agreement` are averaged over the votes, `defection_rate` is the share of the
+/// ballots of its members that went against the largest bloc of the faction.
+#[derive(Debug, Clone, Queryable, QueryableByName, Serialize)]
+pub struct FactionCohesion {
+    #[diesel(sql_type = Int4)]
+    pub faction_id: i32,
+    #[diesel(sql_type = BigInt)]
+    pub votes: i64,
+    #[diesel(sql_type = BigInt)]
+    pub ballots: i64,
+    #[diesel(sql_type = Nullable<Double>)]
+    pub rice: Option<f64>,
+    #[diesel(sql_type = Double)]
+    pub agreement: f64,
+    #[diesel(sql_type = Double)]
+    pub defection_rate: f64,
+}
+
+/// Loyalty of a member to a faction over a period.
+///
+/// `rice` and `agreement` are those of the faction, averaged over the votes the member took part in.
+#[derive(Debug, Clone, Queryable, QueryableByName, Serialize)]
+pub struct MemberCohesion {
+    #[diesel(sql_type = Int4)]
+    pub politician_id: i32,
+    #[diesel(sql_type = Int4)]
+    pub faction_id: i32,
+    #[diesel(sql_type = BigInt)]
+    pub votes: i64,
+    #[diesel(sql_type = BigInt)]
+    pub defections: i64,
+    #[diesel(sql_type = Double)]
+    pub defection_rate: f64,
+    #[diesel(sql_type = Nullable<Double>)]
+    pub rice: Option<f64>,
+    #[diesel(sql_type = Double)]
+    pub agreement: f64,
+}
+
+const FACTION_AGGREGATES: &str = r#"
+    FACTION_TALLY.faction_id,
+    COUNT(*) AS votes,
+    SUM(FACTION_TALLY.ballots)::INT8 AS ballots,
+    AVG(FACTION_TALLY.rice) AS rice,
+    AVG(FACTION_TALLY.agreement) AS agreement,
+    SUM(FACTION_TALLY.defections)::FLOAT8 / SUM(FACTION_TALLY.ballots) AS defection_rate
+FROM FACTION_TALLY
+JOIN VOTE_SCOPE ON VOTE_SCOPE.vote_id = FACTION_TALLY.vote_id"#;
+
+// A ballot is a defection when fewer members of the faction voted that way than for its largest bloc.
+const MEMBER_AGGREGATES: &str = r#"
+    ballot.person_id AS politician_id,
+    ballot.faction_id,
+    COUNT(*) AS votes,
+    COUNT(*) FILTER (WHERE ballot.defected) AS defections,
+    AVG(ballot.defected::INT)::FLOAT8 AS defection_rate,
+    AVG(ballot.rice) AS rice,
+    AVG(ballot.agreement) AS agreement
+FROM (
+    SELECT BALLOT_FACTION.vote_id, BALLOT_FACTION.person_id, BALLOT_FACTION.faction_id, FACTION_TALLY.rice, FACTION_TALLY.agreement,
+        CASE BALLOT_FACTION.vote
+            WHEN 'for' THEN FACTION_TALLY.for_count
+            WHEN 'against' THEN FACTION_TALLY.against_count
+            ELSE FACTION_TALLY.abstain_count
+        END < FACTION_TALLY.majority AS defected
+    FROM BALLOT_FACTION
+    JOIN FACTION_TALLY ON FACTION_TALLY.vote_id = BALLOT_FACTION.vote_id AND FACTION_TALLY.faction_id = BALLOT_FACTION.faction_id
+) ballot
+JOIN VOTE_SCOPE ON VOTE_SCOPE.vote_id = ballot.vote_id"#;
+
+// Every vote counts once for its parliament and once for its session.
+const PERIODS: &str = r#"
+CROSS JOIN LATERAL (VALUES ('parliament'::PQ_PERIOD, VOTE_SCOPE.parliament_id), ('session'::PQ_PERIOD, VOTE_SCOPE.session_id)) AS period ("period", "period_id")"#;
+
+/// Rebuilds the parliament and session summaries from scratch.
+pub fn refresh_cohesion(conn: &mut PgConnection) -> Result<(usize, usize), diesel::result::Error> {
+    conn.transaction(|conn| {
+        diesel::delete(faction_cohesion::table).execute(conn)?;
+        diesel::delete(member_cohesion::table).execute(conn)?;
+        let factions = diesel::sql_query(format!(
+            "INSERT INTO faction_cohesion SELECT period.\"period\", period.\"period_id\", {} {} GROUP BY 1, 2, 3",
+            FACTION_AGGREGATES, PERIODS
+        )).execute(conn)?;
+        let members = diesel::sql_query(format!(
+            "INSERT INTO member_cohesion SELECT period.\"period\", period.\"period_id\", {} {} GROUP BY 1, 2, 3, 4",
+            MEMBER_AGGREGATES, PERIODS
+        )).execute(conn)?;
+        Ok((factions, members))
+    })
+}
+
+pub fn get_faction_cohesion(conn: &mut PgConnection, period: Period) -> Result<Vec<FactionCohesion>, diesel::result::Error> {
+    let (kind, id) = match period {
+        Period::Parliament(id) => (PeriodKind::Parliament, id),
+        Period::Session(id) => (PeriodKind::Session, id),
+        Period::Range(from, to) => {
+            return diesel::sql_query(format!("SELECT {} WHERE VOTE_SCOPE.\"day\" BETWEEN $1 AND $2 GROUP BY 1 ORDER BY 1", FACTION_AGGREGATES))
+                .bind::<Date, _>(from)
+                .bind::<Date, _>(to)
+                .load::<FactionCohesion>(conn);
+        }
+    };
+    faction_cohesion::table
+        .filter(faction_cohesion::period.eq(kind).and(faction_cohesion::period_id.eq(id)))
+        .select((faction_cohesion::faction_id, faction_cohesion::votes, faction_cohesion::ballots, faction_cohesion::rice, faction_cohesion::agreement, faction_cohesion::defection_rate))
+        .order(faction_cohesion::faction_id)
+        .load::<FactionCohesion>(conn)
+}
+
+pub fn get_member_cohesion(conn: &mut PgConnection, period: Period) -> Result<Vec<MemberCohesion>, diesel::result::Error> {
+    let (kind, id) = match period {
+        Period::Parliament(id) => (PeriodKind::Parliament, id),
+        Period::Session(id) => (PeriodKind::Session, id),
+        Period::Range(from, to) => {
+            return diesel::sql_query(format!("SELECT {} WHERE VOTE_SCOPE.\"day\" BETWEEN $1 AND $2 GROUP BY 1, 2 ORDER BY 1, 2", MEMBER_AGGREGATES))
+                .bind::<Date, _>(from)
+                .bind::<Date, _>(to)
+                .load::<MemberCohesion>(conn);
+        }
+    };
+    member_cohesion::table
+        .filter(member_cohesion::period.eq(kind).and(member_cohesion::period_id.eq(id)))
+        .select((member_cohesion::politician_id, member_cohesion::faction_id, member_cohesion::votes, member_cohesion::defections, member_cohesion::defection_rate, member_cohesion::rice, member_cohesion::agreement))
+        .order((member_cohesion::politician_id, member_cohesion::faction_id))
+        .load::<MemberCohesion>(conn)
+}
+
+/// Writes the cohesion of each faction, or of each member when `members` is set, over a period.
+pub async fn export_cohesion(ctx: &Context, period: Period, members: bool, opts: &ExportOptions) -> Result<()> {
+    if members {
+        let rows = ctx.run(move |conn| get_member_cohesion(conn, period)).await?;
+        info!("Writing cohesion of {} member(s) over {:?}", rows.len(), period);
+        write_rows(&rows, opts)
+    } else {
+        let rows = ctx.run(move |conn| get_faction_cohesion(conn, period)).await?;
+        info!("Writing cohesion of {} faction(s) over {:?}", rows.len(), period);
+        write_rows(&rows, opts)
+    }
+}
@@ -0,0 +1,513 @@
+use std::collections::{HashMap, HashSet};
+
+use diesel::prelude::*;
+use diesel::sql_types::{Int4, Nullable};
+use log::{info, warn};
+use serde::Serialize;
+
+use crate::analysis::cohesion::PeriodKind;
+use crate::analysis::linalg::Matrix;
+use crate::database::context::Context;
+use crate::error::{Error, Result};
+use crate::export::{write_rows, ExportOptions};
+use crate::models::session::sessions::Sessions;
+use crate::models::session::voting_data::VoteType;
+use crate::models::upsert::BATCH_SIZE;
+use crate::schema::sql_types::PqVoteType;
+use crate::schema::{cutting_line, ideal_point};
+
+const MAX_ITERATIONS: usize = 500;
+/// Relative change of the log-likelihood under which the fit is considered converged.
+const TOLERANCE: f64 = 1e-8;
+/// Prior precision of the vote parameters, loose enough to only tame votes the members split along perfectly.
+const VOTE_PRECISION: f64 = 1.0 / 25.0;
+/// Prior precision of the member coordinates, which fixes the scale of the space.
+const MEMBER_PRECISION: f64 = 1.0;
+
+#[derive(Debug, Clone)]
+pub struct ScalingOptions {
+    /// 1 or 2.
+    pub dimensions: usize,
+    /// Abstentions are left out unless set.
+    pub abstain_as_against: bool,
+    /// Members who cast fewer ballots on the kept votes are left out.
+    pub min_votes: usize,
+    /// Votes whose minority is smaller than this share are left out, they say next to nothing about positions.
+    pub min_minority: f64,
+    /// Member placed on the positive side of every dimension, the member with the most ballots when not set.
+    pub polarity: Option<i32>,
+}
+
+impl Default for ScalingOptions {
+    fn default() -> Self {
+        ScalingOptions {
+            dimensions: 2,
+            abstain_as_against: false,
+            min_votes: 20,
+            min_minority: 0.025,
+            polarity: None,
+        }
+    }
+}
+
+/// Estimated position of a member, with the standard error of each coordinate.
+#[derive(Debug, Clone, PartialEq, Insertable, Queryable, Serialize)]
+#[diesel(table_name = ideal_point)]
+pub struct IdealPoint {
+    pub period: PeriodKind,
+    pub period_id: i32,
+    pub dimensions: i32,
+    pub politician_id: i32,
+    pub votes: i32,
+    pub coord1: f64,
+    pub coord2: Option<f64>,
+    pub se1: f64,
+    pub se2: Option<f64>,
+}
+
+/// Parameters of a vote: members with `alpha + beta · x > 0` are expected to vote for.
+#[derive(Debug, Clone, PartialEq, Insertable, Queryable, Serialize)]
+#[diesel(table_name = cutting_line)]
+pub struct CuttingLine {
+    pub period: PeriodKind,
+    pub period_id: i32,
+    pub dimensions: i32,
+    pub vote_id: i32,
+    pub alpha: f64,
+    pub beta1: f64,
+    pub beta2: Option<f64>,
+    pub offset: f64,
+    pub angle: f64,
+    pub correct: f64,
+}
+
+#[derive(Debug, QueryableByName)]
+struct Ballot {
+    #[diesel(sql_type = Int4)]
+    vote_id: i32,
+    #[diesel(sql_type = Int4)]
+    person_id: i32,
+    #[diesel(sql_type = Nullable<PqVoteType>)]
+    vote: Option<VoteType>,
+}
+
+// Ballots cast by members of the parliament the vote was held in, `{}` is the scope column the period is matched on.
+const BALLOTS_QUERY: &str = r#"
+SELECT vote_data.id AS vote_id, vote_data.person_id, vote_data.vote
+FROM vote_data
+JOIN VOTE_SCOPE ON VOTE_SCOPE.vote_id = vote_data.id
+JOIN politician ON politician.id = vote_data.person_id AND politician.parliament = VOTE_SCOPE.parliament_id
+WHERE vote_data.vote IS NOT NULL AND {} = $1
+ORDER BY 1, 2
+"#;
+
+/// For and against ballots of the members and votes kept for scaling.
+struct RollCall {
+    members: Vec<i32>,
+    votes: Vec<i32>,
+    /// `(member, vote, voted for)` by index into `members` and `votes`.
+    ballots: Vec<(usize, usize, bool)>,
+}
+
+impl RollCall {
+    fn build(ballots: &[Ballot], opts: &ScalingOptions) -> RollCall {
+        let mut kept: Vec<(i32, i32, bool)> = ballots
+            .iter()
+            .filter_map(|ballot| match ballot.vote? {
+                VoteType::For => Some((ballot.person_id, ballot.vote_id, true)),
+                VoteType::Against => Some((ballot.person_id, ballot.vote_id, false)),
+                VoteType::Abstain if opts.abstain_as_against => Some((ballot.person_id, ballot.vote_id, false)),
+                VoteType::Abstain => None,
+            })
+            .collect();
+
+        // Leaving out lopsided votes can drop members under the minimum and the other way round, so repeat until nothing changes.
+        loop {
+            let mut tally: HashMap<i32, (usize, usize)> = HashMap::new();
+            for (_, vote_id, yes) in &kept {
+                let (yes_count, total) = tally.entry(*vote_id).or_default();
+                *yes_count += *yes as usize;
+                *total += 1;
+            }
+            let votes: HashSet<i32> = tally
+                .iter()
+                .filter(|(_, (yes_count, total))| (*yes_count).min(total - yes_count) as f64 >= opts.min_minority * *total as f64 && *yes_count != 0 && yes_count != total)
+                .map(|(vote_id, _)| *vote_id)
+                .collect();
+            let mut cast: HashMap<i32, usize> = HashMap::new();
+            for (person_id, vote_id, _) in &kept {
+                if votes.contains(vote_id) {
+                    *cast.entry(*person_id).or_default() += 1;
+                }
+            }
+            let before = kept.len();
+            kept.retain(|(person_id, vote_id, _)| votes.contains(vote_id) && cast[person_id] >= opts.min_votes);
+            if kept.len() == before {
+                break;
+            }
+        }
+
+        let mut members: Vec<i32> = kept.iter().map(|(person_id, _, _)| *person_id).collect::<HashSet<_>>().into_iter().collect();
+        let mut votes: Vec<i32> = kept.iter().map(|(_, vote_id, _)| *vote_id).collect::<HashSet<_>>().into_iter().collect();
+        members.sort_unstable();
+        votes.sort_unstable();
+        let member_index: HashMap<i32, usize> = members.iter().enumerate().map(|(i, id)| (*id, i)).collect();
+        let vote_index: HashMap<i32, usize> = votes.iter().enumerate().map(|(i, id)| (*id, i)).collect();
+        let ballots = kept.iter().map(|(person_id, vote_id, yes)| (member_index[person_id], vote_index[vote_id], *yes)).collect();
+        RollCall { members, votes, ballots }
+    }
+
+    /// Principal components of the agreement between members, scaled to unit variance, as a starting point.
+    fn initial_positions(&self, dimensions: usize) -> Vec<Vec<f64>> {
+        let n = self.members.len();
+        let mut by_vote: Vec<Vec<(usize, bool)>> = vec![Vec::new(); self.votes.len()];
+        for (member, vote, yes) in &self.ballots {
+            by_vote[*vote].push((*member, *yes));
+        }
+        let (mut common, mut agreed) = (Matrix::zeros(n), Matrix::zeros(n));
+        for ballots in &by_vote {
+            for &(a, yes_a) in ballots {
+                for &(b, yes_b) in ballots {
+                    common.set(a, b, common.get(a, b) + 1.0);
+                    if yes_a == yes_b {
+                        agreed.set(a, b, agreed.get(a, b) + 1.0);
+                    }
+                }
+            }
+        }
+        let mut agreement = Matrix::zeros(n);
+        for a in 0..n {
+            for b in 0..n {
+                let value = if common.get(a, b) > 0.0 { agreed.get(a, b) / common.get(a, b) } else { 0.5 };
+                agreement.set(a, b, value);
+            }
+        }
+
+        let eigen = agreement.double_centered().symmetric_eigen();
+        let mut positions = vec![vec![0.0; dimensions]; n];
+        for (k, (_, vector)) in eigen.iter().take(dimensions).enumerate() {
+            let spread = (vector.iter().map(|x| x * x).sum::<f64>() / n as f64).sqrt().max(f64::MIN_POSITIVE);
+            for (position, x) in positions.iter_mut().zip(vector) {
+                position[k] = x / spread;
+            }
+        }
+        positions
+    }
+}
+
+fn logistic(z: f64) -> f64 {
+    1.0 / (1.0 + (-z).exp())
+}
+
+fn dot(a: &[f64], b: &[f64]) -> f64 {
+    a.iter().zip(b).map(|(x, y)| x * y).sum()
+}
+
+/// Gradient and Hessian of the log-posterior of a logistic regression with `offset + theta · features`
+/// as the log-odds of voting for and a centred normal prior of the given precision on `theta`.
+fn log_posterior_derivatives<'a>(theta: &[f64], observations: impl Iterator<Item = (f64, &'a [f64], bool)>, precision: f64) -> (Vec<f64>, Matrix) {
+    let m = theta.len();
+    let mut gradient: Vec<f64> = theta.iter().map(|t| -precision * t).collect();
+    let mut information = Matrix::zeros(m);
+    for i in 0..m {
+        information.set(i, i, precision);
+    }
+    for (offset, features, yes) in observations {
+        let p = logistic(offset + dot(theta, features));
+        let residual = yes as u8 as f64 - p;
+        let weight = p * (1.0 - p);
+        for (a, feature_a) in features.iter().enumerate() {
+            gradient[a] += residual * feature_a;
+            for (b, feature_b) in features.iter().enumerate() {
+                information.set(a, b, information.get(a, b) + weight * feature_a * feature_b);
+            }
+        }
+    }
+    (gradient, information)
+}
+
+fn newton_step<'a>(theta: &mut [f64], observations: impl Iterator<Item = (f64, &'a [f64], bool)>, precision: f64) {
+    let (gradient, information) = log_posterior_derivatives(theta, observations, precision);
+    if let Some(step) = information.solve(&gradient) {
+        for (value, change) in theta.iter_mut().zip(step) {
+            *value += change;
+        }
+    }
+}
+
+/// Members positions and vote parameters `[alpha, beta...]` of a fitted roll call.
+struct Fit {
+    positions: Vec<Vec<f64>>,
+    parameters: Vec<Vec<f64>>,
+    iterations: usize,
+}
+
+impl Fit {
+    fn log_odds(&self, member: usize, vote: usize) -> f64 {
+        let parameters = &self.parameters[vote];
+        parameters[0] + dot(&parameters[1..], &self.positions[member])
+    }
+
+    fn log_likelihood(&self, roll_call: &RollCall) -> f64 {
+        roll_call
+            .ballots
+            .iter()
+            .map(|&(member, vote, yes)| {
+                let p = logistic(self.log_odds(member, vote));
+                if yes { p.max(f64::MIN_POSITIVE).ln() } else { (1.0 - p).max(f64::MIN_POSITIVE).ln() }
+            })
+            .sum()
+    }
+}
+
+/// Fits `P(for) = logistic(alpha_j + beta_j · x_i)` by alternating Newton steps on the vote parameters and on the member positions.
+fn fit(roll_call: &RollCall, opts: &ScalingOptions) -> Fit {
+    let dimensions = opts.dimensions;
+    let mut by_vote: Vec<Vec<(usize, bool)>> = vec![Vec::new(); roll_call.votes.len()];
+    let mut by_member: Vec<Vec<(usize, bool)>> = vec![Vec::new(); roll_call.members.len()];
+    for &(member, vote, yes) in &roll_call.ballots {
+        by_vote[vote].push((member, yes));
+        by_member[member].push((vote, yes));
+    }
+
+    let mut fit = Fit {
+        positions: roll_call.initial_positions(dimensions),
+        parameters: vec![vec![0.0; dimensions + 1]; roll_call.votes.len()],
+        iterations: 0,
+    };
+    let mut previous = f64::NEG_INFINITY;
+    while fit.iterations < MAX_ITERATIONS {
+        fit.iterations += 1;
+        let design: Vec<Vec<f64>> = fit.positions.iter().map(|position| std::iter::once(1.0).chain(position.iter().copied()).collect()).collect();
+        for (parameters, ballots) in fit.parameters.iter_mut().zip(&by_vote) {
+            newton_step(parameters, ballots.iter().map(|&(member, yes)| (0.0, design[member].as_slice(), yes)), VOTE_PRECISION);
+        }
+        for (position, ballots) in fit.positions.iter_mut().zip(&by_member) {
+            let parameters = &fit.parameters;
+            newton_step(position, ballots.iter().map(|&(vote, yes)| (parameters[vote][0], &parameters[vote][1..], yes)), MEMBER_PRECISION);
+        }
+
+        let log_likelihood = fit.log_likelihood(roll_call);
+        if (log_likelihood - previous).abs() <= TOLERANCE * log_likelihood.abs() {
+            break;
+        }
+        previous = log_likelihood;
+    }
+
+    orient(&mut fit, roll_call, opts);
+    fit
+}
+
+/// Turns the space so that the first dimension carries the most spread and the polarity member is on the positive side.
+///
+/// Both leave the likelihood and the prior unchanged, the model only pins the positions down up to rotation and reflection.
+fn orient(fit: &mut Fit, roll_call: &RollCall, opts: &ScalingOptions) {
+    let dimensions = opts.dimensions;
+    if dimensions == 2 {
+        let mut covariance = Matrix::zeros(2);
+        for position in &fit.positions {
+            for a in 0..2 {
+                for b in 0..2 {
+                    covariance.set(a, b, covariance.get(a, b) + position[a] * position[b]);
+                }
+            }
+        }
+        let axes: Vec<Vec<f64>> = covariance.symmetric_eigen().into_iter().map(|(_, vector)| vector).collect();
+        let rotate = |point: &mut [f64]| {
+            let rotated: Vec<f64> = axes.iter().map(|axis| dot(axis, point)).collect();
+            point.copy_from_slice(&rotated);
+        };
+        fit.positions.iter_mut().for_each(|position| rotate(position));
+        fit.parameters.iter_mut().for_each(|parameters| rotate(&mut parameters[1..]));
+    }
+
+    let mut cast = vec![0usize; roll_call.members.len()];
+    for (member, _, _) in &roll_call.ballots {
+        cast[*member] += 1;
+    }
+    let polarity = opts
+        .polarity
+        .and_then(|id| roll_call.members.iter().position(|member| *member == id))
+        .or_else(|| (0..cast.len()).max_by_key(|member| (cast[*member], std::cmp::Reverse(*member))));
+    if let Some(polarity) = polarity {
+        for k in 0..dimensions {
+            if fit.positions[polarity][k] < 0.0 {
+                fit.positions.iter_mut().for_each(|position| position[k] = -position[k]);
+                fit.parameters.iter_mut().for_each(|parameters| parameters[k + 1] = -parameters[k + 1]);
+            }
+        }
+    }
+}
+
+/// Loads the ballots of a parliament or session, scales them and replaces the stored results of that period.
+pub fn scale_period(conn: &mut PgConnection, period: PeriodKind, period_id: i32, opts: &ScalingOptions) -> Result<(Vec<IdealPoint>, Vec<CuttingLine>), diesel::result::Error> {
+    let scope = match period {
+        PeriodKind::Parliament => "VOTE_SCOPE.parliament_id",
+        PeriodKind::Session => "VOTE_SCOPE.session_id",
+    };
+    let ballots = diesel::sql_query(BALLOTS_QUERY.replace("{}", scope))
+        .bind::<Int4, _>(period_id)
+        .load::<Ballot>(conn)?;
+    let roll_call = RollCall::build(&ballots, opts);
+    if roll_call.members.len() <= opts.dimensions || roll_call.votes.is_empty() {
+        warn!("Not enough votes to scale {:?} {}: {} member(s) on {} vote(s)", period, period_id, roll_call.members.len(), roll_call.votes.len());
+        return Ok((Vec::new(), Vec::new()));
+    }
+    let fit = fit(&roll_call, opts);
+    let dimensions = opts.dimensions as i32;
+
+    let mut by_member: Vec<Vec<(usize, bool)>> = vec![Vec::new(); roll_call.members.len()];
+    let mut correct = vec![(0usize, 0usize); roll_call.votes.len()];
+    for &(member, vote, yes) in &roll_call.ballots {
+        by_member[member].push((vote, yes));
+        correct[vote].0 += ((fit.log_odds(member, vote) > 0.0) == yes) as usize;
+        correct[vote].1 += 1;
+    }
+
+    let points: Vec<IdealPoint> = roll_call
+        .members
+        .iter()
+        .enumerate()
+        .map(|(member, politician_id)| {
+            let position = &fit.positions[member];
+            let observations = by_member[member].iter().map(|&(vote, yes)| (fit.parameters[vote][0], &fit.parameters[vote][1..], yes));
+            let (_, information) = log_posterior_derivatives(position, observations, MEMBER_PRECISION);
+            let standard_error = |k: usize| {
+                let mut unit = vec![0.0; position.len()];
+                unit[k] = 1.0;
+                information.solve(&unit).map_or(f64::NAN, |column| column[k].sqrt())
+            };
+            IdealPoint {
+                period,
+                period_id,
+                dimensions,
+                politician_id: *politician_id,
+                votes: by_member[member].len() as i32,
+                coord1: position[0],
+                coord2: position.get(1).copied(),
+                se1: standard_error(0),
+                se2: (position.len() > 1).then(|| standard_error(1)),
+            }
+        })
+        .collect();
+
+    let lines: Vec<CuttingLine> = roll_call
+        .votes
+        .iter()
+        .enumerate()
+        .map(|(vote, vote_id)| {
+            let parameters = &fit.parameters[vote];
+            let (alpha, beta) = (parameters[0], &parameters[1..]);
+            let norm = dot(beta, beta).sqrt();
+            CuttingLine {
+                period,
+                period_id,
+                dimensions,
+                vote_id: *vote_id,
+                alpha,
+                beta1: beta[0],
+                beta2: beta.get(1).copied(),
+                offset: if norm > 0.0 { -alpha / norm } else { 0.0 },
+                angle: beta.get(1).copied().unwrap_or(0.0).atan2(beta[0]).to_degrees(),
+                correct: correct[vote].0 as f64 / correct[vote].1 as f64,
+            }
+        })
+        .collect();
+
+    let classified: usize = correct.iter().map(|(right, _)| right).sum();
+    info!(
+        "Scaled {} member(s) on {} vote(s) of {:?} {} in {} dimension(s) after {} iteration(s), {:.1}% of ballots classified correctly",
+        points.len(), lines.len(), period, period_id, dimensions, fit.iterations, 100.0 * classified as f64 / roll_call.ballots.len() as f64
+    );
+
+    conn.transaction(|conn| {
+        diesel::delete(ideal_point::table.filter(ideal_point::period.eq(period).and(ideal_point::period_id.eq(period_id)).and(ideal_point::dimensions.eq(dimensions)))).execute(conn)?;
+        diesel::delete(cutting_line::table.filter(cutting_line::period.eq(period).and(cutting_line::period_id.eq(period_id)).and(cutting_line::dimensions.eq(dimensions)))).execute(conn)?;
+        for chunk in points.chunks(BATCH_SIZE) {
+            diesel::insert_into(ideal_point::table).values(chunk).execute(conn)?;
+        }
+        for chunk in lines.chunks(BATCH_SIZE) {
+            diesel::insert_into(cutting_line::table).values(chunk).execute(conn)?;
+        }
+        Ok::<_, diesel::result::Error>(())
+    })?;
+    Ok((points, lines))
+}
+
+/// Scales a parliament, and each of its sessions when `per_session` is set, then writes the member
+/// positions of every period, or the cutting lines of the votes when `cutting_lines` is set.
+pub async fn export_ideal_points(ctx: &Context, parliament_id: i32, per_session: bool, cutting_lines: bool, opts: ScalingOptions, export: &ExportOptions) -> Result<()> {
+    let mut periods = vec![(PeriodKind::Parliament, parliament_id)];
+    if per_session {
+        let session_ids = ctx.run(move |conn| Sessions::get_sessions_per_parliament(conn, parliament_id)).await?;
+        periods.extend(session_ids.into_iter().map(|session_id| (PeriodKind::Session, session_id)));
+    }
+
+    let (mut points, mut lines) = (Vec::new(), Vec::new());
+    for (period, period_id) in periods {
+        let opts = opts.clone();
+        let context = format!("ideal points of {:?} {}", period, period_id);
+        let (period_points, period_lines) = ctx.run(move |conn| scale_period(conn, period, period_id, &opts).map_err(Error::database(&context))).await?;
+        points.extend(period_points);
+        lines.extend(period_lines);
+    }
+
+    if cutting_lines {
+        write_rows(&lines, export)
+    } else {
+        write_rows(&points, export)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Two blocs of five members voting against each other, each vote with one member crossing over.
+    fn roll_call(opts: &ScalingOptions) -> RollCall {
+        let mut ballots = Vec::new();
+        for vote_id in 0..30 {
+            for person_id in 1..=10 {
+                let first_bloc = person_id <= 5;
+                let crosses = person_id == vote_id % 10 + 1;
+                let vote = if (first_bloc == (vote_id % 3 != 0)) != crosses { VoteType::For } else { VoteType::Against };
+                ballots.push(Ballot { vote_id, person_id, vote: Some(vote) });
+            }
+        }
+        RollCall::build(&ballots, opts)
+    }
+
+    fn options(dimensions: usize, polarity: Option<i32>) -> ScalingOptions {
+        ScalingOptions { dimensions, min_votes: 0, polarity, ..ScalingOptions::default() }
+    }
+
+    #[test]
+    fn separates_two_blocs() {
+        let opts = options(1, None);
+        let roll_call = roll_call(&opts);
+        assert_eq!(roll_call.members, (1..=10).collect::<Vec<_>>());
+        assert_eq!(roll_call.votes.len(), 30);
+
+        let fit = fit(&roll_call, &opts);
+        let coords: Vec<f64> = fit.positions.iter().map(|position| position[0]).collect();
+        // Every member casts the same number of ballots, so the first one is placed on the positive side.
+        assert!(coords[..5].iter().all(|x| *x > 0.0), "{:?}", coords);
+        assert!(coords[5..].iter().all(|x| *x < 0.0), "{:?}", coords);
+
+        let misclassified = roll_call.ballots.iter().filter(|&&(member, vote, yes)| (fit.log_odds(member, vote) > 0.0) != yes).count();
+        assert!(misclassified <= 30, "{} ballots misclassified", misclassified);
+    }
+
+    #[test]
+    fn puts_the_polarity_member_on_the_positive_side() {
+        let opts = options(2, Some(8));
+        let roll_call = roll_call(&opts);
+        let fit = fit(&roll_call, &opts);
+        assert!(fit.positions[7].iter().all(|x| *x >= 0.0), "{:?}", fit.positions[7]);
+        assert!(fit.positions[..5].iter().all(|position| position[0] < 0.0), "{:?}", fit.positions);
+        assert!(fit.positions[5..].iter().all(|position| position[0] > 0.0), "{:?}", fit.positions);
+
+        let spread = |k: usize| fit.positions.iter().map(|position| position[k] * position[k]).sum::<f64>();
+        assert!(spread(0) >= spread(1), "{} < {}", spread(0), spread(1));
+    }
+}
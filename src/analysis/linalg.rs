@@ -0,0 +1,182 @@
+/// Sweeps after which the Jacobi rotation gives up converging.
+const MAX_SWEEPS: usize = 100;
+
+/// Dense square matrix stored row by row.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Matrix {
+    pub size: usize,
+    pub values: Vec<f64>,
+}
+
+impl Matrix {
+    pub fn zeros(size: usize) -> Matrix {
+        Matrix { size, values: vec![0.0; size * size] }
+    }
+
+    pub fn get(&self, row: usize, column: usize) -> f64 {
+        self.values[row * self.size + column]
+    }
+
+    pub fn set(&mut self, row: usize, column: usize, value: f64) {
+        self.values[row * self.size + column] = value;
+    }
+
+    /// Subtracts the row and column means and adds back the grand mean, as done to a Gram matrix before PCA.
+    pub fn double_centered(&self) -> Matrix {
+        let n = self.size;
+        let means: Vec<f64> = (0..n).map(|row| (0..n).map(|column| self.get(row, column)).sum::<f64>() / n as f64).collect();
+        let grand = means.iter().sum::<f64>() / n as f64;
+        let mut centered = Matrix::zeros(n);
+        for row in 0..n {
+            for column in 0..n {
+                centered.set(row, column, self.get(row, column) - means[row] - means[column] + grand);
+            }
+        }
+        centered
+    }
+
+    /// Solution of `self * x = rhs` by Gaussian elimination, `None` when the matrix is singular.
+    pub fn solve(&self, rhs: &[f64]) -> Option<Vec<f64>> {
+        let n = self.size;
+        let mut a = self.clone();
+        let mut x = rhs.to_vec();
+        for column in 0..n {
+            let pivot = (column..n).max_by(|p, q| a.get(*p, column).abs().total_cmp(&a.get(*q, column).abs()))?;
+            if a.get(pivot, column).abs() < 1e-12 {
+                return None;
+            }
+            for k in 0..n {
+                let (upper, lower) = (a.get(column, k), a.get(pivot, k));
+                a.set(column, k, lower);
+                a.set(pivot, k, upper);
+            }
+            x.swap(column, pivot);
+            for row in column + 1..n {
+                let factor = a.get(row, column) / a.get(column, column);
+                for k in column..n {
+                    a.set(row, k, a.get(row, k) - factor * a.get(column, k));
+                }
+                x[row] -= factor * x[column];
+            }
+        }
+        for row in (0..n).rev() {
+            let tail: f64 = (row + 1..n).map(|k| a.get(row, k) * x[k]).sum();
+            x[row] = (x[row] - tail) / a.get(row, row);
+        }
+        Some(x)
+    }
+
+    /// Eigenvalues of a symmetric matrix, largest first, each with its unit eigenvector.
+    ///
+    /// Cyclic Jacobi rotations, which are slow for large matrices but accurate and
+    /// plenty for the few hundred members of a parliament.
+    pub fn symmetric_eigen(&self) -> Vec<(f64, Vec<f64>)> {
+        let n = self.size;
+        let mut a = self.clone();
+        let mut vectors = Matrix::zeros(n);
+        for i in 0..n {
+            vectors.set(i, i, 1.0);
+        }
+
+        let scale: f64 = a.values.iter().map(|value| value * value).sum::<f64>().max(f64::MIN_POSITIVE);
+        for _ in 0..MAX_SWEEPS {
+            let off: f64 = (0..n).flat_map(|p| (0..n).filter(move |q| *q != p).map(move |q| (p, q))).map(|(p, q)| a.get(p, q).powi(2)).sum();
+            if off <= scale * 1e-24 {
+                break;
+            }
+            for p in 0..n {
+                for q in p + 1..n {
+                    let apq = a.get(p, q);
+                    if apq == 0.0 {
+                        continue;
+                    }
+                    let theta = (a.get(q, q) - a.get(p, p)) / (2.0 * apq);
+                    let t = theta.signum() / (theta.abs() + (theta * theta + 1.0).sqrt());
+                    let c = 1.0 / (t * t + 1.0).sqrt();
+                    let s = t * c;
+                    for k in 0..n {
+                        let akp = a.get(k, p);
+                        let akq = a.get(k, q);
+                        a.set(k, p, c * akp - s * akq);
+                        a.set(k, q, s * akp + c * akq);
+                    }
+                    for k in 0..n {
+                        let apk = a.get(p, k);
+                        let aqk = a.get(q, k);
+                        a.set(p, k, c * apk - s * aqk);
+                        a.set(q, k, s * apk + c * aqk);
+                    }
+                    for k in 0..n {
+                        let vkp = vectors.get(k, p);
+                        let vkq = vectors.get(k, q);
+                        vectors.set(k, p, c * vkp - s * vkq);
+                        vectors.set(k, q, s * vkp + c * vkq);
+                    }
+                }
+            }
+        }
+
+        let mut pairs: Vec<(f64, Vec<f64>)> = (0..n).map(|i| (a.get(i, i), (0..n).map(|k| vectors.get(k, i)).collect())).collect();
+        pairs.sort_by(|x, y| y.0.total_cmp(&x.0));
+        pairs
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matrix(size: usize, values: &[f64]) -> Matrix {
+        Matrix { size, values: values.to_vec() }
+    }
+
+    fn assert_close(actual: &[f64], expected: &[f64]) {
+        assert_eq!(actual.len(), expected.len());
+        for (a, e) in actual.iter().zip(expected) {
+            assert!((a - e).abs() < 1e-9, "{:?} is not {:?}", actual, expected);
+        }
+    }
+
+    /// Eigenvectors are only known up to sign, so compare them turned towards `expected`.
+    fn assert_same_direction(actual: &[f64], expected: &[f64]) {
+        let sign = dot(actual, expected).signum();
+        let turned: Vec<f64> = actual.iter().map(|x| x * sign).collect();
+        assert_close(&turned, expected);
+    }
+
+    fn dot(a: &[f64], b: &[f64]) -> f64 {
+        a.iter().zip(b).map(|(x, y)| x * y).sum()
+    }
+
+    #[test]
+    fn solves_a_linear_system() {
+        let a = matrix(3, &[2.0, 1.0, -1.0, -3.0, -1.0, 2.0, -2.0, 1.0, 2.0]);
+        assert_close(&a.solve(&[8.0, -11.0, -3.0]).unwrap(), &[2.0, 3.0, -1.0]);
+    }
+
+    #[test]
+    fn rejects_a_singular_system() {
+        let a = matrix(3, &[1.0, 2.0, 3.0, 2.0, 4.0, 6.0, 0.0, 1.0, 1.0]);
+        assert_eq!(a.solve(&[1.0, 2.0, 3.0]), None);
+    }
+
+    #[test]
+    fn sorts_the_eigenvalues_of_a_diagonal_matrix() {
+        let eigen = matrix(3, &[1.0, 0.0, 0.0, 0.0, 3.0, 0.0, 0.0, 0.0, 2.0]).symmetric_eigen();
+        assert_close(&eigen.iter().map(|(value, _)| *value).collect::<Vec<_>>(), &[3.0, 2.0, 1.0]);
+        assert_same_direction(&eigen[0].1, &[0.0, 1.0, 0.0]);
+        assert_same_direction(&eigen[1].1, &[0.0, 0.0, 1.0]);
+        assert_same_direction(&eigen[2].1, &[1.0, 0.0, 0.0]);
+    }
+
+    #[test]
+    fn finds_the_axes_of_a_rotated_matrix() {
+        // diag(3, 1) turned by 30 degrees.
+        let (c, s) = (30f64.to_radians().cos(), 30f64.to_radians().sin());
+        let a = matrix(2, &[3.0 * c * c + s * s, 2.0 * c * s, 2.0 * c * s, 3.0 * s * s + c * c]);
+        let eigen = a.symmetric_eigen();
+        assert_close(&[eigen[0].0, eigen[1].0], &[3.0, 1.0]);
+        assert_same_direction(&eigen[0].1, &[c, s]);
+        assert_same_direction(&eigen[1].1, &[-s, c]);
+    }
+}
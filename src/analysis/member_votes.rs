@@ -0,0 +1,124 @@
+use clap::ValueEnum;
+use diesel::prelude::*;
+use diesel::sql_types::{BigInt, Bool, Date, Double, Int4, Nullable, Text};
+use log::info;
+use serde::Serialize;
+
+use crate::analysis::cohesion::Period;
+use crate::database::context::Context;
+use crate::error::Result;
+use crate::export::{write_rows, ExportOptions};
+
+/// Whether a member still holds the mandate.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum MemberStatus {
+    /// No end date on the mandate.
+    Sitting,
+    /// The mandate has ended.
+    Former,
+}
+
+/// Which ballots are summed per member.
+#[derive(Debug, Clone)]
+pub struct MemberVoteFilter {
+    pub period: Period,
+    /// Only ballots cast while the member belonged to this faction.
+    pub faction_id: Option<i32>,
+    pub status: Option<MemberStatus>,
+}
+
+/// How a member voted over a period. `none_count` are votes the member is listed in without a ballot,
+/// the percentages are of `voted_count`, apart from `voted_percent` which is of `all_count`.
+#[derive(Debug, Clone, PartialEq, QueryableByName, Serialize)]
+pub struct MemberVoteSummary {
+    #[diesel(sql_type = Int4)]
+    pub person_id: i32,
+    #[diesel(sql_type = Int4)]
+    pub parliament: i32,
+    #[diesel(sql_type = Text)]
+    pub name: String,
+    #[diesel(sql_type = Text)]
+    pub surname: String,
+    #[diesel(sql_type = Nullable<Text>)]
+    pub party: Option<String>,
+    #[diesel(sql_type = BigInt)]
+    pub for_count: i64,
+    #[diesel(sql_type = BigInt)]
+    pub against_count: i64,
+    #[diesel(sql_type = BigInt)]
+    pub abstain_count: i64,
+    #[diesel(sql_type = BigInt)]
+    pub none_count: i64,
+    #[diesel(sql_type = BigInt)]
+    pub voted_count: i64,
+    #[diesel(sql_type = BigInt)]
+    pub all_count: i64,
+    #[diesel(sql_type = Double)]
+    pub for_percent: f64,
+    #[diesel(sql_type = Double)]
+    pub against_percent: f64,
+    #[diesel(sql_type = Double)]
+    pub abstain_percent: f64,
+    #[diesel(sql_type = Double)]
+    pub voted_percent: f64,
+}
+
+/// The filters are bound to fixed placeholders, one left NULL is not applied: `$1` parliament, `$2` session,
+/// `$3` and `$4` the first and last day, `$5` faction and `$6` whether the member is sitting.
+const MEMBER_VOTE_SUMMARIES: &str = r#"
+SELECT counts.*,
+    ROUND(counts.for_count::NUMERIC / GREATEST(counts.voted_count, 1) * 100, 2)::FLOAT8 AS for_percent,
+    ROUND(counts.against_count::NUMERIC / GREATEST(counts.voted_count, 1) * 100, 2)::FLOAT8 AS against_percent,
+    ROUND(counts.abstain_count::NUMERIC / GREATEST(counts.voted_count, 1) * 100, 2)::FLOAT8 AS abstain_percent,
+    ROUND(counts.voted_count::NUMERIC / GREATEST(counts.all_count, 1) * 100, 2)::FLOAT8 AS voted_percent
+FROM (
+    SELECT politician.id AS person_id, politician.parliament, politician.name, politician.surname, politician.party,
+        COUNT(*) FILTER (WHERE vote_data.vote = 'for') AS for_count,
+        COUNT(*) FILTER (WHERE vote_data.vote = 'against') AS against_count,
+        COUNT(*) FILTER (WHERE vote_data.vote = 'abstain') AS abstain_count,
+        COUNT(*) FILTER (WHERE vote_data.vote IS NULL) AS none_count,
+        COUNT(*) FILTER (WHERE vote_data.vote IS NOT NULL) AS voted_count,
+        COUNT(*) AS all_count
+    FROM vote_data
+    JOIN VOTE_SCOPE ON VOTE_SCOPE.vote_id = vote_data.id
+    JOIN politician ON politician.id = vote_data.person_id AND politician.parliament = VOTE_SCOPE.parliament_id
+    WHERE ($1::INT4 IS NULL OR VOTE_SCOPE.parliament_id = $1)
+        AND ($2::INT4 IS NULL OR VOTE_SCOPE.session_id = $2)
+        AND ($3::DATE IS NULL OR VOTE_SCOPE."day" >= $3)
+        AND ($4::DATE IS NULL OR VOTE_SCOPE."day" <= $4)
+        AND ($5::INT4 IS NULL OR EXISTS (SELECT 1 FROM faction_membership
+            WHERE faction_membership.politician_id = vote_data.person_id
+                AND faction_membership.parliament = VOTE_SCOPE.parliament_id
+                AND faction_membership.faction_id = $5
+                AND faction_membership."from" <= VOTE_SCOPE."day"
+                AND (faction_membership."to" IS NULL OR faction_membership."to" >= VOTE_SCOPE."day")))
+        AND ($6::BOOL IS NULL OR (politician."to" IS NULL) = $6)
+    GROUP BY politician.id, politician.parliament
+) counts
+ORDER BY counts.surname, counts.name, counts.person_id, counts.parliament
+"#;
+
+/// Ballot counts of each member matching the filter.
+pub fn get_member_vote_summaries(conn: &mut PgConnection, filter: &MemberVoteFilter) -> Result<Vec<MemberVoteSummary>, diesel::result::Error> {
+    let (parliament_id, session_id, from, to) = match filter.period {
+        Period::Parliament(parliament_id) => (Some(parliament_id), None, None, None),
+        Period::Session(session_id) => (None, Some(session_id), None, None),
+        Period::Range(from, to) => (None, None, Some(from), Some(to)),
+    };
+    let sitting = filter.status.map(|status| status == MemberStatus::Sitting);
+    diesel::sql_query(MEMBER_VOTE_SUMMARIES)
+        .bind::<Nullable<Int4>, _>(parliament_id)
+        .bind::<Nullable<Int4>, _>(session_id)
+        .bind::<Nullable<Date>, _>(from)
+        .bind::<Nullable<Date>, _>(to)
+        .bind::<Nullable<Int4>, _>(filter.faction_id)
+        .bind::<Nullable<Bool>, _>(sitting)
+        .load::<MemberVoteSummary>(conn)
+}
+
+/// Writes the ballot counts of each member matching the filter.
+pub async fn export_member_votes(ctx: &Context, filter: MemberVoteFilter, opts: &ExportOptions) -> Result<()> {
+    let rows = ctx.run(move |conn| get_member_vote_summaries(conn, &filter)).await?;
+    info!("Writing vote summaries of {} member(s)", rows.len());
+    write_rows(&rows, opts)
+}
@@ -0,0 +1,21 @@
+use log::info;
+
+use crate::database::context::Context;
+use crate::error::{Error, Result};
+
+pub mod attendance;
+pub mod cohesion;
+pub mod ideal_points;
+pub mod linalg;
+pub mod member_votes;
+pub mod similarity;
+pub mod vote_breakdown;
+
+/// Rebuilds the summary tables dashboards read from, run at the end of each crawl.
+pub async fn refresh_summaries(ctx: &Context) -> Result<()> {
+    let (factions, members) = ctx.run(|conn| cohesion::refresh_cohesion(conn).map_err(Error::database("cohesion summaries"))).await?;
+    info!("Refreshed cohesion of {} faction and {} member period(s)", factions, members);
+    let (sittings, members) = ctx.run(|conn| attendance::refresh_attendance(conn).map_err(Error::database("attendance summaries"))).await?;
+    info!("Refreshed attendance of {} member sitting(s) and {} member period(s)", sittings, members);
+    Ok(())
+}
\ No newline at end of file
@@ -0,0 +1,393 @@
+use std::collections::HashMap;
+
+use clap::ValueEnum;
+use diesel::prelude::*;
+use diesel::sql_types::{Int4, Nullable};
+use log::info;
+use serde::Serialize;
+
+use crate::analysis::linalg::Matrix;
+use crate::database::context::Context;
+use crate::error::Result;
+use crate::export::{write_rows, ExportOptions};
+use crate::models::politicians::politician::Politician;
+use crate::models::session::voting_data::VoteType;
+use crate::schema::sql_types::PqVoteType;
+
+/// How an abstention or a missing ballot enters the comparison of two members.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum BallotHandling {
+    /// The vote is left out for the pair.
+    Skip,
+    /// A choice of its own, agreeing only with the same choice.
+    Choice,
+    /// Counted as a vote against.
+    Against,
+}
+
+#[derive(Debug, Clone)]
+pub struct SimilarityOptions {
+    pub abstain: BallotHandling,
+    /// Members of the parliament who did not vote, whether listed in the results or not.
+    pub absent: BallotHandling,
+    /// Members who cast fewer ballots are left out of the matrix.
+    pub min_votes: usize,
+    pub clusters: usize,
+}
+
+impl Default for SimilarityOptions {
+    fn default() -> Self {
+        SimilarityOptions {
+            abstain: BallotHandling::Choice,
+            absent: BallotHandling::Skip,
+            min_votes: 10,
+            clusters: 2,
+        }
+    }
+}
+
+/// Position of a member on the first two principal components of the agreement matrix, and its voting bloc.
+#[derive(Debug, Clone, Serialize)]
+pub struct MemberPosition {
+    pub politician_id: i32,
+    pub name: String,
+    pub surname: String,
+    pub votes: usize,
+    pub pc1: f64,
+    pub pc2: f64,
+    pub cluster: usize,
+}
+
+/// Share of the votes both members took part in where they voted the same way.
+#[derive(Debug, Clone, Serialize)]
+pub struct PairAgreement {
+    pub politician_id: i32,
+    pub other_id: i32,
+    pub common_votes: u32,
+    pub agreement: Option<f64>,
+}
+
+#[derive(Debug, QueryableByName)]
+struct Ballot {
+    #[diesel(sql_type = Int4)]
+    vote_id: i32,
+    #[diesel(sql_type = Int4)]
+    person_id: i32,
+    #[diesel(sql_type = Nullable<PqVoteType>)]
+    vote: Option<VoteType>,
+}
+
+// Every member of the parliament on the day of every vote whose results were downloaded, with the ballot if one was cast.
+const BALLOTS_QUERY: &str = r#"
+SELECT VOTE_SCOPE.vote_id, politician.id AS person_id, vote_data.vote
+FROM VOTE_SCOPE
+JOIN politician ON politician.parliament = VOTE_SCOPE.parliament_id
+    AND (politician."from" IS NULL OR politician."from" <= VOTE_SCOPE."day")
+    AND (politician."to" IS NULL OR politician."to" >= VOTE_SCOPE."day")
+LEFT JOIN vote_data ON vote_data.id = VOTE_SCOPE.vote_id AND vote_data.person_id = politician.id
+WHERE VOTE_SCOPE.parliament_id = $1
+    AND EXISTS (SELECT 1 FROM vote_data results WHERE results.id = VOTE_SCOPE.vote_id)
+ORDER BY 1, 2
+"#;
+
+/// Pairwise agreement counts between the members of a parliament.
+#[derive(Debug, Clone)]
+pub struct AgreementMatrix {
+    pub members: Vec<i32>,
+    /// Ballots actually cast by each member.
+    pub votes: Vec<usize>,
+    common: Vec<u32>,
+    agreed: Vec<u32>,
+}
+
+impl AgreementMatrix {
+    fn build(ballots: &[Ballot], opts: &SimilarityOptions) -> AgreementMatrix {
+        let mut cast: HashMap<i32, usize> = HashMap::new();
+        for ballot in ballots {
+            let count = cast.entry(ballot.person_id).or_default();
+            if ballot.vote.is_some() {
+                *count += 1;
+            }
+        }
+        let mut members: Vec<i32> = cast.iter().filter(|(_, count)| **count >= opts.min_votes).map(|(id, _)| *id).collect();
+        members.sort_unstable();
+        let index: HashMap<i32, usize> = members.iter().enumerate().map(|(i, id)| (*id, i)).collect();
+        let n = members.len();
+
+        let mut matrix = AgreementMatrix {
+            votes: members.iter().map(|id| cast[id]).collect(),
+            members,
+            common: vec![0; n * n],
+            agreed: vec![0; n * n],
+        };
+        for vote in ballots.chunk_by(|a, b| a.vote_id == b.vote_id) {
+            let choices: Vec<(usize, u8)> = vote
+                .iter()
+                .filter_map(|ballot| Some((*index.get(&ballot.person_id)?, choice(ballot.vote, opts)?)))
+                .collect();
+            for (i, &(a, choice_a)) in choices.iter().enumerate() {
+                for &(b, choice_b) in &choices[i + 1..] {
+                    matrix.common[a * n + b] += 1;
+                    if choice_a == choice_b {
+                        matrix.agreed[a * n + b] += 1;
+                    }
+                }
+            }
+        }
+        for a in 0..n {
+            for b in 0..a {
+                matrix.common[a * n + b] = matrix.common[b * n + a];
+                matrix.agreed[a * n + b] = matrix.agreed[b * n + a];
+            }
+        }
+        matrix
+    }
+
+    pub fn agreement(&self, a: usize, b: usize) -> Option<f64> {
+        let n = self.members.len();
+        match self.common[a * n + b] {
+            0 => None,
+            common => Some(self.agreed[a * n + b] as f64 / common as f64),
+        }
+    }
+
+    /// Agreement of every pair, the pairs that never voted together get the mean agreement of the others.
+    pub fn filled(&self) -> Matrix {
+        let n = self.members.len();
+        let known: Vec<f64> = (0..n).flat_map(|a| (0..a).map(move |b| (a, b))).filter_map(|(a, b)| self.agreement(a, b)).collect();
+        let mean = if known.is_empty() { 0.5 } else { known.iter().sum::<f64>() / known.len() as f64 };
+        let mut matrix = Matrix::zeros(n);
+        for a in 0..n {
+            for b in 0..n {
+                let value = if a == b { 1.0 } else { self.agreement(a, b).unwrap_or(mean) };
+                matrix.set(a, b, value);
+            }
+        }
+        matrix
+    }
+
+    pub fn pairs(&self) -> Vec<PairAgreement> {
+        let n = self.members.len();
+        (0..n)
+            .flat_map(|a| (a + 1..n).map(move |b| (a, b)))
+            .map(|(a, b)| PairAgreement {
+                politician_id: self.members[a],
+                other_id: self.members[b],
+                common_votes: self.common[a * n + b],
+                agreement: self.agreement(a, b),
+            })
+            .collect()
+    }
+}
+
+/// Code of the choice a ballot stands for, `None` when the vote is skipped for this member.
+fn choice(vote: Option<VoteType>, opts: &SimilarityOptions) -> Option<u8> {
+    let handled = |handling: BallotHandling, own: u8| match handling {
+        BallotHandling::Skip => None,
+        BallotHandling::Choice => Some(own),
+        BallotHandling::Against => Some(1),
+    };
+    match vote {
+        Some(VoteType::For) => Some(0),
+        Some(VoteType::Against) => Some(1),
+        Some(VoteType::Abstain) => handled(opts.abstain, 2),
+        None => handled(opts.absent, 3),
+    }
+}
+
+/// First two principal components of the double-centred agreement matrix, scaled by their spread.
+fn principal_components(agreement: &Matrix) -> Vec<(f64, f64)> {
+    let n = agreement.size;
+    let eigen = agreement.double_centered().symmetric_eigen();
+    let component = |k: usize| -> Vec<f64> {
+        match eigen.get(k) {
+            Some((value, vector)) => vector.iter().map(|x| x * value.max(0.0).sqrt()).collect(),
+            None => vec![0.0; n],
+        }
+    };
+    let (first, second) = (component(0), component(1));
+    first.into_iter().zip(second).collect()
+}
+
+/// Average-linkage agglomerative clustering on `1 - agreement`, stopped at `count` clusters.
+///
+/// Clusters are labelled from 0 by decreasing size.
+fn cluster(agreement: &Matrix, count: usize) -> Vec<usize> {
+    let n = agreement.size;
+    // Each cluster is kept under the index of one of its members, whose row holds the cluster distances.
+    let mut clusters: Vec<(usize, Vec<usize>)> = (0..n).map(|i| (i, vec![i])).collect();
+    let mut distance = Matrix::zeros(n);
+    for a in 0..n {
+        for b in 0..n {
+            distance.set(a, b, 1.0 - agreement.get(a, b));
+        }
+    }
+
+    while clusters.len() > count.max(1) {
+        let (a, b) = (0..clusters.len())
+            .flat_map(|a| (a + 1..clusters.len()).map(move |b| (a, b)))
+            .min_by(|&(a, b), &(c, d)| distance.get(clusters[a].0, clusters[b].0).total_cmp(&distance.get(clusters[c].0, clusters[d].0)))
+            .expect("at least two clusters");
+        let (merged, absorbed) = (clusters[a].0, clusters[b].0);
+        let (size_a, size_b) = (clusters[a].1.len() as f64, clusters[b].1.len() as f64);
+        for (other, _) in &clusters {
+            let value = (size_a * distance.get(merged, *other) + size_b * distance.get(absorbed, *other)) / (size_a + size_b);
+            distance.set(merged, *other, value);
+            distance.set(*other, merged, value);
+        }
+        let (_, members) = clusters.remove(b);
+        clusters[a].1.extend(members);
+    }
+
+    clusters.sort_by_key(|(_, members)| (std::cmp::Reverse(members.len()), members.iter().min().copied()));
+    let mut labels = vec![0; n];
+    for (label, (_, members)) in clusters.iter().enumerate() {
+        for &member in members {
+            labels[member] = label;
+        }
+    }
+    labels
+}
+
+pub fn get_agreement_matrix(conn: &mut PgConnection, parliament_id: i32, opts: &SimilarityOptions) -> Result<AgreementMatrix, diesel::result::Error> {
+    let ballots = diesel::sql_query(BALLOTS_QUERY)
+        .bind::<Int4, _>(parliament_id)
+        .load::<Ballot>(conn)?;
+    Ok(AgreementMatrix::build(&ballots, opts))
+}
+
+/// Places each member on the principal components of the agreement matrix and assigns a voting bloc.
+pub fn get_member_positions(conn: &mut PgConnection, parliament_id: i32, opts: &SimilarityOptions) -> Result<Vec<MemberPosition>, diesel::result::Error> {
+    let matrix = get_agreement_matrix(conn, parliament_id, opts)?;
+    let names: HashMap<i32, (String, String)> = Politician::get_names_per_parliament(conn, parliament_id)?
+        .into_iter()
+        .map(|(id, name, surname)| (id, (name, surname)))
+        .collect();
+
+    let agreement = matrix.filled();
+    let components = principal_components(&agreement);
+    let labels = cluster(&agreement, opts.clusters);
+    Ok(matrix
+        .members
+        .iter()
+        .enumerate()
+        .map(|(i, id)| {
+            let (name, surname) = names.get(id).cloned().unwrap_or_default();
+            MemberPosition {
+                politician_id: *id,
+                name,
+                surname,
+                votes: matrix.votes[i],
+                pc1: components[i].0,
+                pc2: components[i].1,
+                cluster: labels[i],
+            }
+        })
+        .collect())
+}
+
+/// Writes the position and bloc of each member of a parliament, or the agreement of each pair when `pairs` is set.
+pub async fn export_similarity(ctx: &Context, parliament_id: i32, opts: SimilarityOptions, pairs: bool, export: &ExportOptions) -> Result<()> {
+    if pairs {
+        let rows = ctx.run(move |conn| get_agreement_matrix(conn, parliament_id, &opts).map(|matrix| matrix.pairs())).await?;
+        info!("Writing agreement of {} pair(s) of members of parliament {}", rows.len(), parliament_id);
+        write_rows(&rows, export)
+    } else {
+        let rows = ctx.run(move |conn| get_member_positions(conn, parliament_id, &opts)).await?;
+        info!("Writing positions of {} member(s) of parliament {}", rows.len(), parliament_id);
+        write_rows(&rows, export)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ballots() -> Vec<Ballot> {
+        let ballot = |vote_id, person_id, vote| Ballot { vote_id, person_id, vote };
+        vec![
+            ballot(1, 1, Some(VoteType::For)),
+            ballot(1, 2, Some(VoteType::For)),
+            ballot(1, 3, Some(VoteType::Against)),
+            ballot(2, 1, Some(VoteType::Abstain)),
+            ballot(2, 2, Some(VoteType::Abstain)),
+            ballot(2, 3, Some(VoteType::For)),
+            ballot(3, 1, Some(VoteType::For)),
+            ballot(3, 2, None),
+            ballot(3, 3, Some(VoteType::Against)),
+        ]
+    }
+
+    fn options(abstain: BallotHandling, absent: BallotHandling) -> SimilarityOptions {
+        SimilarityOptions { abstain, absent, min_votes: 0, ..SimilarityOptions::default() }
+    }
+
+    /// Common and agreeing votes of each pair of members.
+    fn counts(matrix: &AgreementMatrix) -> Vec<(i32, i32, u32, u32)> {
+        let n = matrix.members.len();
+        (0..n)
+            .flat_map(|a| (a + 1..n).map(move |b| (a, b)))
+            .map(|(a, b)| (matrix.members[a], matrix.members[b], matrix.common[a * n + b], matrix.agreed[a * n + b]))
+            .collect()
+    }
+
+    #[test]
+    fn codes_choices_per_handling() {
+        let skip = options(BallotHandling::Skip, BallotHandling::Skip);
+        assert_eq!(choice(Some(VoteType::For), &skip), Some(0));
+        assert_eq!(choice(Some(VoteType::Against), &skip), Some(1));
+        assert_eq!(choice(Some(VoteType::Abstain), &skip), None);
+        assert_eq!(choice(None, &skip), None);
+
+        let own = options(BallotHandling::Choice, BallotHandling::Choice);
+        assert_eq!(choice(Some(VoteType::Abstain), &own), Some(2));
+        assert_eq!(choice(None, &own), Some(3));
+
+        let against = options(BallotHandling::Against, BallotHandling::Against);
+        assert_eq!(choice(Some(VoteType::Abstain), &against), Some(1));
+        assert_eq!(choice(None, &against), Some(1));
+    }
+
+    #[test]
+    fn skips_abstentions_and_absences() {
+        let matrix = AgreementMatrix::build(&ballots(), &options(BallotHandling::Skip, BallotHandling::Skip));
+        assert_eq!(matrix.members, vec![1, 2, 3]);
+        assert_eq!(matrix.votes, vec![3, 2, 3]);
+        assert_eq!(counts(&matrix), vec![(1, 2, 1, 1), (1, 3, 2, 0), (2, 3, 1, 0)]);
+        assert_eq!(matrix.agreement(1, 0), Some(1.0));
+    }
+
+    #[test]
+    fn counts_abstentions_and_absences_as_choices_of_their_own() {
+        let matrix = AgreementMatrix::build(&ballots(), &options(BallotHandling::Choice, BallotHandling::Choice));
+        assert_eq!(counts(&matrix), vec![(1, 2, 3, 2), (1, 3, 3, 0), (2, 3, 3, 0)]);
+    }
+
+    #[test]
+    fn counts_abstentions_and_absences_as_votes_against() {
+        let matrix = AgreementMatrix::build(&ballots(), &options(BallotHandling::Against, BallotHandling::Against));
+        assert_eq!(counts(&matrix), vec![(1, 2, 3, 2), (1, 3, 3, 0), (2, 3, 3, 1)]);
+    }
+
+    #[test]
+    fn leaves_out_members_with_few_ballots() {
+        let opts = SimilarityOptions { min_votes: 3, ..options(BallotHandling::Choice, BallotHandling::Choice) };
+        let matrix = AgreementMatrix::build(&ballots(), &opts);
+        assert_eq!(matrix.members, vec![1, 3]);
+        assert_eq!(counts(&matrix), vec![(1, 3, 3, 0)]);
+    }
+
+    #[test]
+    fn clusters_two_blocs() {
+        let blocs = [1, 0, 0, 1, 0];
+        let mut agreement = Matrix::zeros(blocs.len());
+        for a in 0..blocs.len() {
+            for b in 0..blocs.len() {
+                let value = if a == b { 1.0 } else if blocs[a] == blocs[b] { 0.9 } else { 0.15 };
+                agreement.set(a, b, value);
+            }
+        }
+        assert_eq!(cluster(&agreement, 2), vec![1, 0, 0, 1, 0]);
+        assert_eq!(cluster(&agreement, 1), vec![0; 5]);
+    }
+}
@@ -0,0 +1,103 @@
+use diesel::prelude::*;
+use diesel::sql_types::{Array, BigInt, Int4, Nullable, Text};
+use log::info;
+use serde::Serialize;
+
+use crate::database::context::Context;
+use crate::error::Result;
+use crate::export::{write_rows, ExportOptions};
+use crate::models::session::{meeting_data::{AgendaItem, MeetingData}, meetings::Meetings, sessions::Sessions};
+
+/// How the members of one faction voted in one roll call.
+///
+/// `faction_id` is empty for voters with no known faction on the day of the vote.
+/// `absent` counts faction members who did not cast a vote, whether or not they are listed in the results.
+#[derive(Debug, Clone, QueryableByName, Serialize)]
+pub struct VoteBreakdown {
+    #[diesel(sql_type = Int4)]
+    pub vote_id: i32,
+    #[diesel(sql_type = Nullable<Int4>)]
+    pub faction_id: Option<i32>,
+    #[diesel(sql_type = Nullable<Text>)]
+    pub faction: Option<String>,
+    #[diesel(sql_type = BigInt)]
+    pub for_count: i64,
+    #[diesel(sql_type = BigInt)]
+    pub against_count: i64,
+    #[diesel(sql_type = BigInt)]
+    pub abstain_count: i64,
+    #[diesel(sql_type = BigInt)]
+    pub absent_count: i64,
+}
+
+// Listed voters take their faction from VOTE_FACTION, members of the parliament missing from the results
+// count as absent for the faction they were in on the day of the vote, as long as their mandate covers it.
+const VOTE_BREAKDOWN_QUERY: &str = r#"
+WITH members AS (
+    SELECT VOTE_FACTION.vote_id, VOTE_FACTION.person_id, VOTE_FACTION.faction_id
+    FROM VOTE_FACTION
+    WHERE VOTE_FACTION.vote_id = ANY($1)
+    UNION ALL
+    (SELECT DISTINCT ON (VOTE_SCOPE.vote_id, faction_membership.politician_id)
+        VOTE_SCOPE.vote_id, faction_membership.politician_id AS person_id, faction_membership.faction_id
+    FROM VOTE_SCOPE
+    JOIN faction_membership ON faction_membership.parliament = VOTE_SCOPE.parliament_id
+        AND faction_membership."from" <= VOTE_SCOPE."day"
+        AND (faction_membership."to" IS NULL OR faction_membership."to" >= VOTE_SCOPE."day")
+    JOIN politician ON politician.id = faction_membership.politician_id AND politician.parliament = VOTE_SCOPE.parliament_id
+        AND (politician."from" IS NULL OR politician."from" <= VOTE_SCOPE."day")
+        AND (politician."to" IS NULL OR politician."to" >= VOTE_SCOPE."day")
+    WHERE VOTE_SCOPE.vote_id = ANY($1)
+        AND NOT EXISTS (SELECT 1 FROM vote_data WHERE vote_data.id = VOTE_SCOPE.vote_id AND vote_data.person_id = faction_membership.politician_id)
+    ORDER BY VOTE_SCOPE.vote_id, faction_membership.politician_id, faction_membership."from" DESC)
+), ballots AS (
+    SELECT vote_data.id AS vote_id, vote_data.person_id, vote_data.vote
+    FROM vote_data
+    WHERE vote_data.id = ANY($1)
+)
+SELECT
+    COALESCE(members.vote_id, ballots.vote_id) AS vote_id,
+    members.faction_id,
+    COALESCE(faction.short_name, faction.name) AS faction,
+    COUNT(*) FILTER (WHERE ballots.vote = 'for') AS for_count,
+    COUNT(*) FILTER (WHERE ballots.vote = 'against') AS against_count,
+    COUNT(*) FILTER (WHERE ballots.vote = 'abstain') AS abstain_count,
+    COUNT(*) FILTER (WHERE ballots.vote IS NULL) AS absent_count
+FROM members
+FULL JOIN ballots ON ballots.vote_id = members.vote_id AND ballots.person_id = members.person_id
+LEFT JOIN faction ON faction.id = members.faction_id
+GROUP BY 1, 2, 3
+ORDER BY 1, 2
+"#;
+
+/// For/against/abstain/absent counts per faction for each of the given votes.
+pub fn get_vote_breakdown(conn: &mut PgConnection, vote_ids: &[i32]) -> Result<Vec<VoteBreakdown>, diesel::result::Error> {
+    diesel::sql_query(VOTE_BREAKDOWN_QUERY)
+        .bind::<Array<Int4>, _>(vote_ids)
+        .load::<VoteBreakdown>(conn)
+}
+
+/// IDs of every vote held during a parliament.
+pub fn get_vote_ids_per_parliament(conn: &mut PgConnection, parliament_id: i32) -> Result<Vec<i32>, diesel::result::Error> {
+    let session_ids = Sessions::get_sessions_per_parliament(conn, parliament_id)?;
+    let meeting_ids: Vec<i32> = Meetings::get_meetings_per_sessions(conn, &session_ids)?.into_iter().map(|meeting| meeting.id).collect();
+    let agenda_ids = MeetingData::get_agenda_ids(conn, &meeting_ids)?;
+    AgendaItem::get_vote_ids(conn, &agenda_ids)
+}
+
+/// Writes the faction breakdown of the given votes and of every vote of the given parliaments.
+pub async fn export_vote_breakdown(ctx: &Context, parliament_ids: Vec<i32>, vote_ids: Vec<i32>, opts: &ExportOptions) -> Result<()> {
+    let rows = ctx
+        .run(move |conn| {
+            let mut vote_ids = vote_ids;
+            for parliament_id in parliament_ids {
+                vote_ids.extend(get_vote_ids_per_parliament(conn, parliament_id)?);
+            }
+            vote_ids.sort_unstable();
+            vote_ids.dedup();
+            get_vote_breakdown(conn, &vote_ids)
+        })
+        .await?;
+    info!("Writing {} vote breakdown row(s)", rows.len());
+    write_rows(&rows, opts)
+}
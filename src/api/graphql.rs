@@ -0,0 +1,198 @@
+use async_graphql::dataloader::DataLoader;
+use async_graphql::http::GraphiQLSource;
+use async_graphql::{ComplexObject, Context, EmptyMutation, EmptySubscription, Object, Schema};
+use async_graphql_axum::{GraphQLRequest, GraphQLResponse};
+use axum::extract::State;
+use axum::response::Html;
+use axum::Extension;
+use diesel::pg::PgConnection;
+use diesel::prelude::*;
+
+use crate::api::loader::{AgendaItemSpeeches, AgendaItemVotes, DbLoader, MeetingAgendaItems, MeetingDataId, MeetingRegistrations, ParliamentSessions, PoliticianTerm, RegistrationMembers, SessionMeetings, TermOffices, VoteBallots, VoteParliament};
+use crate::api::queries::{self, ListFilter, Page};
+use crate::database::context;
+use crate::models::politicians::politician::{OfficeQueryable, Politician};
+use crate::models::session::meeting_data::{AgendaItem, MeetingData, Registration, Speech, Vote};
+use crate::models::session::meetings::Meetings;
+use crate::models::session::parliament::Parliament;
+use crate::models::session::registration_data::RegistrationData;
+use crate::models::session::sessions::Sessions;
+use crate::models::session::voting_data::VoteData;
+use crate::schema::{agenda_item, meetings, registration, vote};
+
+/// Nesting deeper than parliament down to the offices of a member is refused.
+const MAX_DEPTH: usize = 16;
+
+pub type ApiSchema = Schema<QueryRoot, EmptyMutation, EmptySubscription>;
+
+pub fn schema(ctx: context::Context) -> ApiSchema {
+    Schema::build(QueryRoot, EmptyMutation, EmptySubscription).data(ctx).limit_depth(MAX_DEPTH).finish()
+}
+
+/// Answers a query with a loader of its own, so batches and their cache last one request.
+pub async fn execute(State(ctx): State<context::Context>, Extension(schema): Extension<ApiSchema>, request: GraphQLRequest) -> GraphQLResponse {
+    let loader = DataLoader::new(DbLoader::new(ctx), tokio::spawn);
+    schema.execute(request.into_inner().data(loader)).await.into()
+}
+
+pub async fn graphiql() -> Html<String> {
+    Html(GraphiQLSource::build().endpoint("/graphql").finish())
+}
+
+fn loader<'a>(ctx: &Context<'a>) -> &'a DataLoader<DbLoader> {
+    ctx.data_unchecked::<DataLoader<DbLoader>>()
+}
+
+async fn run<T, F>(ctx: &Context<'_>, query: F) -> async_graphql::Result<T>
+where
+    F: FnOnce(&mut PgConnection) -> Result<T, diesel::result::Error> + Send + 'static,
+    T: Send + 'static,
+{
+    Ok(ctx.data_unchecked::<context::Context>().run(query).await?)
+}
+
+pub struct QueryRoot;
+
+#[Object]
+impl QueryRoot {
+    async fn parliaments(&self, ctx: &Context<'_>, #[graphql(default)] filter: ListFilter) -> async_graphql::Result<Page<Parliament>> {
+        run(ctx, move |conn| queries::list_parliaments(conn, &filter)).await
+    }
+
+    async fn parliament(&self, ctx: &Context<'_>, id: i32) -> async_graphql::Result<Option<Parliament>> {
+        run(ctx, move |conn| queries::get_parliament(conn, id).optional()).await
+    }
+
+    async fn sessions(&self, ctx: &Context<'_>, #[graphql(default)] filter: ListFilter) -> async_graphql::Result<Page<Sessions>> {
+        run(ctx, move |conn| queries::list_sessions(conn, &filter)).await
+    }
+
+    async fn session(&self, ctx: &Context<'_>, id: i32) -> async_graphql::Result<Option<Sessions>> {
+        run(ctx, move |conn| queries::get_session(conn, id).optional()).await
+    }
+
+    async fn meetings(&self, ctx: &Context<'_>, #[graphql(default)] filter: ListFilter) -> async_graphql::Result<Page<Meetings>> {
+        run(ctx, move |conn| queries::list_meetings(conn, &filter)).await
+    }
+
+    async fn meeting(&self, ctx: &Context<'_>, id: i32) -> async_graphql::Result<Option<Meetings>> {
+        run(ctx, move |conn| meetings::table.find(id).first::<Meetings>(conn).optional()).await
+    }
+
+    async fn agenda_items(&self, ctx: &Context<'_>, #[graphql(default)] filter: ListFilter) -> async_graphql::Result<Page<AgendaItem>> {
+        run(ctx, move |conn| queries::list_agenda_items(conn, &filter)).await
+    }
+
+    async fn agenda_item(&self, ctx: &Context<'_>, id: i32) -> async_graphql::Result<Option<AgendaItem>> {
+        run(ctx, move |conn| agenda_item::table.find(id).first::<AgendaItem>(conn).optional()).await
+    }
+
+    async fn votes(&self, ctx: &Context<'_>, #[graphql(default)] filter: ListFilter) -> async_graphql::Result<Page<Vote>> {
+        run(ctx, move |conn| queries::list_votes(conn, &filter)).await
+    }
+
+    async fn vote(&self, ctx: &Context<'_>, id: i32) -> async_graphql::Result<Option<Vote>> {
+        run(ctx, move |conn| vote::table.find(id).first::<Vote>(conn).optional()).await
+    }
+
+    async fn registrations(&self, ctx: &Context<'_>, #[graphql(default)] filter: ListFilter) -> async_graphql::Result<Page<Registration>> {
+        run(ctx, move |conn| queries::list_registrations(conn, &filter)).await
+    }
+
+    async fn registration(&self, ctx: &Context<'_>, id: i32) -> async_graphql::Result<Option<Registration>> {
+        run(ctx, move |conn| registration::table.find(id).first::<Registration>(conn).optional()).await
+    }
+
+    async fn speeches(&self, ctx: &Context<'_>, #[graphql(default)] filter: ListFilter) -> async_graphql::Result<Page<Speech>> {
+        run(ctx, move |conn| queries::list_speeches(conn, &filter)).await
+    }
+
+    async fn speech(&self, ctx: &Context<'_>, id: i32) -> async_graphql::Result<Option<Speech>> {
+        run(ctx, move |conn| queries::get_speech(conn, id).optional()).await
+    }
+
+    async fn politicians(&self, ctx: &Context<'_>, #[graphql(default)] filter: ListFilter) -> async_graphql::Result<Page<Politician>> {
+        run(ctx, move |conn| queries::list_politicians(conn, &filter)).await
+    }
+
+    /// Every term of a member, one per parliament.
+    async fn politician(&self, ctx: &Context<'_>, id: i32) -> async_graphql::Result<Vec<Politician>> {
+        run(ctx, move |conn| Politician::get_terms(conn, id)).await
+    }
+}
+
+#[ComplexObject]
+impl Parliament {
+    async fn sessions(&self, ctx: &Context<'_>) -> async_graphql::Result<Vec<Sessions>> {
+        Ok(loader(ctx).load_one(ParliamentSessions(self.id)).await?.unwrap_or_default())
+    }
+}
+
+#[ComplexObject]
+impl Sessions {
+    async fn meetings(&self, ctx: &Context<'_>) -> async_graphql::Result<Vec<Meetings>> {
+        Ok(loader(ctx).load_one(SessionMeetings(self.id)).await?.unwrap_or_default())
+    }
+}
+
+#[ComplexObject]
+impl Meetings {
+    /// Actual times and agenda of the sitting, once its data was downloaded.
+    async fn data(&self, ctx: &Context<'_>) -> async_graphql::Result<Option<MeetingData>> {
+        Ok(loader(ctx).load_one(MeetingDataId(self.id)).await?)
+    }
+}
+
+#[ComplexObject]
+impl MeetingData {
+    async fn agenda_items(&self, ctx: &Context<'_>) -> async_graphql::Result<Vec<AgendaItem>> {
+        Ok(loader(ctx).load_one(MeetingAgendaItems(self.id)).await?.unwrap_or_default())
+    }
+
+    async fn registrations(&self, ctx: &Context<'_>) -> async_graphql::Result<Vec<Registration>> {
+        Ok(loader(ctx).load_one(MeetingRegistrations(self.id)).await?.unwrap_or_default())
+    }
+}
+
+#[ComplexObject]
+impl AgendaItem {
+    async fn votes(&self, ctx: &Context<'_>) -> async_graphql::Result<Vec<Vote>> {
+        Ok(loader(ctx).load_one(AgendaItemVotes(self.id)).await?.unwrap_or_default())
+    }
+
+    async fn speeches(&self, ctx: &Context<'_>) -> async_graphql::Result<Vec<Speech>> {
+        Ok(loader(ctx).load_one(AgendaItemSpeeches(self.id)).await?.unwrap_or_default())
+    }
+}
+
+#[ComplexObject]
+impl Vote {
+    async fn ballots(&self, ctx: &Context<'_>) -> async_graphql::Result<Vec<VoteData>> {
+        Ok(loader(ctx).load_one(VoteBallots(self.id)).await?.unwrap_or_default())
+    }
+}
+
+#[ComplexObject]
+impl VoteData {
+    /// The member as of the parliament the vote was held in.
+    async fn politician(&self, ctx: &Context<'_>) -> async_graphql::Result<Option<Politician>> {
+        match loader(ctx).load_one(VoteParliament(self.id)).await? {
+            Some(parliament) => Ok(loader(ctx).load_one(PoliticianTerm(self.person_id, parliament)).await?),
+            None => Ok(None),
+        }
+    }
+}
+
+#[ComplexObject]
+impl Registration {
+    async fn members(&self, ctx: &Context<'_>) -> async_graphql::Result<Vec<RegistrationData>> {
+        Ok(loader(ctx).load_one(RegistrationMembers(self.id)).await?.unwrap_or_default())
+    }
+}
+
+#[ComplexObject]
+impl Politician {
+    async fn offices(&self, ctx: &Context<'_>) -> async_graphql::Result<Vec<OfficeQueryable>> {
+        Ok(loader(ctx).load_one(TermOffices(self.id, self.parliament)).await?.unwrap_or_default())
+    }
+}
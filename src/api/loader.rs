@@ -0,0 +1,303 @@
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::sync::Arc;
+
+use async_graphql::async_trait::async_trait;
+use async_graphql::dataloader::Loader;
+use diesel::pg::PgConnection;
+use diesel::prelude::*;
+
+use crate::database::context::Context;
+use crate::error::Error;
+use crate::models::politicians::politician::{OfficeQueryable, Politician};
+use crate::models::session::meeting_data::{AgendaItem, MeetingData, Registration, Speech, Vote};
+use crate::models::session::meetings::Meetings;
+use crate::models::session::registration_data::RegistrationData;
+use crate::models::session::sessions::Sessions;
+use crate::models::session::voting_data::VoteData;
+use crate::schema::{agenda_item, agenda_speech, agenda_vote, meeting_agenda_item, meeting_data, meeting_registration, meetings, office, politician, politician_office, registration, registration_data, sessions, speech, vote, vote_data};
+
+/// Sessions of a parliament.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ParliamentSessions(pub i32);
+
+/// Meetings of a session.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SessionMeetings(pub i32);
+
+/// Data of a meeting, keyed by the meeting id.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct MeetingDataId(pub i32);
+
+/// Agenda of a meeting, in order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct MeetingAgendaItems(pub i32);
+
+/// Registrations of a meeting, in order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct MeetingRegistrations(pub i32);
+
+/// Votes on an agenda item, in order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct AgendaItemVotes(pub i32);
+
+/// Speeches on an agenda item, in order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct AgendaItemSpeeches(pub i32);
+
+/// Ballots of a vote.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct VoteBallots(pub i32);
+
+/// Parliament a vote was held in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct VoteParliament(pub i32);
+
+/// Members listed in a registration.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct RegistrationMembers(pub i32);
+
+/// A member during one parliament, `(politician_id, parliament)`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PoliticianTerm(pub i32, pub i32);
+
+/// Offices held by a member during one parliament, `(politician_id, parliament)`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TermOffices(pub i32, pub i32);
+
+/// Batches the lookups of the GraphQL resolvers into one query per relation and request.
+pub struct DbLoader {
+    ctx: Context,
+}
+
+impl DbLoader {
+    pub fn new(ctx: Context) -> DbLoader {
+        DbLoader { ctx }
+    }
+
+    /// Runs a query returning the children of the given parents, tagged with the parent, and groups them.
+    async fn grouped<I, K, V, F>(&self, ids: Vec<I>, key: fn(I) -> K, query: F) -> Result<HashMap<K, Vec<V>>, Arc<Error>>
+    where
+        I: Send + 'static,
+        K: Hash + Eq,
+        V: Send + 'static,
+        F: FnOnce(&mut PgConnection, Vec<I>) -> Result<Vec<(I, V)>, diesel::result::Error> + Send + 'static,
+    {
+        let rows = self.ctx.run(move |conn| query(conn, ids)).await.map_err(Arc::new)?;
+        let mut groups: HashMap<K, Vec<V>> = HashMap::new();
+        for (id, value) in rows {
+            groups.entry(key(id)).or_default().push(value);
+        }
+        Ok(groups)
+    }
+
+    /// Runs a query returning at most one row per key.
+    async fn keyed<I, K, V, F>(&self, ids: Vec<I>, key: fn(I) -> K, query: F) -> Result<HashMap<K, V>, Arc<Error>>
+    where
+        I: Send + 'static,
+        K: Hash + Eq,
+        V: Send + 'static,
+        F: FnOnce(&mut PgConnection, Vec<I>) -> Result<Vec<(I, V)>, diesel::result::Error> + Send + 'static,
+    {
+        let rows = self.ctx.run(move |conn| query(conn, ids)).await.map_err(Arc::new)?;
+        Ok(rows.into_iter().map(|(id, value)| (key(id), value)).collect())
+    }
+}
+
+#[async_trait]
+impl Loader<ParliamentSessions> for DbLoader {
+    type Value = Vec<Sessions>;
+    type Error = Arc<Error>;
+
+    async fn load(&self, keys: &[ParliamentSessions]) -> Result<HashMap<ParliamentSessions, Self::Value>, Self::Error> {
+        self.grouped(keys.iter().map(|key| key.0).collect(), ParliamentSessions, |conn, ids| {
+            let rows = sessions::table.filter(sessions::parliament.eq_any(ids)).order(sessions::id).load::<Sessions>(conn)?;
+            Ok(rows.into_iter().map(|session| (session.parliament, session)).collect())
+        })
+        .await
+    }
+}
+
+#[async_trait]
+impl Loader<SessionMeetings> for DbLoader {
+    type Value = Vec<Meetings>;
+    type Error = Arc<Error>;
+
+    async fn load(&self, keys: &[SessionMeetings]) -> Result<HashMap<SessionMeetings, Self::Value>, Self::Error> {
+        self.grouped(keys.iter().map(|key| key.0).collect(), SessionMeetings, |conn, ids| {
+            let rows = meetings::table.filter(meetings::session.eq_any(ids)).order((meetings::from, meetings::id)).load::<Meetings>(conn)?;
+            Ok(rows.into_iter().map(|meeting| (meeting.session, meeting)).collect())
+        })
+        .await
+    }
+}
+
+#[async_trait]
+impl Loader<MeetingDataId> for DbLoader {
+    type Value = MeetingData;
+    type Error = Arc<Error>;
+
+    async fn load(&self, keys: &[MeetingDataId]) -> Result<HashMap<MeetingDataId, Self::Value>, Self::Error> {
+        self.keyed(keys.iter().map(|key| key.0).collect(), MeetingDataId, |conn, ids| {
+            let rows = meeting_data::table.filter(meeting_data::id.eq_any(ids)).load::<MeetingData>(conn)?;
+            Ok(rows.into_iter().map(|data| (data.id, data)).collect())
+        })
+        .await
+    }
+}
+
+#[async_trait]
+impl Loader<MeetingAgendaItems> for DbLoader {
+    type Value = Vec<AgendaItem>;
+    type Error = Arc<Error>;
+
+    async fn load(&self, keys: &[MeetingAgendaItems]) -> Result<HashMap<MeetingAgendaItems, Self::Value>, Self::Error> {
+        self.grouped(keys.iter().map(|key| key.0).collect(), MeetingAgendaItems, |conn, ids| {
+            meeting_agenda_item::table
+                .inner_join(agenda_item::table)
+                .filter(meeting_agenda_item::meeting_id.eq_any(ids))
+                .order((meeting_agenda_item::meeting_id, meeting_agenda_item::position))
+                .select((meeting_agenda_item::meeting_id, agenda_item::all_columns))
+                .load::<(i32, AgendaItem)>(conn)
+        })
+        .await
+    }
+}
+
+#[async_trait]
+impl Loader<MeetingRegistrations> for DbLoader {
+    type Value = Vec<Registration>;
+    type Error = Arc<Error>;
+
+    async fn load(&self, keys: &[MeetingRegistrations]) -> Result<HashMap<MeetingRegistrations, Self::Value>, Self::Error> {
+        self.grouped(keys.iter().map(|key| key.0).collect(), MeetingRegistrations, |conn, ids| {
+            meeting_registration::table
+                .inner_join(registration::table)
+                .filter(meeting_registration::meeting_id.eq_any(ids))
+                .order((meeting_registration::meeting_id, meeting_registration::position))
+                .select((meeting_registration::meeting_id, registration::all_columns))
+                .load::<(i32, Registration)>(conn)
+        })
+        .await
+    }
+}
+
+#[async_trait]
+impl Loader<AgendaItemVotes> for DbLoader {
+    type Value = Vec<Vote>;
+    type Error = Arc<Error>;
+
+    async fn load(&self, keys: &[AgendaItemVotes]) -> Result<HashMap<AgendaItemVotes, Self::Value>, Self::Error> {
+        self.grouped(keys.iter().map(|key| key.0).collect(), AgendaItemVotes, |conn, ids| {
+            agenda_vote::table
+                .inner_join(vote::table)
+                .filter(agenda_vote::agenda_item_id.eq_any(ids))
+                .order((agenda_vote::agenda_item_id, agenda_vote::position))
+                .select((agenda_vote::agenda_item_id, vote::all_columns))
+                .load::<(i32, Vote)>(conn)
+        })
+        .await
+    }
+}
+
+#[async_trait]
+impl Loader<AgendaItemSpeeches> for DbLoader {
+    type Value = Vec<Speech>;
+    type Error = Arc<Error>;
+
+    async fn load(&self, keys: &[AgendaItemSpeeches]) -> Result<HashMap<AgendaItemSpeeches, Self::Value>, Self::Error> {
+        self.grouped(keys.iter().map(|key| key.0).collect(), AgendaItemSpeeches, |conn, ids| {
+            agenda_speech::table
+                .inner_join(speech::table)
+                .filter(agenda_speech::agenda_item_id.eq_any(ids))
+                .order((agenda_speech::agenda_item_id, agenda_speech::position))
+                .select((agenda_speech::agenda_item_id, speech::all_columns))
+                .load::<(i32, Speech)>(conn)
+        })
+        .await
+    }
+}
+
+#[async_trait]
+impl Loader<VoteBallots> for DbLoader {
+    type Value = Vec<VoteData>;
+    type Error = Arc<Error>;
+
+    async fn load(&self, keys: &[VoteBallots]) -> Result<HashMap<VoteBallots, Self::Value>, Self::Error> {
+        self.grouped(keys.iter().map(|key| key.0).collect(), VoteBallots, |conn, ids| {
+            let rows = vote_data::table.filter(vote_data::id.eq_any(ids)).order((vote_data::id, vote_data::person_id)).load::<VoteData>(conn)?;
+            Ok(rows.into_iter().map(|ballot| (ballot.id, ballot)).collect())
+        })
+        .await
+    }
+}
+
+#[async_trait]
+impl Loader<VoteParliament> for DbLoader {
+    type Value = i32;
+    type Error = Arc<Error>;
+
+    async fn load(&self, keys: &[VoteParliament]) -> Result<HashMap<VoteParliament, Self::Value>, Self::Error> {
+        self.keyed(keys.iter().map(|key| key.0).collect(), VoteParliament, |conn, ids| VoteData::get_parliament_ids(conn, &ids)).await
+    }
+}
+
+#[async_trait]
+impl Loader<RegistrationMembers> for DbLoader {
+    type Value = Vec<RegistrationData>;
+    type Error = Arc<Error>;
+
+    async fn load(&self, keys: &[RegistrationMembers]) -> Result<HashMap<RegistrationMembers, Self::Value>, Self::Error> {
+        self.grouped(keys.iter().map(|key| key.0).collect(), RegistrationMembers, |conn, ids| {
+            let rows = registration_data::table
+                .filter(registration_data::id.eq_any(ids))
+                .order((registration_data::id, registration_data::person_id))
+                .load::<RegistrationData>(conn)?;
+            Ok(rows.into_iter().map(|member| (member.id, member)).collect())
+        })
+        .await
+    }
+}
+
+#[async_trait]
+impl Loader<PoliticianTerm> for DbLoader {
+    type Value = Politician;
+    type Error = Arc<Error>;
+
+    async fn load(&self, keys: &[PoliticianTerm]) -> Result<HashMap<PoliticianTerm, Self::Value>, Self::Error> {
+        self.keyed(keys.iter().map(|key| (key.0, key.1)).collect(), |(id, parliament)| PoliticianTerm(id, parliament), |conn, ids| {
+            let politician_ids: Vec<i32> = ids.iter().map(|(id, _)| *id).collect();
+            let rows = politician::table.filter(politician::id.eq_any(politician_ids)).load::<Politician>(conn)?;
+            Ok(rows
+                .into_iter()
+                .map(|politician| ((politician.id, politician.parliament), politician))
+                .filter(|(key, _)| ids.contains(key))
+                .collect())
+        })
+        .await
+    }
+}
+
+#[async_trait]
+impl Loader<TermOffices> for DbLoader {
+    type Value = Vec<OfficeQueryable>;
+    type Error = Arc<Error>;
+
+    async fn load(&self, keys: &[TermOffices]) -> Result<HashMap<TermOffices, Self::Value>, Self::Error> {
+        self.grouped(keys.iter().map(|key| (key.0, key.1)).collect(), |(id, parliament)| TermOffices(id, parliament), |conn, ids| {
+            let politician_ids: Vec<i32> = ids.iter().map(|(id, _)| *id).collect();
+            let rows = politician_office::table
+                .inner_join(office::table)
+                .filter(politician_office::politician_id.eq_any(politician_ids))
+                .order((politician_office::politician_id, politician_office::parliament, politician_office::position))
+                .select((politician_office::politician_id, politician_office::parliament, office::all_columns))
+                .load::<(i32, i32, OfficeQueryable)>(conn)?;
+            Ok(rows
+                .into_iter()
+                .map(|(id, parliament, office)| ((id, parliament), office))
+                .filter(|(key, _)| ids.contains(key))
+                .collect())
+        })
+        .await
+    }
+}
@@ -0,0 +1,117 @@
+use std::net::SocketAddr;
+
+use axum::extract::{Path, Query, State};
+use axum::http::header::{CACHE_CONTROL, CONTENT_TYPE, ETAG, IF_NONE_MATCH};
+use axum::http::{HeaderMap, HeaderValue, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::routing::get;
+use axum::{Extension, Json, Router};
+use diesel::pg::PgConnection;
+use log::{error, info};
+use serde::Serialize;
+use serde_json::json;
+use sha2::{Digest, Sha256};
+
+use crate::database::context::Context;
+use crate::error::{Error, Result};
+
+use queries::ListFilter;
+
+pub mod graphql;
+pub mod loader;
+pub mod queries;
+
+#[derive(Debug, Clone)]
+pub struct ServeOptions {
+    pub listen: SocketAddr,
+}
+
+/// Handler of a list endpoint, filtered and paginated by the query string.
+macro_rules! list {
+    ($query:path) => {
+        get(|State(ctx): State<Context>, Query(filter): Query<ListFilter>, headers: HeaderMap| respond(ctx, headers, move |conn| $query(conn, &filter)))
+    };
+}
+
+/// Handler of an endpoint returning the row with the id in the path.
+macro_rules! detail {
+    ($query:path) => {
+        get(|State(ctx): State<Context>, Path(id): Path<i32>, headers: HeaderMap| respond(ctx, headers, move |conn| $query(conn, id)))
+    };
+}
+
+/// Routes of the read-only JSON API, and of the GraphQL endpoint over the same data.
+pub fn router(ctx: Context) -> Router {
+    Router::new()
+        .route("/graphql", get(graphql::graphiql).post(graphql::execute))
+        .route("/parliaments", list!(queries::list_parliaments))
+        .route("/parliaments/:id", detail!(queries::get_parliament))
+        .route("/sessions", list!(queries::list_sessions))
+        .route("/sessions/:id", detail!(queries::get_session))
+        .route("/meetings", list!(queries::list_meetings))
+        .route("/meetings/:id", detail!(queries::get_meeting))
+        .route("/agenda_items", list!(queries::list_agenda_items))
+        .route("/agenda_items/:id", detail!(queries::get_agenda_item))
+        .route("/votes", list!(queries::list_votes))
+        .route("/votes/:id", detail!(queries::get_vote))
+        .route("/registrations", list!(queries::list_registrations))
+        .route("/registrations/:id", detail!(queries::get_registration))
+        .route("/speeches", list!(queries::list_speeches))
+        .route("/speeches/:id", detail!(queries::get_speech))
+        .route("/politicians", list!(queries::list_politicians))
+        .route("/politicians/:id", detail!(queries::get_politician))
+        .layer(Extension(graphql::schema(ctx.clone())))
+        .with_state(ctx)
+}
+
+/// Serves the API until interrupted.
+pub async fn serve(ctx: &Context, opts: &ServeOptions) -> Result<()> {
+    let server = axum::Server::try_bind(&opts.listen).map_err(|error| Error::from(format!("cannot listen on {}: {}", opts.listen, error)))?;
+    info!("Serving the API on http://{}", opts.listen);
+    server
+        .serve(router(ctx.clone()).into_make_service())
+        .with_graceful_shutdown(async {
+            tokio::signal::ctrl_c().await.ok();
+            info!("Interrupted, stopping the API");
+        })
+        .await
+        .map_err(|error| Error::from(format!("API server failed: {}", error)))
+}
+
+/// Runs a query and answers with its result as JSON, or with `304 Not Modified` when the
+/// client already holds the same body, as told by `If-None-Match`.
+async fn respond<T, F>(ctx: Context, headers: HeaderMap, query: F) -> Result<Response>
+where
+    F: FnOnce(&mut PgConnection) -> Result<T, diesel::result::Error> + Send + 'static,
+    T: Serialize + Send + 'static,
+{
+    let value = ctx.run(query).await?;
+    let body = serde_json::to_vec(&value)?;
+    let etag = format!("\"{:x}\"", Sha256::digest(&body));
+    let etag = HeaderValue::from_str(&etag).expect("hex digest is a valid header value");
+
+    let cached = headers
+        .get(IF_NONE_MATCH)
+        .and_then(|value| value.to_str().ok())
+        .map(|value| value.split(',').map(|tag| tag.trim().trim_start_matches("W/")).any(|tag| tag == "*" || tag.as_bytes() == etag.as_bytes()))
+        .unwrap_or(false);
+    let cache = [(ETAG, etag), (CACHE_CONTROL, HeaderValue::from_static("no-cache"))];
+    if cached {
+        return Ok((StatusCode::NOT_MODIFIED, cache).into_response());
+    }
+    Ok((cache, [(CONTENT_TYPE, HeaderValue::from_static("application/json"))], body).into_response())
+}
+
+impl IntoResponse for Error {
+    fn into_response(self) -> Response {
+        let status = match &self {
+            Error::Database { source: diesel::result::Error::NotFound, .. } => StatusCode::NOT_FOUND,
+            _ => {
+                error!("API request failed: {}", self);
+                StatusCode::INTERNAL_SERVER_ERROR
+            }
+        };
+        let message = status.canonical_reason().unwrap_or("error");
+        (status, Json(json!({ "error": message }))).into_response()
+    }
+}
@@ -0,0 +1,410 @@
+use async_graphql::{InputObject, OutputType, SimpleObject};
+use chrono::{NaiveDate, NaiveDateTime};
+use diesel::pg::Pg;
+use diesel::prelude::*;
+use diesel::sql_types::Integer;
+use serde::{Deserialize, Serialize};
+
+use crate::models::politicians::politician::{OfficeQueryable, Politician, PoliticianOffice};
+use crate::models::session::meeting_data::{AgendaItem, MeetingData, Registration, Speech, Vote};
+use crate::models::session::meetings::Meetings;
+use crate::models::session::parliament::Parliament;
+use crate::models::session::registration_data::RegistrationData;
+use crate::models::session::sessions::Sessions;
+use crate::models::session::voting_data::VoteData;
+use crate::schema::{agenda_item, agenda_speech, agenda_vote, meeting_agenda_item, meeting_data, meeting_registration, meetings, parliament, politician, registration, registration_data, sessions, speech, vote, vote_data};
+
+pub const DEFAULT_LIMIT: i64 = 100;
+pub const MAX_LIMIT: i64 = 1000;
+
+/// Pagination and filters of a list endpoint, taken from the query string.
+///
+/// Each list applies the filters that make sense for it and ignores the others,
+/// `from` and `to` select rows that started on or between those days.
+#[derive(Debug, Clone, Default, Deserialize, InputObject)]
+pub struct ListFilter {
+    pub offset: Option<i64>,
+    pub limit: Option<i64>,
+    pub parliament: Option<i32>,
+    pub session: Option<i32>,
+    pub meeting: Option<i32>,
+    pub agenda_item: Option<i32>,
+    /// A politician, matched on the ballots, registrations or speeches of the member.
+    pub member: Option<i32>,
+    pub from: Option<NaiveDate>,
+    pub to: Option<NaiveDate>,
+}
+
+impl ListFilter {
+    pub fn offset(&self) -> i64 {
+        self.offset.unwrap_or(0).max(0)
+    }
+
+    pub fn limit(&self) -> i64 {
+        self.limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT)
+    }
+
+    fn starts_after(&self) -> Option<NaiveDateTime> {
+        self.from.map(day_start)
+    }
+
+    /// Start of the day after `to`, the end of the range being inclusive.
+    fn starts_before(&self) -> Option<NaiveDateTime> {
+        self.to.map(|to| day_start(to.succ_opt().unwrap_or(NaiveDate::MAX)))
+    }
+}
+
+fn day_start(day: NaiveDate) -> NaiveDateTime {
+    day.and_hms_opt(0, 0, 0).expect("midnight exists")
+}
+
+/// One page of a list, with the number of rows matching the filters.
+#[derive(Debug, Serialize, SimpleObject)]
+#[graphql(concrete(name = "ParliamentPage", params(Parliament)))]
+#[graphql(concrete(name = "SessionPage", params(Sessions)))]
+#[graphql(concrete(name = "MeetingPage", params(Meetings)))]
+#[graphql(concrete(name = "AgendaItemPage", params(AgendaItem)))]
+#[graphql(concrete(name = "VotePage", params(Vote)))]
+#[graphql(concrete(name = "RegistrationPage", params(Registration)))]
+#[graphql(concrete(name = "SpeechPage", params(Speech)))]
+#[graphql(concrete(name = "PoliticianPage", params(Politician)))]
+pub struct Page<T: OutputType> {
+    pub items: Vec<T>,
+    pub total: i64,
+    pub offset: i64,
+    pub limit: i64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct MeetingDetail {
+    #[serde(flatten)]
+    pub meeting: Meetings,
+    /// Actual start and end of the sitting, when its data was downloaded.
+    pub started: Option<NaiveDateTime>,
+    pub ended: Option<NaiveDateTime>,
+    pub agenda_items: Vec<AgendaItem>,
+    pub registration_ids: Vec<i32>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AgendaItemDetail {
+    #[serde(flatten)]
+    pub agenda_item: AgendaItem,
+    pub meeting_ids: Vec<i32>,
+    pub vote_ids: Vec<i32>,
+    pub speech_ids: Vec<i32>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct VoteDetail {
+    #[serde(flatten)]
+    pub vote: Vote,
+    pub agenda_item_ids: Vec<i32>,
+    pub ballots: Vec<VoteData>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RegistrationDetail {
+    #[serde(flatten)]
+    pub registration: Registration,
+    pub meeting_ids: Vec<i32>,
+    pub members: Vec<RegistrationData>,
+}
+
+/// A member during one parliament, with the offices held.
+#[derive(Debug, Serialize)]
+pub struct PoliticianTerm {
+    #[serde(flatten)]
+    pub politician: Politician,
+    pub offices: Vec<OfficeQueryable>,
+}
+
+/// Ids of the meetings matching the meeting, session and parliament filters, `None` when none is set.
+fn meeting_ids(filter: &ListFilter) -> Option<meetings::BoxedQuery<'static, Pg, Integer>> {
+    if filter.meeting.is_none() && filter.session.is_none() && filter.parliament.is_none() {
+        return None;
+    }
+    let mut query = meetings::table.select(meetings::id).into_boxed();
+    if let Some(meeting_id) = filter.meeting {
+        query = query.filter(meetings::id.eq(meeting_id));
+    }
+    if let Some(session_id) = filter.session {
+        query = query.filter(meetings::session.eq(session_id));
+    }
+    if let Some(parliament_id) = filter.parliament {
+        query = query.filter(meetings::session.eq_any(sessions::table.filter(sessions::parliament.eq(parliament_id)).select(sessions::id)));
+    }
+    Some(query)
+}
+
+/// Ids of the agenda items matching the agenda item filter and held in the meetings matching the others.
+fn agenda_item_ids(filter: &ListFilter) -> Option<meeting_agenda_item::BoxedQuery<'static, Pg, Integer>> {
+    let meeting_ids = meeting_ids(filter);
+    if meeting_ids.is_none() && filter.agenda_item.is_none() {
+        return None;
+    }
+    let mut query = meeting_agenda_item::table.select(meeting_agenda_item::agenda_item_id).into_boxed();
+    if let Some(meeting_ids) = meeting_ids {
+        query = query.filter(meeting_agenda_item::meeting_id.eq_any(meeting_ids));
+    }
+    if let Some(agenda_item_id) = filter.agenda_item {
+        query = query.filter(meeting_agenda_item::agenda_item_id.eq(agenda_item_id));
+    }
+    Some(query)
+}
+
+fn filtered_parliaments(filter: &ListFilter) -> parliament::BoxedQuery<'static, Pg> {
+    let mut query = parliament::table.into_boxed();
+    if let Some(from) = filter.from {
+        query = query.filter(parliament::from.ge(from));
+    }
+    if let Some(to) = filter.to {
+        query = query.filter(parliament::from.le(to));
+    }
+    query
+}
+
+fn filtered_sessions(filter: &ListFilter) -> sessions::BoxedQuery<'static, Pg> {
+    let mut query = sessions::table.into_boxed();
+    if let Some(parliament_id) = filter.parliament {
+        query = query.filter(sessions::parliament.eq(parliament_id));
+    }
+    if let Some(from) = filter.from {
+        query = query.filter(sessions::from.ge(from));
+    }
+    if let Some(to) = filter.to {
+        query = query.filter(sessions::from.le(to));
+    }
+    query
+}
+
+fn filtered_meetings(filter: &ListFilter) -> meetings::BoxedQuery<'static, Pg> {
+    let mut query = meetings::table.into_boxed();
+    if let Some(session_id) = filter.session {
+        query = query.filter(meetings::session.eq(session_id));
+    }
+    if let Some(parliament_id) = filter.parliament {
+        query = query.filter(meetings::session.eq_any(sessions::table.filter(sessions::parliament.eq(parliament_id)).select(sessions::id)));
+    }
+    if let Some(from) = filter.starts_after() {
+        query = query.filter(meetings::from.ge(from));
+    }
+    if let Some(to) = filter.starts_before() {
+        query = query.filter(meetings::from.lt(to));
+    }
+    query
+}
+
+fn filtered_agenda_items(filter: &ListFilter) -> agenda_item::BoxedQuery<'static, Pg> {
+    let mut query = agenda_item::table.into_boxed();
+    if let Some(meeting_ids) = meeting_ids(filter) {
+        query = query.filter(agenda_item::id.eq_any(meeting_agenda_item::table.filter(meeting_agenda_item::meeting_id.eq_any(meeting_ids)).select(meeting_agenda_item::agenda_item_id)));
+    }
+    if let Some(from) = filter.starts_after() {
+        query = query.filter(agenda_item::from.ge(from));
+    }
+    if let Some(to) = filter.starts_before() {
+        query = query.filter(agenda_item::from.lt(to));
+    }
+    query
+}
+
+fn filtered_votes(filter: &ListFilter) -> vote::BoxedQuery<'static, Pg> {
+    let mut query = vote::table.into_boxed();
+    if let Some(agenda_item_ids) = agenda_item_ids(filter) {
+        query = query.filter(vote::id.eq_any(agenda_vote::table.filter(agenda_vote::agenda_item_id.eq_any(agenda_item_ids)).select(agenda_vote::vote_id)));
+    }
+    if let Some(member_id) = filter.member {
+        query = query.filter(vote::id.eq_any(vote_data::table.filter(vote_data::person_id.eq(member_id)).select(vote_data::id)));
+    }
+    if let Some(from) = filter.starts_after() {
+        query = query.filter(vote::from.ge(from));
+    }
+    if let Some(to) = filter.starts_before() {
+        query = query.filter(vote::from.lt(to));
+    }
+    query
+}
+
+fn filtered_registrations(filter: &ListFilter) -> registration::BoxedQuery<'static, Pg> {
+    let mut query = registration::table.into_boxed();
+    if let Some(meeting_ids) = meeting_ids(filter) {
+        query = query.filter(registration::id.eq_any(meeting_registration::table.filter(meeting_registration::meeting_id.eq_any(meeting_ids)).select(meeting_registration::registration_id)));
+    }
+    if let Some(member_id) = filter.member {
+        query = query.filter(registration::id.eq_any(registration_data::table.filter(registration_data::person_id.eq(member_id)).select(registration_data::id)));
+    }
+    if let Some(from) = filter.starts_after() {
+        query = query.filter(registration::from.ge(from));
+    }
+    if let Some(to) = filter.starts_before() {
+        query = query.filter(registration::from.lt(to));
+    }
+    query
+}
+
+fn filtered_speeches(filter: &ListFilter) -> speech::BoxedQuery<'static, Pg> {
+    let mut query = speech::table.into_boxed();
+    if let Some(agenda_item_ids) = agenda_item_ids(filter) {
+        query = query.filter(speech::id.eq_any(agenda_speech::table.filter(agenda_speech::agenda_item_id.eq_any(agenda_item_ids)).select(agenda_speech::speech_id)));
+    }
+    if let Some(member_id) = filter.member {
+        query = query.filter(speech::person_id.eq(member_id));
+    }
+    if let Some(from) = filter.starts_after() {
+        query = query.filter(speech::from.ge(from));
+    }
+    if let Some(to) = filter.starts_before() {
+        query = query.filter(speech::from.lt(to));
+    }
+    query
+}
+
+/// Members are listed once per parliament, `from` and `to` select those whose mandate overlaps the range.
+fn filtered_politicians(filter: &ListFilter) -> politician::BoxedQuery<'static, Pg> {
+    let mut query = politician::table.into_boxed();
+    if let Some(parliament_id) = filter.parliament {
+        query = query.filter(politician::parliament.eq(parliament_id));
+    }
+    if let Some(member_id) = filter.member {
+        query = query.filter(politician::id.eq(member_id));
+    }
+    if let Some(from) = filter.from {
+        query = query.filter(politician::to.is_null().or(politician::to.ge(from)));
+    }
+    if let Some(to) = filter.to {
+        query = query.filter(politician::from.is_null().or(politician::from.le(to)));
+    }
+    query
+}
+
+pub fn list_parliaments(conn: &mut PgConnection, filter: &ListFilter) -> Result<Page<Parliament>, diesel::result::Error> {
+    let total = filtered_parliaments(filter).count().get_result::<i64>(conn)?;
+    let items = filtered_parliaments(filter).order(parliament::id).offset(filter.offset()).limit(filter.limit()).load::<Parliament>(conn)?;
+    Ok(Page { items, total, offset: filter.offset(), limit: filter.limit() })
+}
+
+pub fn list_sessions(conn: &mut PgConnection, filter: &ListFilter) -> Result<Page<Sessions>, diesel::result::Error> {
+    let total = filtered_sessions(filter).count().get_result::<i64>(conn)?;
+    let items = filtered_sessions(filter).order(sessions::id).offset(filter.offset()).limit(filter.limit()).load::<Sessions>(conn)?;
+    Ok(Page { items, total, offset: filter.offset(), limit: filter.limit() })
+}
+
+pub fn list_meetings(conn: &mut PgConnection, filter: &ListFilter) -> Result<Page<Meetings>, diesel::result::Error> {
+    let total = filtered_meetings(filter).count().get_result::<i64>(conn)?;
+    let items = filtered_meetings(filter).order((meetings::from, meetings::id)).offset(filter.offset()).limit(filter.limit()).load::<Meetings>(conn)?;
+    Ok(Page { items, total, offset: filter.offset(), limit: filter.limit() })
+}
+
+pub fn list_agenda_items(conn: &mut PgConnection, filter: &ListFilter) -> Result<Page<AgendaItem>, diesel::result::Error> {
+    let total = filtered_agenda_items(filter).count().get_result::<i64>(conn)?;
+    let items = filtered_agenda_items(filter).order((agenda_item::from, agenda_item::id)).offset(filter.offset()).limit(filter.limit()).load::<AgendaItem>(conn)?;
+    Ok(Page { items, total, offset: filter.offset(), limit: filter.limit() })
+}
+
+pub fn list_votes(conn: &mut PgConnection, filter: &ListFilter) -> Result<Page<Vote>, diesel::result::Error> {
+    let total = filtered_votes(filter).count().get_result::<i64>(conn)?;
+    let items = filtered_votes(filter).order((vote::from, vote::id)).offset(filter.offset()).limit(filter.limit()).load::<Vote>(conn)?;
+    Ok(Page { items, total, offset: filter.offset(), limit: filter.limit() })
+}
+
+pub fn list_registrations(conn: &mut PgConnection, filter: &ListFilter) -> Result<Page<Registration>, diesel::result::Error> {
+    let total = filtered_registrations(filter).count().get_result::<i64>(conn)?;
+    let items = filtered_registrations(filter).order((registration::from, registration::id)).offset(filter.offset()).limit(filter.limit()).load::<Registration>(conn)?;
+    Ok(Page { items, total, offset: filter.offset(), limit: filter.limit() })
+}
+
+pub fn list_speeches(conn: &mut PgConnection, filter: &ListFilter) -> Result<Page<Speech>, diesel::result::Error> {
+    let total = filtered_speeches(filter).count().get_result::<i64>(conn)?;
+    let items = filtered_speeches(filter).order((speech::from, speech::id)).offset(filter.offset()).limit(filter.limit()).load::<Speech>(conn)?;
+    Ok(Page { items, total, offset: filter.offset(), limit: filter.limit() })
+}
+
+pub fn list_politicians(conn: &mut PgConnection, filter: &ListFilter) -> Result<Page<Politician>, diesel::result::Error> {
+    let total = filtered_politicians(filter).count().get_result::<i64>(conn)?;
+    let items = filtered_politicians(filter)
+        .order((politician::surname, politician::name, politician::id, politician::parliament))
+        .offset(filter.offset())
+        .limit(filter.limit())
+        .load::<Politician>(conn)?;
+    Ok(Page { items, total, offset: filter.offset(), limit: filter.limit() })
+}
+
+pub fn get_parliament(conn: &mut PgConnection, parliament_id: i32) -> Result<Parliament, diesel::result::Error> {
+    parliament::table.find(parliament_id).first::<Parliament>(conn)
+}
+
+pub fn get_session(conn: &mut PgConnection, session_id: i32) -> Result<Sessions, diesel::result::Error> {
+    sessions::table.find(session_id).first::<Sessions>(conn)
+}
+
+pub fn get_meeting(conn: &mut PgConnection, meeting_id: i32) -> Result<MeetingDetail, diesel::result::Error> {
+    let meeting = meetings::table.find(meeting_id).first::<Meetings>(conn)?;
+    let data = meeting_data::table.find(meeting_id).first::<MeetingData>(conn).optional()?;
+    let agenda_item_ids = MeetingData::get_agenda_ids(conn, &[meeting_id])?;
+    let mut agenda_items = AgendaItem::get_agenda_items(conn, &agenda_item_ids)?;
+    agenda_items.sort_by_key(|item| agenda_item_ids.iter().position(|id| *id == item.id));
+    Ok(MeetingDetail {
+        meeting,
+        started: data.as_ref().and_then(|data| data.from),
+        ended: data.as_ref().and_then(|data| data.to),
+        agenda_items,
+        registration_ids: MeetingData::get_registration_ids(conn, &[meeting_id])?,
+    })
+}
+
+pub fn get_agenda_item(conn: &mut PgConnection, agenda_item_id: i32) -> Result<AgendaItemDetail, diesel::result::Error> {
+    Ok(AgendaItemDetail {
+        agenda_item: agenda_item::table.find(agenda_item_id).first::<AgendaItem>(conn)?,
+        meeting_ids: meeting_agenda_item::table
+            .filter(meeting_agenda_item::agenda_item_id.eq(agenda_item_id))
+            .order(meeting_agenda_item::meeting_id)
+            .select(meeting_agenda_item::meeting_id)
+            .load::<i32>(conn)?,
+        vote_ids: AgendaItem::get_vote_ids(conn, &[agenda_item_id])?,
+        speech_ids: AgendaItem::get_speech_ids(conn, &[agenda_item_id])?,
+    })
+}
+
+pub fn get_vote(conn: &mut PgConnection, vote_id: i32) -> Result<VoteDetail, diesel::result::Error> {
+    Ok(VoteDetail {
+        vote: vote::table.find(vote_id).first::<Vote>(conn)?,
+        agenda_item_ids: agenda_vote::table
+            .filter(agenda_vote::vote_id.eq(vote_id))
+            .order(agenda_vote::agenda_item_id)
+            .select(agenda_vote::agenda_item_id)
+            .load::<i32>(conn)?,
+        ballots: VoteData::get_ballots(conn, vote_id)?,
+    })
+}
+
+pub fn get_registration(conn: &mut PgConnection, registration_id: i32) -> Result<RegistrationDetail, diesel::result::Error> {
+    Ok(RegistrationDetail {
+        registration: registration::table.find(registration_id).first::<Registration>(conn)?,
+        meeting_ids: meeting_registration::table
+            .filter(meeting_registration::registration_id.eq(registration_id))
+            .order(meeting_registration::meeting_id)
+            .select(meeting_registration::meeting_id)
+            .load::<i32>(conn)?,
+        members: RegistrationData::get_members(conn, registration_id)?,
+    })
+}
+
+pub fn get_speech(conn: &mut PgConnection, speech_id: i32) -> Result<Speech, diesel::result::Error> {
+    speech::table.find(speech_id).first::<Speech>(conn)
+}
+
+/// Every term of a member with the offices held, `NotFound` when the member is unknown.
+pub fn get_politician(conn: &mut PgConnection, politician_id: i32) -> Result<Vec<PoliticianTerm>, diesel::result::Error> {
+    let terms = Politician::get_terms(conn, politician_id)?;
+    if terms.is_empty() {
+        return Err(diesel::result::Error::NotFound);
+    }
+    terms
+        .into_iter()
+        .map(|politician| {
+            let offices = PoliticianOffice::get_offices(conn, politician.id, politician.parliament)?;
+            Ok(PoliticianTerm { politician, offices })
+        })
+        .collect()
+}
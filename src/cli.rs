@@ -0,0 +1,508 @@
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use chrono::NaiveDate;
+use clap::{ArgGroup, Args, Parser, Subcommand};
+
+use crate::analysis::cohesion::Period;
+use crate::analysis::ideal_points::ScalingOptions;
+use crate::analysis::member_votes::{MemberStatus, MemberVoteFilter};
+use crate::analysis::similarity::{BallotHandling, SimilarityOptions};
+use crate::api::ServeOptions;
+use crate::crawler::CrawlOptions;
+use crate::export::bulk::{BulkExportOptions, FileFormat};
+use crate::export::{ExportOptions, Format};
+use crate::networking::client::ClientConfig;
+use crate::sync::SyncOptions;
+
+#[derive(Parser, Debug)]
+#[command(name = "politics", about = "Scraper for the Lithuanian Seimas open data (apps.lrs.lt)")]
+pub struct Cli {
+    /// Save every raw XML response to this fixture store
+    #[arg(long, global = true, value_name = "DIR", conflicts_with = "replay")]
+    pub record: Option<PathBuf>,
+
+    /// Serve XML responses from this fixture store instead of the network
+    #[arg(long, global = true, value_name = "DIR")]
+    pub replay: Option<PathBuf>,
+
+    /// Maximum number of open database connections
+    #[arg(long, global = true, default_value_t = 16)]
+    pub pool_size: u32,
+
+    #[command(flatten)]
+    pub http: HttpArgs,
+
+    #[command(subcommand)]
+    pub command: Command,
+}
+
+#[derive(Subcommand, Debug)]
+#[command(rename_all = "snake_case")]
+pub enum Command {
+    /// Download the list of parliaments (kadencijos)
+    DownloadParliaments(CrawlArgs),
+    /// Download members of each parliament
+    DownloadPoliticians(CrawlArgs),
+    /// Download committees, commissions and factions of each parliament with their members
+    DownloadDepartments(CrawlArgs),
+    /// Download sessions of each parliament
+    DownloadSessions(CrawlArgs),
+    /// Download meetings of each session
+    DownloadMeetings(CrawlArgs),
+    /// Download agenda, speeches, votes and registrations of each meeting
+    DownloadMeetingData(CrawlArgs),
+    /// Download individual votes of each vote
+    DownloadVotingData(CrawlArgs),
+    /// Download individual registrations of each registration
+    DownloadRegistrationData(CrawlArgs),
+    /// Download protocol and stenogram documents of each meeting
+    DownloadMeetingsDocuments(CrawlArgs),
+    /// Split downloaded stenograms into speaker turns
+    ParseStenograms(CrawlArgs),
+    /// Extract agenda outcomes from downloaded protocols
+    ParseProtocols(CrawlArgs),
+    /// Download everything still missing for the given parliaments
+    DownloadAllParliament(CrawlArgs),
+    /// Run every stage for every parliament
+    DownloadAll(CrawlArgs),
+    /// Summarise the crawl ledger and list failed fetches
+    CrawlReport,
+    /// Keep polling the current parliament and fetch new sittings as they appear
+    Sync(SyncArgs),
+    /// Count for, against, abstain and absent per faction in each vote
+    VoteBreakdown(VoteBreakdownArgs),
+    /// Rice index, agreement index and defection rate of each faction or member over a period
+    Cohesion(CohesionArgs),
+    /// Position on the principal components of the agreement matrix and voting bloc of each member
+    Similarity(SimilarityArgs),
+    /// Estimate ideal points of members and cutting lines of votes of a parliament, and optionally of each session
+    IdealPoints(IdealPointsArgs),
+    /// Registration rate, days absent and share of votes cast while registered of each member
+    Attendance(AttendanceArgs),
+    /// How each member voted over a parliament, session or date range
+    MemberVotes(MemberVotesArgs),
+    /// Rebuild the summary tables from the crawled data
+    RefreshSummaries,
+    /// Serve the crawled data as a read-only JSON API, with a GraphQL endpoint at /graphql
+    Serve(ServeArgs),
+    /// Write every table and the denormalised datasets to CSV and Parquet files with a manifest
+    Export(BulkExportArgs),
+}
+
+#[derive(Args, Debug, Clone)]
+pub struct CrawlArgs {
+    /// Only process these parliaments (kadencijos_id), comma separated
+    #[arg(long = "parliament", value_delimiter = ',')]
+    pub parliament_ids: Vec<i32>,
+
+    /// Only process these sessions (sesijos_id), comma separated
+    #[arg(long = "session", value_delimiter = ',')]
+    pub session_ids: Vec<i32>,
+
+    /// Only process these meetings (posedzio_id), comma separated
+    #[arg(long = "meeting", value_delimiter = ',')]
+    pub meeting_ids: Vec<i32>,
+
+    /// Number of downloads running at the same time
+    #[arg(short = 'j', long, default_value_t = 16, value_parser = clap::value_parser!(u16).range(1..))]
+    pub concurrency: u16,
+
+    /// Only print what would be downloaded
+    #[arg(long)]
+    pub dry_run: bool,
+
+    /// Stop retrying an item after it failed this many times
+    #[arg(long, default_value_t = 3)]
+    pub max_attempts: i32,
+
+    /// Skip items the crawl ledger already has a result for
+    #[arg(long)]
+    pub resume: bool,
+}
+
+#[derive(Args, Debug, Clone)]
+pub struct SyncArgs {
+    /// Parliament to follow (kadencijos_id), the most recent one by default
+    #[arg(long = "parliament")]
+    pub parliament_id: Option<i32>,
+
+    /// Minutes to wait between sync cycles
+    #[arg(long, default_value_t = 30, value_parser = clap::value_parser!(u64).range(1..))]
+    pub interval: u64,
+
+    /// Run a single sync cycle and exit
+    #[arg(long)]
+    pub once: bool,
+
+    /// Do not download protocols and stenograms of new meetings
+    #[arg(long)]
+    pub no_documents: bool,
+
+    /// Number of downloads running at the same time
+    #[arg(short = 'j', long, default_value_t = 16, value_parser = clap::value_parser!(u16).range(1..))]
+    pub concurrency: u16,
+
+    /// Stop retrying an item after it failed this many times
+    #[arg(long, default_value_t = 3)]
+    pub max_attempts: i32,
+}
+
+#[derive(Args, Debug, Clone)]
+pub struct VoteBreakdownArgs {
+    /// Every vote of these parliaments (kadencijos_id), comma separated
+    #[arg(long = "parliament", value_delimiter = ',', required_unless_present = "vote_ids")]
+    pub parliament_ids: Vec<i32>,
+
+    /// These votes (balsavimo_id), comma separated
+    #[arg(long = "vote", value_delimiter = ',')]
+    pub vote_ids: Vec<i32>,
+
+    #[command(flatten)]
+    pub export: ExportArgs,
+}
+
+#[derive(Args, Debug, Clone)]
+#[command(group(ArgGroup::new("period").required(true).args(["parliament_id", "session_id", "from"])))]
+pub struct CohesionArgs {
+    /// Votes of this parliament (kadencijos_id)
+    #[arg(long = "parliament")]
+    pub parliament_id: Option<i32>,
+
+    /// Votes of this session (sesijos_id)
+    #[arg(long = "session")]
+    pub session_id: Option<i32>,
+
+    /// Votes held on or after this day (YYYY-MM-DD)
+    #[arg(long, requires = "to")]
+    pub from: Option<NaiveDate>,
+
+    /// Votes held on or before this day (YYYY-MM-DD)
+    #[arg(long, requires = "from")]
+    pub to: Option<NaiveDate>,
+
+    /// One row per member instead of per faction
+    #[arg(long)]
+    pub members: bool,
+
+    #[command(flatten)]
+    pub export: ExportArgs,
+}
+
+#[derive(Args, Debug, Clone)]
+pub struct SimilarityArgs {
+    /// Parliament whose members are compared (kadencijos_id)
+    #[arg(long = "parliament")]
+    pub parliament_id: i32,
+
+    /// How abstentions are compared
+    #[arg(long, value_enum, default_value_t = BallotHandling::Choice)]
+    pub abstain: BallotHandling,
+
+    /// How members who did not vote are compared
+    #[arg(long, value_enum, default_value_t = BallotHandling::Skip)]
+    pub absent: BallotHandling,
+
+    /// Leave out members who cast fewer ballots
+    #[arg(long, default_value_t = 10)]
+    pub min_votes: usize,
+
+    /// Number of voting blocs
+    #[arg(long, default_value_t = 2, value_parser = clap::value_parser!(u16).range(1..))]
+    pub clusters: u16,
+
+    /// One row per pair of members with their agreement instead of one row per member
+    #[arg(long)]
+    pub pairs: bool,
+
+    #[command(flatten)]
+    pub export: ExportArgs,
+}
+
+#[derive(Args, Debug, Clone)]
+pub struct IdealPointsArgs {
+    /// Parliament whose votes are scaled (kadencijos_id)
+    #[arg(long = "parliament")]
+    pub parliament_id: i32,
+
+    /// Also scale each session of the parliament on its own
+    #[arg(long)]
+    pub per_session: bool,
+
+    /// Number of dimensions of the space
+    #[arg(long, default_value_t = 2, value_parser = clap::value_parser!(u8).range(1..=2))]
+    pub dimensions: u8,
+
+    /// Count abstentions as votes against instead of leaving them out
+    #[arg(long)]
+    pub abstain_as_against: bool,
+
+    /// Leave out members who cast fewer ballots
+    #[arg(long, default_value_t = 20)]
+    pub min_votes: usize,
+
+    /// Leave out votes whose minority is a smaller share of the ballots
+    #[arg(long, default_value_t = 0.025)]
+    pub min_minority: f64,
+
+    /// Member (asmens_id) placed on the positive side of every dimension
+    #[arg(long)]
+    pub polarity: Option<i32>,
+
+    /// One row per vote with its cutting line instead of one row per member
+    #[arg(long)]
+    pub cutting_lines: bool,
+
+    #[command(flatten)]
+    pub export: ExportArgs,
+}
+
+#[derive(Args, Debug, Clone)]
+#[command(group(ArgGroup::new("period").required(true).args(["parliament_id", "session_id", "meeting_id", "from"])))]
+pub struct AttendanceArgs {
+    /// Sittings of this parliament (kadencijos_id)
+    #[arg(long = "parliament")]
+    pub parliament_id: Option<i32>,
+
+    /// Sittings of this session (sesijos_id)
+    #[arg(long = "session")]
+    pub session_id: Option<i32>,
+
+    /// This sitting only (posedzio_id)
+    #[arg(long = "meeting")]
+    pub meeting_id: Option<i32>,
+
+    /// Sittings held on or after this day (YYYY-MM-DD)
+    #[arg(long, requires = "to")]
+    pub from: Option<NaiveDate>,
+
+    /// Sittings held on or before this day (YYYY-MM-DD)
+    #[arg(long, requires = "from")]
+    pub to: Option<NaiveDate>,
+
+    #[command(flatten)]
+    pub export: ExportArgs,
+}
+
+#[derive(Args, Debug, Clone)]
+#[command(group(ArgGroup::new("period").required(true).args(["parliament_id", "session_id", "from"])))]
+pub struct MemberVotesArgs {
+    /// Votes of this parliament (kadencijos_id)
+    #[arg(long = "parliament")]
+    pub parliament_id: Option<i32>,
+
+    /// Votes of this session (sesijos_id)
+    #[arg(long = "session")]
+    pub session_id: Option<i32>,
+
+    /// Votes held on or after this day (YYYY-MM-DD)
+    #[arg(long, requires = "to")]
+    pub from: Option<NaiveDate>,
+
+    /// Votes held on or before this day (YYYY-MM-DD)
+    #[arg(long, requires = "from")]
+    pub to: Option<NaiveDate>,
+
+    /// Only ballots cast while in this faction
+    #[arg(long = "faction")]
+    pub faction_id: Option<i32>,
+
+    /// Only sitting or only former members
+    #[arg(long, value_enum)]
+    pub status: Option<MemberStatus>,
+
+    #[command(flatten)]
+    pub export: ExportArgs,
+}
+
+#[derive(Args, Debug, Clone)]
+pub struct ServeArgs {
+    /// Address and port to listen on
+    #[arg(long, default_value = "127.0.0.1:8080")]
+    pub listen: SocketAddr,
+}
+
+#[derive(Args, Debug, Clone)]
+pub struct BulkExportArgs {
+    /// Directory the files and manifest.json are written to
+    #[arg(short, long, value_name = "DIR")]
+    pub output_dir: PathBuf,
+
+    /// File formats, comma separated
+    #[arg(long, value_enum, value_delimiter = ',', default_values_t = [FileFormat::Csv, FileFormat::Parquet])]
+    pub format: Vec<FileFormat>,
+
+    /// Only rows of this parliament (kadencijos_id)
+    #[arg(long = "parliament")]
+    pub parliament_id: Option<i32>,
+}
+
+#[derive(Args, Debug, Clone)]
+pub struct ExportArgs {
+    /// Output format
+    #[arg(long, value_enum, default_value_t = Format::Csv)]
+    pub format: Format,
+
+    /// Write to this file instead of standard output
+    #[arg(short, long, value_name = "FILE")]
+    pub output: Option<PathBuf>,
+}
+
+#[derive(Args, Debug, Clone)]
+pub struct HttpArgs {
+    /// Maximum number of requests per second sent to the Seimas servers, 0 for no limit
+    #[arg(long, global = true, default_value_t = 8.0, value_parser = parse_rps)]
+    pub rps: f64,
+
+    /// How many times a failed request is retried
+    #[arg(long, global = true, default_value_t = 5)]
+    pub retries: u32,
+
+    /// Request timeout in seconds
+    #[arg(long, global = true, default_value_t = 60)]
+    pub timeout: u64,
+}
+
+/// Either 0 or a finite rate of at least one request every 1000 seconds, so the interval between
+/// requests is always a valid duration.
+fn parse_rps(value: &str) -> Result<f64, String> {
+    let rps: f64 = value.parse().map_err(|error| format!("{}", error))?;
+    if rps != 0.0 && !(rps.is_finite() && rps >= 0.001) {
+        return Err(format!("expected 0 or a finite rate of at least 0.001, got {}", value));
+    }
+    Ok(rps)
+}
+
+impl From<HttpArgs> for ClientConfig {
+    fn from(args: HttpArgs) -> Self {
+        ClientConfig {
+            timeout: Duration::from_secs(args.timeout),
+            max_retries: args.retries,
+            requests_per_second: args.rps,
+            ..ClientConfig::default()
+        }
+    }
+}
+
+impl From<CrawlArgs> for CrawlOptions {
+    fn from(args: CrawlArgs) -> Self {
+        CrawlOptions {
+            parliament_ids: args.parliament_ids,
+            session_ids: args.session_ids,
+            meeting_ids: args.meeting_ids,
+            concurrency: args.concurrency as usize,
+            dry_run: args.dry_run,
+            max_attempts: args.max_attempts,
+            resume: args.resume,
+        }
+    }
+}
+
+impl From<ServeArgs> for ServeOptions {
+    fn from(args: ServeArgs) -> Self {
+        ServeOptions { listen: args.listen }
+    }
+}
+
+impl From<SyncArgs> for SyncOptions {
+    fn from(args: SyncArgs) -> Self {
+        SyncOptions {
+            parliament_id: args.parliament_id,
+            interval: Duration::from_secs(args.interval * 60),
+            once: args.once,
+            documents: !args.no_documents,
+            crawl: CrawlOptions {
+                concurrency: args.concurrency as usize,
+                max_attempts: args.max_attempts,
+                ..CrawlOptions::default()
+            },
+        }
+    }
+}
+
+impl From<&SimilarityArgs> for SimilarityOptions {
+    fn from(args: &SimilarityArgs) -> Self {
+        SimilarityOptions {
+            abstain: args.abstain,
+            absent: args.absent,
+            min_votes: args.min_votes,
+            clusters: args.clusters as usize,
+        }
+    }
+}
+
+impl From<&IdealPointsArgs> for ScalingOptions {
+    fn from(args: &IdealPointsArgs) -> Self {
+        ScalingOptions {
+            dimensions: args.dimensions as usize,
+            abstain_as_against: args.abstain_as_against,
+            min_votes: args.min_votes,
+            min_minority: args.min_minority,
+            polarity: args.polarity,
+        }
+    }
+}
+
+impl From<BulkExportArgs> for BulkExportOptions {
+    fn from(args: BulkExportArgs) -> Self {
+        let mut formats = args.format;
+        formats.sort();
+        formats.dedup();
+        BulkExportOptions {
+            output_dir: args.output_dir,
+            formats,
+            parliament_id: args.parliament_id,
+        }
+    }
+}
+
+impl From<ExportArgs> for ExportOptions {
+    fn from(args: ExportArgs) -> Self {
+        ExportOptions {
+            format: args.format,
+            output: args.output,
+        }
+    }
+}
+
+impl CohesionArgs {
+    /// The period selected on the command line, clap makes sure exactly one was given.
+    pub fn period(&self) -> Period {
+        match (self.parliament_id, self.session_id, self.from, self.to) {
+            (Some(parliament_id), _, _, _) => Period::Parliament(parliament_id),
+            (_, Some(session_id), _, _) => Period::Session(session_id),
+            (_, _, Some(from), Some(to)) => Period::Range(from, to),
+            _ => unreachable!("clap requires a period"),
+        }
+    }
+}
+
+impl AttendanceArgs {
+    /// The period selected on the command line, `None` when a single sitting was asked for.
+    pub fn period(&self) -> Option<Period> {
+        match (self.parliament_id, self.session_id, self.from, self.to) {
+            (Some(parliament_id), _, _, _) => Some(Period::Parliament(parliament_id)),
+            (_, Some(session_id), _, _) => Some(Period::Session(session_id)),
+            (_, _, Some(from), Some(to)) => Some(Period::Range(from, to)),
+            _ => None,
+        }
+    }
+}
+
+impl MemberVotesArgs {
+    /// The filter selected on the command line, clap makes sure exactly one period was given.
+    pub fn filter(&self) -> MemberVoteFilter {
+        let period = match (self.parliament_id, self.session_id, self.from, self.to) {
+            (Some(parliament_id), _, _, _) => Period::Parliament(parliament_id),
+            (_, Some(session_id), _, _) => Period::Session(session_id),
+            (_, _, Some(from), Some(to)) => Period::Range(from, to),
+            _ => unreachable!("clap requires a period"),
+        };
+        MemberVoteFilter {
+            period,
+            faction_id: self.faction_id,
+            status: self.status,
+        }
+    }
+}
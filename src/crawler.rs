@@ -1,17 +1,17 @@
-use std::error::Error;
-
 use log::{error, debug};
 use models::session::{meetings::Meetings, sessions::Sessions};
-use networking::download::{get_protocol_document, get_stenogram_document};
+use networking::download::{get_protocol_document, get_stenogram_document, protocol_text_path, stenogram_text_path};
 use parser::session::meetings::get_meetings;
 
-use crate::{models::{self, session::{meeting_data::{Vote, Registration}, parliament::Parliament, voting_data::VoteData, registration_data::RegistrationData}}, networking, parser::{self, politicians::politician, session::{meeting_data, voting_data, registration_data, parliaments, sessions, meetings}}};
+use crate::error::Result;
+use crate::ledger::{self, Endpoint};
+use crate::{analysis, database::context::Context, models::{self, session::{meeting_data::{Vote, Registration, MeetingData, AgendaItem}, parliament::Parliament, voting_data::VoteData, registration_data::RegistrationData}}, networking, parser::{self, document::{protocol, stenogram}, politicians::{department, politician}, session::{meeting_data, voting_data, registration_data, parliaments, sessions, meetings}}};
 
 #[macro_export]
 macro_rules! asyncrun {
     ( $functions:expr,$task_count:expr ) => {
         {
-
+            let mut failed = 0usize;
             while $functions.len() > 0 {
                 let mut download_futures = Vec::new();
                 for _ in 0..$task_count {
@@ -20,201 +20,456 @@ macro_rules! asyncrun {
                         None => break,
                     }
                 }
-                
+
                 for download_future in download_futures {
                     match download_future.await {
-                        Ok(_) => {}
+                        Ok(Ok(_)) => {}
+                        Ok(Err(error)) => {
+                            error!("{}", error);
+                            failed += 1;
+                        }
                         Err(error) => {
                             error!("{:?}", error);
+                            failed += 1;
                         }
                     }
                 }
             }
+            failed
         }
     };
 }
 
 
-pub async fn download_parliaments() -> Result<(), Box<dyn Error + Send + Sync>> {
+#[derive(Debug, Clone)]
+pub struct CrawlOptions {
+    pub parliament_ids: Vec<i32>,
+    pub session_ids: Vec<i32>,
+    pub meeting_ids: Vec<i32>,
+    pub concurrency: usize,
+    pub dry_run: bool,
+    pub max_attempts: i32,
+    pub resume: bool,
+}
+
+impl Default for CrawlOptions {
+    fn default() -> Self {
+        CrawlOptions {
+            parliament_ids: vec![],
+            session_ids: vec![],
+            meeting_ids: vec![],
+            concurrency: 16,
+            dry_run: false,
+            max_attempts: 3,
+            resume: false,
+        }
+    }
+}
+
+impl CrawlOptions {
+    fn is_filtered(&self) -> bool {
+        !(self.parliament_ids.is_empty() && self.session_ids.is_empty() && self.meeting_ids.is_empty())
+    }
+
+    pub(crate) async fn pending(&self, ctx: &Context, endpoint: Endpoint, ids: Vec<i32>) -> Result<Vec<i32>> {
+        ledger::pending(ctx, endpoint, ids, self.max_attempts, self.resume).await
+    }
+
+    async fn parliaments(&self, ctx: &Context) -> Result<Vec<i32>> {
+        if self.parliament_ids.is_empty() {
+            ctx.run(Parliament::get_parliaments_ids).await
+        } else {
+            Ok(self.parliament_ids.clone())
+        }
+    }
+
+    async fn sessions(&self, ctx: &Context, parliament_id: i32) -> Result<Vec<i32>> {
+        let session_ids = ctx.run(move |conn| Sessions::get_sessions_per_parliament(conn, parliament_id)).await?;
+        Ok(retain(session_ids, &self.session_ids))
+    }
+
+    async fn meetings(&self, ctx: &Context, session_id: i32) -> Result<Vec<i32>> {
+        let meeting_ids = ctx.run(move |conn| Meetings::get_meetings_ids(conn, session_id)).await?;
+        Ok(retain(meeting_ids, &self.meeting_ids))
+    }
+
+    async fn all_meetings(&self, ctx: &Context) -> Result<Vec<i32>> {
+        let mut meeting_ids = Vec::new();
+        for parliament_id in self.parliaments(ctx).await? {
+            for session_id in self.sessions(ctx, parliament_id).await? {
+                meeting_ids.extend(self.meetings(ctx, session_id).await?);
+            }
+        }
+        Ok(meeting_ids)
+    }
+
+    async fn meeting_rows(&self, ctx: &Context) -> Result<Vec<Meetings>> {
+        let mut session_ids = Vec::new();
+        for parliament_id in self.parliaments(ctx).await? {
+            session_ids.extend(self.sessions(ctx, parliament_id).await?);
+        }
+        let meetings = ctx.run(move |conn| Meetings::get_meetings_per_sessions(conn, &session_ids)).await?;
+        Ok(meetings.into_iter().filter(|meeting| self.meeting_ids.is_empty() || self.meeting_ids.contains(&meeting.id)).collect())
+    }
+
+    async fn vote_ids(&self, ctx: &Context) -> Result<Vec<i32>> {
+        let meeting_ids = self.all_meetings(ctx).await?;
+        ctx.run(move |conn| {
+            let agenda_ids = MeetingData::get_agenda_ids(conn, &meeting_ids)?;
+            AgendaItem::get_vote_ids(conn, &agenda_ids)
+        }).await
+    }
+
+    async fn registration_ids(&self, ctx: &Context) -> Result<Vec<i32>> {
+        let meeting_ids = self.all_meetings(ctx).await?;
+        ctx.run(move |conn| MeetingData::get_registration_ids(conn, &meeting_ids)).await
+    }
+}
+
+fn retain(ids: Vec<i32>, filter: &[i32]) -> Vec<i32> {
+    if filter.is_empty() {
+        ids
+    } else {
+        ids.into_iter().filter(|id| filter.contains(id)).collect()
+    }
+}
+
+fn print_plan(stage: &str, ids: &[i32]) {
+    println!("[dry-run] {}: {} item(s) {:?}", stage, ids.len(), ids);
+}
+
+
+// Each stage returns the number of jobs that failed, the ledger keeps what went wrong with them.
+pub async fn download_parliaments(ctx: &Context, opts: &CrawlOptions) -> Result<usize> {
     debug!("Downloading parliaments...");
-    parliaments::get_parliaments().await
+    if opts.dry_run {
+        println!("[dry-run] parliaments: full list");
+        return Ok(0);
+    }
+    parliaments::get_parliaments(ctx.clone()).await?;
+    Ok(0)
 }
 
-pub async fn download_politicians() -> Result<(), Box<dyn Error + Send + Sync>> {
+pub async fn download_politicians(ctx: &Context, opts: &CrawlOptions) -> Result<usize> {
     debug!("Downloading politicians...");
+    let parliament_ids = opts.pending(ctx, Endpoint::Politicians, opts.parliaments(ctx).await?).await?;
+    if opts.dry_run {
+        print_plan("politicians", &parliament_ids);
+        return Ok(0);
+    }
+
     let mut functions = Vec::new();
+    for parliament_id in parliament_ids {
+        functions.push(ledger::track(ctx.clone(), Endpoint::Politicians, parliament_id, politician::get_politicians(ctx.clone(), parliament_id)));
+    }
+
+    let failed = asyncrun!(functions, opts.concurrency);
+
+    Ok(failed)
+}
 
-    for parliament_id in Parliament::get_parliaments_ids()? {
-        functions.push(politician::get_politicians(parliament_id));
+pub async fn download_departments(ctx: &Context, opts: &CrawlOptions) -> Result<usize> {
+    debug!("Downloading departments...");
+    let parliament_ids = opts.pending(ctx, Endpoint::Departments, opts.parliaments(ctx).await?).await?;
+    if opts.dry_run {
+        print_plan("departments", &parliament_ids);
+        return Ok(0);
+    }
+
+    let mut functions = Vec::new();
+    for parliament_id in parliament_ids {
+        functions.push(ledger::track(ctx.clone(), Endpoint::Departments, parliament_id, department::get_departments(ctx.clone(), parliament_id)));
     }
 
-    asyncrun!(functions, 16);
+    let failed = asyncrun!(functions, opts.concurrency);
 
-    Ok(())
+    Ok(failed)
 }
 
-pub async fn download_meetings_documents(parliament_id: i32) -> Result<(), Box<dyn Error + Send + Sync>> {
+pub async fn download_meetings_documents(ctx: &Context, parliament_id: i32, opts: &CrawlOptions) -> Result<usize> {
     debug!("Downloading meetings documents parliament {}...", parliament_id);
     let mut functions = Vec::new();
     let mut functions2 = Vec::new();
 
-    for session in Sessions::get_sessions_per_parliament(parliament_id)? {
-        if Meetings::get_session_count(session)? < 1 {
-            get_meetings(session).await?;
+    for session in opts.sessions(ctx, parliament_id).await? {
+        if ctx.run(move |conn| Meetings::get_session_count(conn, session)).await? < 1 && !opts.dry_run {
+            get_meetings(ctx.clone(), session).await?;
         }
-        for protocol in Meetings::get_protocols_per_session(session)? {
+        for protocol in ctx.run(move |conn| Meetings::get_protocols_per_session(conn, session)).await? {
             if let (session_id, meeting_num, Some(protocol_url_unwrapped)) = protocol {
-                functions.push(get_protocol_document(protocol_url_unwrapped, session_id, meeting_num));
+                if opts.dry_run {
+                    println!("[dry-run] protocol: {}", protocol_url_unwrapped);
+                } else {
+                    functions.push(get_protocol_document(protocol_url_unwrapped, session_id, meeting_num));
+                }
             }
         }
-        for stenogram in Meetings::get_stenograms_per_session(session)? {
+        for stenogram in ctx.run(move |conn| Meetings::get_stenograms_per_session(conn, session)).await? {
             if let (session_id, meeting_num, Some(stenogram_url_unwrapped)) = stenogram {
-                functions2.push(get_stenogram_document(stenogram_url_unwrapped, session_id, meeting_num));
+                if opts.dry_run {
+                    println!("[dry-run] stenogram: {}", stenogram_url_unwrapped);
+                } else {
+                    functions2.push(get_stenogram_document(stenogram_url_unwrapped, session_id, meeting_num));
+                }
             }
         }
     }
 
-    asyncrun!(functions, 8);
-    asyncrun!(functions2, 8);
+    let failed = asyncrun!(functions, opts.concurrency) + asyncrun!(functions2, opts.concurrency);
 
-    Ok(())
+    Ok(failed)
 }
 
-pub async fn download_all_meetings_documents() -> Result<(), Box<dyn Error + Send + Sync>> {
+pub async fn download_all_meetings_documents(ctx: &Context, opts: &CrawlOptions) -> Result<usize> {
     debug!("Downloading all meetings documents...");
 
-    for parliament_id in Parliament::get_parliaments_ids()? {
-        download_meetings_documents(parliament_id).await?;
+    let mut failed = 0;
+    for parliament_id in opts.parliaments(ctx).await? {
+        failed += download_meetings_documents(ctx, parliament_id, opts).await?;
     }
 
-    Ok(())
+    Ok(failed)
 }
 
-pub async fn download_sessions() -> Result<(), Box<dyn Error + Send + Sync>> {
-    debug!("Downloading sessions...");
+pub async fn parse_stenograms(ctx: &Context, opts: &CrawlOptions) -> Result<usize> {
+    debug!("Parsing stenograms...");
+    let mut functions = Vec::new();
+    for meeting in opts.meeting_rows(ctx).await? {
+        let path = match &meeting.stenogram_link {
+            Some(link) => stenogram_text_path(link, meeting.session, meeting.num)?,
+            None => continue,
+        };
+        if !path.exists() {
+            debug!("Stenogram not downloaded: {}", path.display());
+            continue;
+        }
+        if opts.dry_run {
+            println!("[dry-run] stenogram: {}", path.display());
+        } else {
+            functions.push(stenogram::parse_stenogram(ctx.clone(), meeting, path));
+        }
+    }
+
+    let failed = asyncrun!(functions, opts.concurrency);
+
+    Ok(failed)
+}
+
+pub async fn parse_protocols(ctx: &Context, opts: &CrawlOptions) -> Result<usize> {
+    debug!("Parsing protocols...");
     let mut functions = Vec::new();
+    for meeting in opts.meeting_rows(ctx).await? {
+        let path = match &meeting.protocol_link {
+            Some(link) => protocol_text_path(link, meeting.session, meeting.num)?,
+            None => continue,
+        };
+        if !path.exists() {
+            debug!("Protocol not downloaded: {}", path.display());
+            continue;
+        }
+        if opts.dry_run {
+            println!("[dry-run] protocol: {}", path.display());
+        } else {
+            functions.push(protocol::parse_protocol(ctx.clone(), meeting, path));
+        }
+    }
+
+    let failed = asyncrun!(functions, opts.concurrency);
+
+    Ok(failed)
+}
+
+pub async fn download_sessions(ctx: &Context, opts: &CrawlOptions) -> Result<usize> {
+    debug!("Downloading sessions...");
+    let parliament_ids = opts.pending(ctx, Endpoint::Sessions, opts.parliaments(ctx).await?).await?;
+    if opts.dry_run {
+        print_plan("sessions", &parliament_ids);
+        return Ok(0);
+    }
 
-    for parliament_id in Parliament::get_parliaments_ids()? {
-        functions.push(sessions::get_sessions(parliament_id));
+    let mut functions = Vec::new();
+    for parliament_id in parliament_ids {
+        functions.push(ledger::track(ctx.clone(), Endpoint::Sessions, parliament_id, sessions::get_sessions(ctx.clone(), parliament_id)));
     }
-    
-    asyncrun!(functions, 16);
 
-    Ok(())
+    let failed = asyncrun!(functions, opts.concurrency);
+
+    Ok(failed)
 }
 
-pub async fn download_meetings() -> Result<(), Box<dyn Error + Send + Sync>> {
+pub async fn download_meetings(ctx: &Context, opts: &CrawlOptions) -> Result<usize> {
     debug!("Downloading meetings...");
-    let mut functions = Vec::new();
+    let mut session_ids = Vec::new();
+    for parliament_id in opts.parliaments(ctx).await? {
+        session_ids.extend(opts.sessions(ctx, parliament_id).await?);
+    }
+    let session_ids = opts.pending(ctx, Endpoint::Meetings, session_ids).await?;
+    if opts.dry_run {
+        print_plan("meetings", &session_ids);
+        return Ok(0);
+    }
 
-    for parliament_id in Parliament::get_parliaments_ids()? {
-        for session in  Sessions::get_sessions_per_parliament(parliament_id)? {
-            functions.push(meetings::get_meetings(session));
-        }
+    let mut functions = Vec::new();
+    for session in session_ids {
+        functions.push(ledger::track(ctx.clone(), Endpoint::Meetings, session, meetings::get_meetings(ctx.clone(), session)));
     }
-    
-    asyncrun!(functions, 16);
 
-    Ok(())
+    let failed = asyncrun!(functions, opts.concurrency);
+
+    Ok(failed)
 }
 
 
-pub async fn download_meeting_data() -> Result<(), Box<dyn Error + Send + Sync>> {
+pub async fn download_meeting_data(ctx: &Context, opts: &CrawlOptions) -> Result<usize> {
     debug!("Downloading meeting data...");
-    let mut functions = Vec::new();
+    let meeting_ids = opts.pending(ctx, Endpoint::MeetingData, opts.all_meetings(ctx).await?).await?;
+    if opts.dry_run {
+        print_plan("meeting data", &meeting_ids);
+        return Ok(0);
+    }
 
-    for parliament_id in Parliament::get_parliaments_ids()? {
-        for session in  Sessions::get_sessions_per_parliament(parliament_id)? {
-            for meeting_id in Meetings::get_meetings_ids(session)? {
-                functions.push(meeting_data::get_meeting_data(meeting_id));
-            }
-        }
+    let mut functions = Vec::new();
+    for meeting_id in meeting_ids {
+        functions.push(ledger::track(ctx.clone(), Endpoint::MeetingData, meeting_id, meeting_data::get_meeting_data(ctx.clone(), meeting_id)));
     }
-    
-    asyncrun!(functions, 16);
 
-    Ok(())
+    let failed = asyncrun!(functions, opts.concurrency);
+
+    Ok(failed)
 }
 
-pub async fn download_voting_data() -> Result<(), Box<dyn Error + Send + Sync>> {
+pub async fn download_voting_data(ctx: &Context, opts: &CrawlOptions) -> Result<usize> {
     debug!("Downloading voting data...");
-    let mut functions = Vec::new();
+    let vote_ids = if opts.is_filtered() {
+        opts.vote_ids(ctx).await?
+    } else {
+        ctx.run(Vote::get_vote_ids).await?
+    };
+    let vote_ids = opts.pending(ctx, Endpoint::VotingData, vote_ids).await?;
+    if opts.dry_run {
+        print_plan("voting data", &vote_ids);
+        return Ok(0);
+    }
 
-    for vote_id in Vote::get_vote_ids()? {
-        functions.push(voting_data::get_voting_data(vote_id));
+    let mut functions = Vec::new();
+    for vote_id in vote_ids {
+        functions.push(ledger::track(ctx.clone(), Endpoint::VotingData, vote_id, voting_data::get_voting_data(ctx.clone(), vote_id)));
     }
 
-    asyncrun!(functions, 16);
+    let failed = asyncrun!(functions, opts.concurrency);
 
-    Ok(())
+    Ok(failed)
 }
 
-pub async fn download_registration_data() -> Result<(), Box<dyn Error + Send + Sync>> {
+pub async fn download_registration_data(ctx: &Context, opts: &CrawlOptions) -> Result<usize> {
     debug!("Downloading registration data...");
-    let mut functions = Vec::new();
+    let registration_ids = if opts.is_filtered() {
+        opts.registration_ids(ctx).await?
+    } else {
+        ctx.run(Registration::get_registration_ids).await?
+    };
+    let registration_ids = opts.pending(ctx, Endpoint::RegistrationData, registration_ids).await?;
+    if opts.dry_run {
+        print_plan("registration data", &registration_ids);
+        return Ok(0);
+    }
 
-    for registration_id in Registration::get_registration_ids()? {
-        functions.push(registration_data::get_registration_data(registration_id));
+    let mut functions = Vec::new();
+    for registration_id in registration_ids {
+        functions.push(ledger::track(ctx.clone(), Endpoint::RegistrationData, registration_id, registration_data::get_registration_data(ctx.clone(), registration_id)));
     }
 
-    asyncrun!(functions, 16);
+    let failed = asyncrun!(functions, opts.concurrency);
 
-    Ok(())
+    Ok(failed)
 }
 
-pub async fn download_all_parliament(parliament_id: i32) -> Result<(), Box<dyn Error + Send + Sync>> {
+pub async fn download_all_parliament(ctx: &Context, parliament_id: i32, opts: &CrawlOptions) -> Result<usize> {
+    let mut failed = 0;
     debug!("Downloading sessions...");
-    sessions::get_sessions(parliament_id).await?;
+    if opts.dry_run {
+        print_plan("sessions", &[parliament_id]);
+    } else {
+        ledger::track(ctx.clone(), Endpoint::Sessions, parliament_id, sessions::get_sessions(ctx.clone(), parliament_id)).await?;
+    }
 
     debug!("Downloading meetings...");
-    let mut functions = Vec::new();
-
-    for session in  Sessions::get_sessions_per_parliament(parliament_id)? {
-        functions.push(meetings::get_meetings(session));
+    let session_ids = opts.pending(ctx, Endpoint::Meetings, opts.sessions(ctx, parliament_id).await?).await?;
+    if opts.dry_run {
+        print_plan("meetings", &session_ids);
+    } else {
+        let mut functions = Vec::new();
+        for session in session_ids {
+            functions.push(ledger::track(ctx.clone(), Endpoint::Meetings, session, meetings::get_meetings(ctx.clone(), session)));
+        }
+        failed += asyncrun!(functions, opts.concurrency);
     }
-    
-    asyncrun!(functions, 16);
 
     debug!("Downloading meeting data...");
-    let mut functions = Vec::new();
-
-    for meeting_id in Meetings::get_missing_meeting_ids()? {
-        functions.push(meeting_data::get_meeting_data(meeting_id));
+    let mut meeting_ids = ctx.run(Meetings::get_missing_meeting_ids).await?;
+    if opts.is_filtered() {
+        meeting_ids = retain(meeting_ids, &opts.all_meetings(ctx).await?);
+    }
+    let meeting_ids = opts.pending(ctx, Endpoint::MeetingData, meeting_ids).await?;
+    if opts.dry_run {
+        print_plan("meeting data", &meeting_ids);
+    } else {
+        let mut functions = Vec::new();
+        for meeting_id in meeting_ids {
+            functions.push(ledger::track(ctx.clone(), Endpoint::MeetingData, meeting_id, meeting_data::get_meeting_data(ctx.clone(), meeting_id)));
+        }
+        failed += asyncrun!(functions, opts.concurrency);
     }
-    
-    asyncrun!(functions, 16);
 
     debug!("Downloading voting data...");
-    let mut functions = Vec::new();
-
-    for vote_id in VoteData::get_missing_vote_ids()? {
-        functions.push(voting_data::get_voting_data(vote_id));
+    let mut vote_ids = ctx.run(VoteData::get_missing_vote_ids).await?;
+    if opts.is_filtered() {
+        vote_ids = retain(vote_ids, &opts.vote_ids(ctx).await?);
+    }
+    let vote_ids = opts.pending(ctx, Endpoint::VotingData, vote_ids).await?;
+    if opts.dry_run {
+        print_plan("voting data", &vote_ids);
+    } else {
+        let mut functions = Vec::new();
+        for vote_id in vote_ids {
+            functions.push(ledger::track(ctx.clone(), Endpoint::VotingData, vote_id, voting_data::get_voting_data(ctx.clone(), vote_id)));
+        }
+        failed += asyncrun!(functions, opts.concurrency);
     }
-
-    asyncrun!(functions, 16);
 
     debug!("Downloading registration data...");
-    let mut functions = Vec::new();
-
-    for registration_id in RegistrationData::get_missing_registration_ids()? {
-        functions.push(registration_data::get_registration_data(registration_id));
+    let mut registration_ids = ctx.run(RegistrationData::get_missing_registration_ids).await?;
+    if opts.is_filtered() {
+        registration_ids = retain(registration_ids, &opts.registration_ids(ctx).await?);
+    }
+    let registration_ids = opts.pending(ctx, Endpoint::RegistrationData, registration_ids).await?;
+    if opts.dry_run {
+        print_plan("registration data", &registration_ids);
+    } else {
+        let mut functions = Vec::new();
+        for registration_id in registration_ids {
+            functions.push(ledger::track(ctx.clone(), Endpoint::RegistrationData, registration_id, registration_data::get_registration_data(ctx.clone(), registration_id)));
+        }
+        failed += asyncrun!(functions, opts.concurrency);
     }
 
-    asyncrun!(functions, 16);
-
-    download_meetings_documents(parliament_id).await?;
+    failed += download_meetings_documents(ctx, parliament_id, opts).await?;
 
-    Ok(())
+    Ok(failed)
 }
 
-pub async fn download_all() -> Result<(), Box<dyn Error + Send + Sync>> {
-    download_parliaments().await?;
-    download_politicians().await?;
-    download_sessions().await?;
-    download_meetings().await?;
-    download_meeting_data().await?;
-    download_voting_data().await?;
-    download_registration_data().await?;
-    download_all_meetings_documents().await?;
-    Ok(())
+pub async fn download_all(ctx: &Context, opts: &CrawlOptions) -> Result<usize> {
+    let mut failed = download_parliaments(ctx, opts).await?;
+    failed += download_politicians(ctx, opts).await?;
+    failed += download_departments(ctx, opts).await?;
+    failed += download_sessions(ctx, opts).await?;
+    failed += download_meetings(ctx, opts).await?;
+    failed += download_meeting_data(ctx, opts).await?;
+    failed += download_voting_data(ctx, opts).await?;
+    failed += download_registration_data(ctx, opts).await?;
+    failed += download_all_meetings_documents(ctx, opts).await?;
+    if !opts.dry_run {
+        analysis::refresh_summaries(ctx).await?;
+    }
+    Ok(failed)
 }
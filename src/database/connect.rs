@@ -1,11 +1,14 @@
 use diesel::pg::PgConnection;
-use diesel::prelude::*;
+use diesel::r2d2::{ConnectionManager, Pool, PoolError};
 use dotenvy::dotenv;
 use std::env;
 
-pub fn establish_connection() -> PgConnection {
+pub type PgPool = Pool<ConnectionManager<PgConnection>>;
+
+pub fn establish_pool(max_size: u32) -> Result<PgPool, PoolError> {
     dotenv().ok();
 
     let database_url = env::var("DATABASE_URL").expect("DATABASE_URL must be set");
-    PgConnection::establish(&database_url).unwrap()
-}
\ No newline at end of file
+    let manager = ConnectionManager::<PgConnection>::new(database_url);
+    Pool::builder().max_size(max_size).build(manager)
+}
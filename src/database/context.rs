@@ -0,0 +1,32 @@
+use diesel::pg::PgConnection;
+
+use crate::database::connect::PgPool;
+use crate::error::{Error, Result};
+
+/// Shared state handed to every crawler stage, parser and query.
+///
+/// Cloning is cheap, the connection pool is reference counted.
+#[derive(Clone)]
+pub struct Context {
+    pool: PgPool,
+}
+
+impl Context {
+    pub fn new(pool: PgPool) -> Context {
+        Context { pool }
+    }
+
+    /// Runs blocking Diesel work on a pooled connection without stalling the async workers.
+    pub async fn run<F, T, E>(&self, f: F) -> Result<T>
+    where
+        F: FnOnce(&mut PgConnection) -> Result<T, E> + Send + 'static,
+        T: Send + 'static,
+        E: Into<Error>,
+    {
+        let pool = self.pool.clone();
+        tokio::task::spawn_blocking(move || {
+            let mut conn = pool.get()?;
+            f(&mut conn).map_err(Into::into)
+        }).await?
+    }
+}
@@ -0,0 +1,120 @@
+use thiserror::Error;
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("request to {url} failed: {source}")]
+    Network {
+        url: String,
+        #[source]
+        source: reqwest::Error,
+    },
+
+    #[error("{url} returned HTTP {status}")]
+    Http { url: String, status: u16 },
+
+    #[error("invalid URL {url}: {reason}")]
+    InvalidUrl { url: String, reason: String },
+
+    #[error("no recorded response for {0}")]
+    MissingFixture(String),
+
+    #[error("malformed XML in {context}: {source}")]
+    Xml {
+        context: String,
+        #[source]
+        source: xml::reader::Error,
+    },
+
+    #[error("unreadable document {context}: {source}")]
+    Zip {
+        context: String,
+        #[source]
+        source: zip::result::ZipError,
+    },
+
+    #[error("<{element}> in {context} is missing attribute `{attribute}`")]
+    MissingAttribute {
+        element: String,
+        attribute: String,
+        context: String,
+    },
+
+    #[error("<{element}> in {context} has invalid `{attribute}` value {value:?}")]
+    InvalidValue {
+        element: String,
+        attribute: String,
+        value: String,
+        context: String,
+    },
+
+    #[error("<{element}> in {context} is outside of <{parent}>")]
+    Orphan {
+        element: String,
+        parent: String,
+        context: String,
+    },
+
+    #[error("database error in {context}: {source}")]
+    Database {
+        context: String,
+        #[source]
+        source: diesel::result::Error,
+    },
+
+    #[error("database pool error: {0}")]
+    Pool(#[from] diesel::r2d2::PoolError),
+
+    #[error("background task failed: {0}")]
+    Task(#[from] tokio::task::JoinError),
+
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
+
+    #[error(transparent)]
+    Csv(#[from] csv::Error),
+
+    #[error(transparent)]
+    Parquet(#[from] parquet::errors::ParquetError),
+
+    #[error("{0}")]
+    Other(String),
+}
+
+impl Error {
+    /// Wraps a Diesel error with the record it was raised for, for use with `map_err`.
+    pub fn database(context: &str) -> impl FnOnce(diesel::result::Error) -> Error + '_ {
+        move |source| Error::Database { context: context.to_owned(), source }
+    }
+
+    /// HTTP status of the failed response, if the error came from one.
+    pub fn http_status(&self) -> Option<u16> {
+        match self {
+            Error::Http { status, .. } => Some(*status),
+            Error::Network { source, .. } => source.status().map(|status| status.as_u16()),
+            _ => None,
+        }
+    }
+}
+
+impl From<diesel::result::Error> for Error {
+    fn from(source: diesel::result::Error) -> Self {
+        Error::Database { context: "query".to_owned(), source }
+    }
+}
+
+impl From<String> for Error {
+    fn from(message: String) -> Self {
+        Error::Other(message)
+    }
+}
+
+impl From<&str> for Error {
+    fn from(message: &str) -> Self {
+        Error::Other(message.to_owned())
+    }
+}
@@ -0,0 +1,473 @@
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use chrono::{NaiveDate, NaiveDateTime, Utc};
+use clap::ValueEnum;
+use diesel::pg::PgConnection;
+use diesel::prelude::*;
+use diesel::sql_types::{Array, Nullable, Text};
+use log::info;
+use parquet::basic::{Compression, LogicalType, Repetition, TimeUnit, Type as PhysicalType};
+use parquet::column::writer::ColumnWriter;
+use parquet::data_type::ByteArray;
+use parquet::file::properties::WriterProperties;
+use parquet::file::writer::SerializedFileWriter;
+use parquet::format::MicroSeconds;
+use parquet::schema::types::Type;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+
+use crate::database::context::Context;
+use crate::error::{Error, Result};
+
+/// Rows fetched from the cursor at a time, each batch is one Parquet row group.
+const BATCH_SIZE: usize = 50_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, ValueEnum, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FileFormat {
+    Csv,
+    Parquet,
+}
+
+impl FileFormat {
+    fn extension(self) -> &'static str {
+        match self {
+            FileFormat::Csv => "csv",
+            FileFormat::Parquet => "parquet",
+        }
+    }
+}
+
+/// Where the files are written and what goes in them.
+#[derive(Debug, Clone)]
+pub struct BulkExportOptions {
+    pub output_dir: PathBuf,
+    pub formats: Vec<FileFormat>,
+    /// Only rows belonging to this parliament, tables not tied to one are written in full.
+    pub parliament_id: Option<i32>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Kind {
+    /// A table of `schema.rs` as it is stored.
+    Table,
+    /// One of the EXPORT_* views, with the joins resolved.
+    Dataset,
+}
+
+impl Kind {
+    fn dir(self) -> &'static str {
+        match self {
+            Kind::Table => "tables",
+            Kind::Dataset => "datasets",
+        }
+    }
+}
+
+struct Relation {
+    name: &'static str,
+    source: &'static str,
+    kind: Kind,
+    /// Condition selecting the rows of the parliament `{parliament}`.
+    parliament: Option<&'static str>,
+}
+
+macro_rules! parliament_sessions {
+    () => {
+        "SELECT sessions.id FROM sessions WHERE sessions.parliament = {parliament}"
+    };
+}
+
+macro_rules! parliament_meetings {
+    () => {
+        "SELECT meetings.id FROM meetings JOIN sessions ON sessions.id = meetings.\"session\" WHERE sessions.parliament = {parliament}"
+    };
+}
+
+macro_rules! parliament_agenda_items {
+    () => {
+        concat!("SELECT meeting_agenda_item.agenda_item_id FROM meeting_agenda_item WHERE meeting_agenda_item.meeting_id IN (", parliament_meetings!(), ")")
+    };
+}
+
+macro_rules! parliament_registrations {
+    () => {
+        concat!("SELECT meeting_registration.registration_id FROM meeting_registration WHERE meeting_registration.meeting_id IN (", parliament_meetings!(), ")")
+    };
+}
+
+macro_rules! parliament_votes {
+    () => {
+        "SELECT VOTE_SCOPE.vote_id FROM VOTE_SCOPE WHERE VOTE_SCOPE.parliament_id = {parliament}"
+    };
+}
+
+/// Summaries of the parliament itself and of its sessions.
+macro_rules! parliament_periods {
+    () => {
+        concat!("(period = 'parliament' AND period_id = {parliament}) OR (period = 'session' AND period_id IN (", parliament_sessions!(), "))")
+    };
+}
+
+const fn table(name: &'static str, parliament: Option<&'static str>) -> Relation {
+    Relation { name, source: name, kind: Kind::Table, parliament }
+}
+
+const fn dataset(name: &'static str, source: &'static str) -> Relation {
+    Relation { name, source, kind: Kind::Dataset, parliament: Some("parliament_id = {parliament}") }
+}
+
+/// Every table of `schema.rs`, followed by the denormalised datasets.
+const RELATIONS: &[Relation] = &[
+    table("agenda_item", Some(concat!("id IN (", parliament_agenda_items!(), ")"))),
+    table("agenda_outcome", Some(concat!("meeting_id IN (", parliament_meetings!(), ")"))),
+    table("agenda_outcome_document", Some(concat!("meeting_id IN (", parliament_meetings!(), ")"))),
+    table("agenda_outcome_presenter", Some(concat!("meeting_id IN (", parliament_meetings!(), ")"))),
+    table("agenda_speech", Some(concat!("agenda_item_id IN (", parliament_agenda_items!(), ")"))),
+    table("agenda_vote", Some(concat!("agenda_item_id IN (", parliament_agenda_items!(), ")"))),
+    table("crawl_job", None),
+    table("cutting_line", Some(parliament_periods!())),
+    table("department", None),
+    table("department_member", Some("parliament = {parliament}")),
+    table("faction", Some("parliament = {parliament}")),
+    table("faction_cohesion", Some(parliament_periods!())),
+    table("faction_membership", Some("parliament = {parliament}")),
+    table("ideal_point", Some(parliament_periods!())),
+    table("meeting_agenda_item", Some(concat!("meeting_id IN (", parliament_meetings!(), ")"))),
+    table("meeting_data", Some(concat!("id IN (", parliament_meetings!(), ")"))),
+    table("meeting_registration", Some(concat!("meeting_id IN (", parliament_meetings!(), ")"))),
+    table("meetings", Some(concat!("\"session\" IN (", parliament_sessions!(), ")"))),
+    table("member_attendance", Some(parliament_periods!())),
+    table("member_cohesion", Some(parliament_periods!())),
+    table("office", Some("id IN (SELECT politician_office.office_id FROM politician_office WHERE politician_office.parliament = {parliament})")),
+    table("parliament", Some("id = {parliament}")),
+    table("parliamentary_group", Some("id IN (SELECT office.group_id FROM office JOIN politician_office ON politician_office.office_id = office.id WHERE politician_office.parliament = {parliament})")),
+    table("politician", Some("parliament = {parliament}")),
+    table("politician_office", Some("parliament = {parliament}")),
+    table("registration", Some(concat!("id IN (", parliament_registrations!(), ")"))),
+    table("registration_data", Some(concat!("id IN (", parliament_registrations!(), ")"))),
+    table("sessions", Some("parliament = {parliament}")),
+    table("sitting_attendance", Some(concat!("meeting_id IN (", parliament_meetings!(), ")"))),
+    table("speech", Some(concat!("id IN (SELECT agenda_speech.speech_id FROM agenda_speech WHERE agenda_speech.agenda_item_id IN (", parliament_agenda_items!(), "))"))),
+    table("speech_text", Some(concat!("meeting_id IN (", parliament_meetings!(), ")"))),
+    table("vote", Some(concat!("id IN (", parliament_votes!(), ")"))),
+    table("vote_data", Some(concat!("id IN (", parliament_votes!(), ")"))),
+    dataset("member_votes", "export_member_vote"),
+    dataset("votes", "export_vote"),
+    dataset("member_registrations", "export_member_registration"),
+    dataset("agenda", "export_agenda"),
+];
+
+/// One written file, as listed in `manifest.json`.
+#[derive(Debug, Clone, Serialize)]
+pub struct ManifestFile {
+    pub name: String,
+    pub kind: Kind,
+    pub format: FileFormat,
+    /// Relative to the manifest.
+    pub path: String,
+    pub rows: u64,
+    pub bytes: u64,
+    pub sha256: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Manifest {
+    pub generated_at: NaiveDateTime,
+    pub parliament_id: Option<i32>,
+    pub files: Vec<ManifestFile>,
+}
+
+#[derive(Debug, Clone, QueryableByName)]
+struct Column {
+    #[diesel(sql_type = Text)]
+    name: String,
+    #[diesel(sql_type = Text)]
+    data_type: String,
+}
+
+/// A row with every value as Postgres prints it, so any table reads the same way.
+#[derive(Debug, QueryableByName)]
+struct TextRow {
+    #[diesel(sql_type = Array<Nullable<Text>>)]
+    values: Vec<Option<String>>,
+}
+
+/// Writes every table and dataset in each format, then `manifest.json` with the row count,
+/// size and SHA-256 of each file.
+pub async fn export_all(ctx: &Context, opts: &BulkExportOptions) -> Result<()> {
+    for kind in [Kind::Table, Kind::Dataset] {
+        fs::create_dir_all(opts.output_dir.join(kind.dir()))?;
+    }
+
+    let mut files = Vec::new();
+    for relation in RELATIONS {
+        let export_opts = opts.clone();
+        let rows = ctx.run(move |conn| export_relation(conn, relation, &export_opts)).await?;
+        info!("Exported {} row(s) of {}", rows, relation.name);
+
+        for &format in &opts.formats {
+            let path = format!("{}/{}.{}", relation.kind.dir(), relation.name, format.extension());
+            let (bytes, sha256) = checksum(&opts.output_dir.join(&path))?;
+            files.push(ManifestFile {
+                name: relation.name.to_owned(),
+                kind: relation.kind,
+                format,
+                path,
+                rows,
+                bytes,
+                sha256,
+            });
+        }
+    }
+
+    let manifest = Manifest {
+        generated_at: Utc::now().naive_utc(),
+        parliament_id: opts.parliament_id,
+        files,
+    };
+    let mut writer = BufWriter::new(File::create(opts.output_dir.join("manifest.json"))?);
+    serde_json::to_writer_pretty(&mut writer, &manifest)?;
+    writeln!(writer)?;
+    writer.flush()?;
+    info!("Wrote {} file(s) to {}", manifest.files.len(), opts.output_dir.display());
+    Ok(())
+}
+
+/// Streams the rows of one table or dataset through a cursor into each requested file,
+/// ordered by every column so that unchanged data gives identical files. Returns the row count.
+fn export_relation(conn: &mut PgConnection, relation: &Relation, opts: &BulkExportOptions) -> Result<u64> {
+    let columns = diesel::sql_query(
+        "SELECT column_name::TEXT AS name, data_type::TEXT AS data_type FROM information_schema.columns \
+         WHERE table_schema = current_schema() AND table_name = $1 ORDER BY ordinal_position",
+    )
+    .bind::<Text, _>(relation.source)
+    .load::<Column>(conn)
+    .map_err(Error::database(relation.name))?;
+    if columns.is_empty() {
+        return Err(Error::from(format!("{} does not exist, are the migrations applied?", relation.source)));
+    }
+
+    let names = columns.iter().map(|column| format!("\"{}\"", column.name)).collect::<Vec<_>>();
+    let values = names.iter().map(|name| format!("{}::TEXT", name)).collect::<Vec<_>>().join(", ");
+    let filter = match (opts.parliament_id, relation.parliament) {
+        (Some(parliament_id), Some(condition)) => format!(" WHERE {}", condition.replace("{parliament}", &parliament_id.to_string())),
+        _ => String::new(),
+    };
+    let query = format!(
+        "DECLARE export_rows NO SCROLL CURSOR FOR SELECT ARRAY[{}] AS \"values\" FROM {}{} ORDER BY {}",
+        values,
+        relation.source,
+        filter,
+        names.join(", ")
+    );
+
+    let dir = opts.output_dir.join(relation.kind.dir());
+    let mut csv = match opts.formats.contains(&FileFormat::Csv) {
+        true => Some(csv::Writer::from_writer(BufWriter::new(File::create(dir.join(format!("{}.csv", relation.name)))?))),
+        false => None,
+    };
+    let mut parquet = match opts.formats.contains(&FileFormat::Parquet) {
+        true => Some(ParquetSink::create(&dir.join(format!("{}.parquet", relation.name)), relation.name, &columns)?),
+        false => None,
+    };
+    if let Some(csv) = csv.as_mut() {
+        csv.write_record(columns.iter().map(|column| &column.name))?;
+    }
+
+    let rows = conn
+        .build_transaction()
+        .read_only()
+        .run(|conn| -> Result<u64> {
+            diesel::sql_query(&query).execute(conn).map_err(Error::database(relation.name))?;
+            let fetch = format!("FETCH {} FROM export_rows", BATCH_SIZE);
+            let mut rows = 0;
+            loop {
+                let batch = diesel::sql_query(&fetch).load::<TextRow>(conn).map_err(Error::database(relation.name))?;
+                if batch.is_empty() {
+                    break;
+                }
+                if let Some(csv) = csv.as_mut() {
+                    for row in &batch {
+                        csv.write_record(row.values.iter().map(|value| value.as_deref().unwrap_or("")))?;
+                    }
+                }
+                if let Some(parquet) = parquet.as_mut() {
+                    parquet.write_batch(&batch)?;
+                }
+                rows += batch.len() as u64;
+            }
+            Ok(rows)
+        })?;
+
+    if let Some(mut csv) = csv {
+        csv.flush()?;
+    }
+    if let Some(parquet) = parquet {
+        parquet.close()?;
+    }
+    Ok(rows)
+}
+
+fn checksum(path: &Path) -> Result<(u64, String)> {
+    let mut hasher = Sha256::new();
+    let bytes = io::copy(&mut File::open(path)?, &mut hasher)?;
+    Ok((bytes, format!("{:x}", hasher.finalize())))
+}
+
+/// Parquet type each Postgres type is written as, anything without a counterpart
+/// (enums, arrays, text) is written as its text.
+#[derive(Debug, Clone, Copy)]
+enum ColumnType {
+    Boolean,
+    Int32,
+    Int64,
+    Double,
+    Date,
+    Timestamp,
+    Text,
+}
+
+impl ColumnType {
+    fn of(data_type: &str) -> ColumnType {
+        match data_type {
+            "boolean" => ColumnType::Boolean,
+            "smallint" | "integer" => ColumnType::Int32,
+            "bigint" => ColumnType::Int64,
+            "real" | "double precision" | "numeric" => ColumnType::Double,
+            "date" => ColumnType::Date,
+            "timestamp without time zone" => ColumnType::Timestamp,
+            _ => ColumnType::Text,
+        }
+    }
+
+    fn parquet_type(self, name: &str) -> Result<Type> {
+        let (physical, logical) = match self {
+            ColumnType::Boolean => (PhysicalType::BOOLEAN, None),
+            ColumnType::Int32 => (PhysicalType::INT32, None),
+            ColumnType::Int64 => (PhysicalType::INT64, None),
+            ColumnType::Double => (PhysicalType::DOUBLE, None),
+            ColumnType::Date => (PhysicalType::INT32, Some(LogicalType::Date)),
+            ColumnType::Timestamp => (PhysicalType::INT64, Some(LogicalType::Timestamp { is_adjusted_to_u_t_c: false, unit: TimeUnit::MICROS(MicroSeconds {}) })),
+            ColumnType::Text => (PhysicalType::BYTE_ARRAY, Some(LogicalType::String)),
+        };
+        Ok(Type::primitive_type_builder(name, physical).with_repetition(Repetition::OPTIONAL).with_logical_type(logical).build()?)
+    }
+
+    fn values(self) -> Values {
+        match self {
+            ColumnType::Boolean => Values::Boolean(Vec::new()),
+            ColumnType::Int32 | ColumnType::Date => Values::Int32(Vec::new()),
+            ColumnType::Int64 | ColumnType::Timestamp => Values::Int64(Vec::new()),
+            ColumnType::Double => Values::Double(Vec::new()),
+            ColumnType::Text => Values::ByteArray(Vec::new()),
+        }
+    }
+}
+
+/// Non-null values of a column in one row group.
+enum Values {
+    Boolean(Vec<bool>),
+    Int32(Vec<i32>),
+    Int64(Vec<i64>),
+    Double(Vec<f64>),
+    ByteArray(Vec<ByteArray>),
+}
+
+impl Values {
+    /// Parses a value as printed by Postgres.
+    fn push(&mut self, column_type: ColumnType, value: &str) -> Option<()> {
+        match (self, column_type) {
+            (Values::Boolean(values), _) => values.push(match value {
+                "t" | "true" => true,
+                "f" | "false" => false,
+                _ => return None,
+            }),
+            (Values::Int32(values), ColumnType::Date) => {
+                let epoch = NaiveDate::from_ymd_opt(1970, 1, 1)?;
+                values.push((NaiveDate::parse_from_str(value, "%Y-%m-%d").ok()? - epoch).num_days().try_into().ok()?)
+            }
+            (Values::Int32(values), _) => values.push(value.parse().ok()?),
+            (Values::Int64(values), ColumnType::Timestamp) => values.push(NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M:%S%.f").ok()?.timestamp_micros()),
+            (Values::Int64(values), _) => values.push(value.parse().ok()?),
+            (Values::Double(values), _) => values.push(value.parse().ok()?),
+            (Values::ByteArray(values), _) => values.push(ByteArray::from(value.as_bytes().to_vec())),
+        }
+        Some(())
+    }
+
+    fn clear(&mut self) {
+        match self {
+            Values::Boolean(values) => values.clear(),
+            Values::Int32(values) => values.clear(),
+            Values::Int64(values) => values.clear(),
+            Values::Double(values) => values.clear(),
+            Values::ByteArray(values) => values.clear(),
+        }
+    }
+}
+
+/// A Parquet file written one row group per fetched batch.
+struct ParquetSink {
+    writer: SerializedFileWriter<BufWriter<File>>,
+    relation: &'static str,
+    columns: Vec<(String, ColumnType, Values)>,
+    definition_levels: Vec<i16>,
+}
+
+impl ParquetSink {
+    fn create(path: &Path, relation: &'static str, columns: &[Column]) -> Result<ParquetSink> {
+        let columns = columns
+            .iter()
+            .map(|column| {
+                let column_type = ColumnType::of(&column.data_type);
+                (column.name.clone(), column_type, column_type.values())
+            })
+            .collect::<Vec<_>>();
+        let mut fields = columns.iter().map(|(name, column_type, _)| column_type.parquet_type(name).map(Arc::new)).collect::<Result<Vec<_>>>()?;
+        let schema = Type::group_type_builder(relation).with_fields(&mut fields).build()?;
+        let properties = WriterProperties::builder().set_compression(Compression::SNAPPY).build();
+        let writer = SerializedFileWriter::new(BufWriter::new(File::create(path)?), Arc::new(schema), Arc::new(properties))?;
+        Ok(ParquetSink { writer, relation, columns, definition_levels: Vec::new() })
+    }
+
+    fn write_batch(&mut self, rows: &[TextRow]) -> Result<()> {
+        let mut row_group = self.writer.next_row_group()?;
+        for (index, (name, column_type, values)) in self.columns.iter_mut().enumerate() {
+            values.clear();
+            self.definition_levels.clear();
+            for row in rows {
+                match &row.values[index] {
+                    Some(value) => {
+                        values.push(*column_type, value).ok_or_else(|| Error::from(format!("{}.{} has unexpected value {:?}", self.relation, name, value)))?;
+                        self.definition_levels.push(1);
+                    }
+                    None => self.definition_levels.push(0),
+                }
+            }
+
+            let mut column = row_group.next_column()?.expect("a column writer for each schema field");
+            let levels = Some(self.definition_levels.as_slice());
+            match (column.untyped(), &*values) {
+                (ColumnWriter::BoolColumnWriter(writer), Values::Boolean(values)) => writer.write_batch(values, levels, None)?,
+                (ColumnWriter::Int32ColumnWriter(writer), Values::Int32(values)) => writer.write_batch(values, levels, None)?,
+                (ColumnWriter::Int64ColumnWriter(writer), Values::Int64(values)) => writer.write_batch(values, levels, None)?,
+                (ColumnWriter::DoubleColumnWriter(writer), Values::Double(values)) => writer.write_batch(values, levels, None)?,
+                (ColumnWriter::ByteArrayColumnWriter(writer), Values::ByteArray(values)) => writer.write_batch(values, levels, None)?,
+                _ => unreachable!("column writers follow the schema built from the same types"),
+            };
+            column.close()?;
+        }
+        row_group.close()?;
+        Ok(())
+    }
+
+    fn close(self) -> Result<()> {
+        self.writer.into_inner()?.flush()?;
+        Ok(())
+    }
+}
@@ -0,0 +1,48 @@
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::PathBuf;
+
+use clap::ValueEnum;
+use serde::Serialize;
+
+use crate::error::Result;
+
+pub mod bulk;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Format {
+    Csv,
+    Json,
+}
+
+/// Where and how query results are written.
+#[derive(Debug, Clone)]
+pub struct ExportOptions {
+    pub format: Format,
+    /// Standard output when not set.
+    pub output: Option<PathBuf>,
+}
+
+/// Writes rows as CSV with a header line, or as a JSON array.
+pub fn write_rows<T: Serialize>(rows: &[T], opts: &ExportOptions) -> Result<()> {
+    let writer: Box<dyn Write> = match &opts.output {
+        Some(path) => Box::new(BufWriter::new(File::create(path)?)),
+        None => Box::new(BufWriter::new(io::stdout().lock())),
+    };
+    match opts.format {
+        Format::Csv => {
+            let mut writer = csv::Writer::from_writer(writer);
+            for row in rows {
+                writer.serialize(row)?;
+            }
+            writer.flush()?;
+        }
+        Format::Json => {
+            let mut writer = writer;
+            serde_json::to_writer_pretty(&mut writer, rows)?;
+            writeln!(writer)?;
+            writer.flush()?;
+        }
+    }
+    Ok(())
+}
\ No newline at end of file
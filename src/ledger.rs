@@ -0,0 +1,160 @@
+use std::collections::HashSet;
+use std::future::Future;
+
+use chrono::{NaiveDateTime, Utc};
+use log::{error, warn};
+
+use crate::database::context::Context;
+use crate::error::{Error, Result};
+use crate::models::crawl_job::{CrawlJob, CrawlStatus};
+
+/// LRS endpoints tracked in the `crawl_job` table, keyed by the ID they are queried with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Endpoint {
+    Politicians,
+    Departments,
+    Sessions,
+    Meetings,
+    MeetingData,
+    VotingData,
+    RegistrationData,
+}
+
+impl Endpoint {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Endpoint::Politicians => "politicians",
+            Endpoint::Departments => "departments",
+            Endpoint::Sessions => "sessions",
+            Endpoint::Meetings => "meetings",
+            Endpoint::MeetingData => "meeting_data",
+            Endpoint::VotingData => "voting_data",
+            Endpoint::RegistrationData => "registration_data",
+        }
+    }
+}
+
+
+/// Runs a fetch job and records its outcome in the ledger.
+///
+/// A job that saved nothing is recorded as `empty`, a job that failed as `failed`
+/// together with the error and HTTP status.
+pub async fn track<F>(ctx: Context, endpoint: Endpoint, entity_id: i32, job: F) -> Result<usize>
+where
+    F: Future<Output = Result<usize>>,
+{
+    let result = job.await;
+
+    let (status, last_error, http_status) = outcome(&result);
+    let crawl_job = CrawlJob {
+        endpoint: endpoint.as_str().to_owned(),
+        entity_id,
+        attempts: i32::from(status == CrawlStatus::Failed),
+        status,
+        last_error,
+        http_status,
+        fetched_at: Utc::now().naive_utc(),
+    };
+
+    if let Err(error) = ctx.run(move |conn| crawl_job.record(conn)).await {
+        error!("Failed to record {} {} in the crawl ledger: {}", endpoint.as_str(), entity_id, error);
+    }
+    result
+}
+
+/// Status, error message and HTTP status a job result is recorded with.
+fn outcome(result: &Result<usize>) -> (CrawlStatus, Option<String>, Option<i32>) {
+    match result {
+        Ok(0) => (CrawlStatus::Empty, None, None),
+        Ok(_) => (CrawlStatus::Done, None, None),
+        Err(error) => (CrawlStatus::Failed, Some(error.to_string()), error.http_status().map(i32::from)),
+    }
+}
+
+/// Drops the IDs the ledger says should not be fetched again.
+pub async fn pending(ctx: &Context, endpoint: Endpoint, ids: Vec<i32>, max_attempts: i32, resume: bool) -> Result<Vec<i32>> {
+    let skipped: HashSet<i32> = ctx
+        .run(move |conn| CrawlJob::get_skipped_ids(conn, endpoint.as_str(), max_attempts, resume))
+        .await?
+        .into_iter()
+        .collect();
+    if !skipped.is_empty() {
+        warn!("Skipping {} {} item(s) based on the crawl ledger", skipped.len(), endpoint.as_str());
+    }
+    Ok(ids.into_iter().filter(|id| !skipped.contains(id)).collect())
+}
+
+
+pub async fn print_report(ctx: &Context) -> Result<()> {
+    let counts = ctx.run(CrawlJob::get_status_counts).await?;
+    let failures = ctx.run(|conn| CrawlJob::get_failures(conn, None)).await?;
+
+    println!("{:<20} {:<8} {:>8}", "endpoint", "status", "count");
+    for (endpoint, status, count) in counts {
+        println!("{:<20} {:<8} {:>8}", endpoint, format!("{:?}", status).to_lowercase(), count);
+    }
+
+    if !failures.is_empty() {
+        println!();
+        print_failures(failures);
+    }
+    Ok(())
+}
+
+/// Ends a crawl command: prints the ledger failures recorded since it started and fails if any job did.
+///
+/// `failed` also counts jobs the ledger does not track, such as document downloads and parsing.
+pub async fn finish(ctx: &Context, started: NaiveDateTime, failed: usize) -> Result<()> {
+    if failed == 0 {
+        return Ok(());
+    }
+    let failures = ctx.run(move |conn| CrawlJob::get_failures(conn, Some(started))).await?;
+    if !failures.is_empty() {
+        print_failures(failures);
+    }
+    Err(Error::Other(format!("{} job(s) failed", failed)))
+}
+
+fn print_failures(failures: Vec<CrawlJob>) {
+    println!("{:<20} {:>10} {:>8} {:>6}  {:<19}  error", "endpoint", "id", "attempts", "http", "fetched at");
+    for failure in failures {
+        println!(
+            "{:<20} {:>10} {:>8} {:>6}  {:<19}  {}",
+            failure.endpoint,
+            failure.entity_id,
+            failure.attempts,
+            failure.http_status.map(|x| x.to_string()).unwrap_or_default(),
+            failure.fetched_at.format("%Y-%m-%d %H:%M:%S"),
+            failure.last_error.unwrap_or_default(),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn records_saved_rows_as_done() {
+        assert_eq!(outcome(&Ok(3)), (CrawlStatus::Done, None, None));
+    }
+
+    #[test]
+    fn records_nothing_saved_as_empty() {
+        assert_eq!(outcome(&Ok(0)), (CrawlStatus::Empty, None, None));
+    }
+
+    #[test]
+    fn records_errors_as_failed_with_the_http_status() {
+        let error = Error::Http { url: "https://apps.lrs.lt/sip/p2b.ad_seimo_sesijos".to_owned(), status: 503 };
+        assert_eq!(
+            outcome(&Err(error)),
+            (CrawlStatus::Failed, Some("https://apps.lrs.lt/sip/p2b.ad_seimo_sesijos returned HTTP 503".to_owned()), Some(503))
+        );
+
+        let (status, last_error, http_status) = outcome(&Err(Error::MissingFixture("https://apps.lrs.lt".to_owned())));
+        assert_eq!(status, CrawlStatus::Failed);
+        assert_eq!(last_error.as_deref(), Some("no recorded response for https://apps.lrs.lt"));
+        assert_eq!(http_status, None);
+    }
+}
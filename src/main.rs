@@ -1,20 +1,87 @@
 use std::error::Error;
 
+use chrono::Utc;
+use clap::Parser;
 use log::info;
 
+use cli::{Cli, Command};
+use crawler::CrawlOptions;
+use database::{connect::establish_pool, context::Context};
+use networking::client;
+use networking::fixture::{self, FixtureStore, RequestMode};
+
+pub mod analysis;
+pub mod api;
+pub mod cli;
 pub mod crawler;
 pub mod database;
+pub mod error;
+pub mod export;
+pub mod ledger;
 pub mod models;
 pub mod parser;
 pub mod networking;
 pub mod schema;
+pub mod sync;
 
 #[tokio::main(worker_threads = 16)]
 async fn main() -> Result<(), Box<dyn Error + Send + Sync>> {
     env_logger::init();
-    
-    crawler::download_all_parliament(9).await?;
-    
+    let cli = Cli::parse();
+    client::init(cli.http.into());
+
+    if let Some(dir) = cli.record {
+        fixture::set_mode(RequestMode::Record(FixtureStore::new(dir)));
+    } else if let Some(dir) = cli.replay {
+        fixture::set_mode(RequestMode::Replay(FixtureStore::new(dir)));
+    }
+
+    let ctx = Context::new(establish_pool(cli.pool_size)?);
+    let started = Utc::now().naive_utc();
+
+    match cli.command {
+        Command::DownloadParliaments(args) => ledger::finish(&ctx, started, crawler::download_parliaments(&ctx, &args.into()).await?).await?,
+        Command::DownloadPoliticians(args) => ledger::finish(&ctx, started, crawler::download_politicians(&ctx, &args.into()).await?).await?,
+        Command::DownloadDepartments(args) => ledger::finish(&ctx, started, crawler::download_departments(&ctx, &args.into()).await?).await?,
+        Command::DownloadSessions(args) => ledger::finish(&ctx, started, crawler::download_sessions(&ctx, &args.into()).await?).await?,
+        Command::DownloadMeetings(args) => ledger::finish(&ctx, started, crawler::download_meetings(&ctx, &args.into()).await?).await?,
+        Command::DownloadMeetingData(args) => ledger::finish(&ctx, started, crawler::download_meeting_data(&ctx, &args.into()).await?).await?,
+        Command::DownloadVotingData(args) => ledger::finish(&ctx, started, crawler::download_voting_data(&ctx, &args.into()).await?).await?,
+        Command::DownloadRegistrationData(args) => ledger::finish(&ctx, started, crawler::download_registration_data(&ctx, &args.into()).await?).await?,
+        Command::DownloadMeetingsDocuments(args) => ledger::finish(&ctx, started, crawler::download_all_meetings_documents(&ctx, &args.into()).await?).await?,
+        Command::ParseStenograms(args) => ledger::finish(&ctx, started, crawler::parse_stenograms(&ctx, &args.into()).await?).await?,
+        Command::ParseProtocols(args) => ledger::finish(&ctx, started, crawler::parse_protocols(&ctx, &args.into()).await?).await?,
+        Command::DownloadAllParliament(args) => {
+            let opts: CrawlOptions = args.into();
+            if opts.parliament_ids.is_empty() {
+                return Err("download_all_parliament requires at least one --parliament".into());
+            }
+            let mut failed = 0;
+            for parliament_id in opts.parliament_ids.clone() {
+                failed += crawler::download_all_parliament(&ctx, parliament_id, &opts).await?;
+            }
+            if !opts.dry_run {
+                analysis::refresh_summaries(&ctx).await?;
+            }
+            ledger::finish(&ctx, started, failed).await?;
+        }
+        Command::DownloadAll(args) => ledger::finish(&ctx, started, crawler::download_all(&ctx, &args.into()).await?).await?,
+        Command::CrawlReport => ledger::print_report(&ctx).await?,
+        Command::Sync(args) => sync::run(&ctx, &args.into()).await?,
+        Command::VoteBreakdown(args) => analysis::vote_breakdown::export_vote_breakdown(&ctx, args.parliament_ids, args.vote_ids, &args.export.into()).await?,
+        Command::Cohesion(args) => analysis::cohesion::export_cohesion(&ctx, args.period(), args.members, &args.export.into()).await?,
+        Command::Similarity(args) => analysis::similarity::export_similarity(&ctx, args.parliament_id, (&args).into(), args.pairs, &args.export.into()).await?,
+        Command::IdealPoints(args) => analysis::ideal_points::export_ideal_points(&ctx, args.parliament_id, args.per_session, args.cutting_lines, (&args).into(), &args.export.into()).await?,
+        Command::Attendance(args) => match (args.meeting_id, args.period()) {
+            (Some(meeting_id), _) => analysis::attendance::export_sitting_attendance(&ctx, meeting_id, &args.export.into()).await?,
+            (None, Some(period)) => analysis::attendance::export_member_attendance(&ctx, period, &args.export.into()).await?,
+            (None, None) => unreachable!("clap requires a period"),
+        },
+        Command::MemberVotes(args) => analysis::member_votes::export_member_votes(&ctx, args.filter(), &args.export.into()).await?,
+        Command::RefreshSummaries => analysis::refresh_summaries(&ctx).await?,
+        Command::Serve(args) => api::serve(&ctx, &args.into()).await?,
+        Command::Export(args) => export::bulk::export_all(&ctx, &args.into()).await?,
+    }
 
     info!("Done.");
     Ok(())
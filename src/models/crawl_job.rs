@@ -0,0 +1,117 @@
+use chrono::NaiveDateTime;
+use diesel::{prelude::*, upsert::excluded};
+use diesel_derive_enum::DbEnum;
+use log::{debug, error};
+
+use crate::schema::crawl_job;
+
+/// Outcome of the latest fetch of one entity from one LRS endpoint.
+#[derive(Insertable, Debug, Queryable, AsChangeset)]
+#[diesel(table_name = crawl_job)]
+pub struct CrawlJob {
+    pub endpoint: String,
+    pub entity_id: i32,
+    pub status: CrawlStatus,
+    /// Consecutive failed fetches, 0 once the entity was fetched successfully.
+    pub attempts: i32,
+    pub last_error: Option<String>,
+    pub http_status: Option<i32>,
+    pub fetched_at: NaiveDateTime,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, DbEnum)]
+#[DieselTypePath = "crate::schema::sql_types::PqCrawlStatus"]
+pub enum CrawlStatus {
+    Done,
+    Empty,
+    Failed,
+}
+
+impl CrawlJob {
+    /// Records an attempt. A failure increments `attempts` if the entity was fetched before,
+    /// a success resets it, so only an unbroken run of failures gets an entity skipped.
+    pub fn record(&self, conn: &mut PgConnection) -> Result<CrawlJob, diesel::result::Error> {
+        debug!("Recording {:?}", self);
+        let failed = i32::from(self.status == CrawlStatus::Failed);
+        diesel::insert_into(crawl_job::table)
+            .values(self)
+            .on_conflict((crawl_job::endpoint, crawl_job::entity_id))
+            .do_update()
+            .set((
+                crawl_job::status.eq(excluded(crawl_job::status)),
+                crawl_job::attempts.eq((crawl_job::attempts + 1) * failed),
+                crawl_job::last_error.eq(excluded(crawl_job::last_error)),
+                crawl_job::http_status.eq(excluded(crawl_job::http_status)),
+                crawl_job::fetched_at.eq(excluded(crawl_job::fetched_at)),
+            ))
+            .get_result::<CrawlJob>(conn)
+            .map_err(|err| {
+                error!("{:?}\n{:?}", self, err);
+                err
+            })
+    }
+
+    /// IDs that should not be fetched again, see `is_skipped`.
+    pub fn get_skipped_ids(conn: &mut PgConnection, endpoint: &str, max_attempts: i32, resume: bool) -> Result<Vec<i32>, diesel::result::Error> {
+        let jobs = crawl_job::table
+            .filter(crawl_job::endpoint.eq(endpoint))
+            .select((crawl_job::entity_id, crawl_job::status, crawl_job::attempts))
+            .load::<(i32, CrawlStatus, i32)>(conn)?;
+        Ok(jobs
+            .into_iter()
+            .filter(|(_, status, attempts)| CrawlJob::is_skipped(*status, *attempts, max_attempts, resume))
+            .map(|(entity_id, _, _)| entity_id)
+            .collect())
+    }
+
+    /// Whether an entity is left alone: it failed `max_attempts` times or more in a row,
+    /// or, when `resume` is set, it was already fetched.
+    fn is_skipped(status: CrawlStatus, attempts: i32, max_attempts: i32, resume: bool) -> bool {
+        match status {
+            CrawlStatus::Failed => attempts >= max_attempts,
+            CrawlStatus::Done | CrawlStatus::Empty => resume,
+        }
+    }
+
+    /// Entities whose latest fetch failed, only those fetched from `since` on when set.
+    pub fn get_failures(conn: &mut PgConnection, since: Option<NaiveDateTime>) -> Result<Vec<CrawlJob>, diesel::result::Error> {
+        let mut query = crawl_job::table
+            .filter(crawl_job::status.eq(CrawlStatus::Failed))
+            .order((crawl_job::endpoint, crawl_job::attempts.desc(), crawl_job::entity_id))
+            .into_boxed();
+        if let Some(since) = since {
+            query = query.filter(crawl_job::fetched_at.ge(since));
+        }
+        query.load::<CrawlJob>(conn)
+    }
+
+    pub fn get_status_counts(conn: &mut PgConnection) -> Result<Vec<(String, CrawlStatus, i64)>, diesel::result::Error> {
+        crawl_job::table
+            .group_by((crawl_job::endpoint, crawl_job::status))
+            .select((crawl_job::endpoint, crawl_job::status, diesel::dsl::count_star()))
+            .order((crawl_job::endpoint, crawl_job::status))
+            .load::<(String, CrawlStatus, i64)>(conn)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn skips_entities_that_failed_too_often() {
+        assert!(!CrawlJob::is_skipped(CrawlStatus::Failed, 2, 3, false));
+        assert!(CrawlJob::is_skipped(CrawlStatus::Failed, 3, 3, false));
+        assert!(CrawlJob::is_skipped(CrawlStatus::Failed, 4, 3, true));
+        assert!(!CrawlJob::is_skipped(CrawlStatus::Failed, 1, 3, true));
+    }
+
+    #[test]
+    fn refetches_fetched_entities_unless_resuming() {
+        assert!(!CrawlJob::is_skipped(CrawlStatus::Done, 0, 3, false));
+        assert!(!CrawlJob::is_skipped(CrawlStatus::Empty, 0, 3, false));
+        assert!(CrawlJob::is_skipped(CrawlStatus::Done, 0, 3, true));
+        assert!(CrawlJob::is_skipped(CrawlStatus::Empty, 0, 3, true));
+    }
+}
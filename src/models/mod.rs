@@ -1,2 +1,4 @@
 pub mod session;
-pub mod politicians;
\ No newline at end of file
+pub mod politicians;
+pub mod crawl_job;
+pub mod upsert;
\ No newline at end of file
@@ -0,0 +1,80 @@
+use diesel::prelude::*;
+use chrono::NaiveDate;
+use diesel_derive_enum::DbEnum;
+use log::debug;
+
+use crate::models::upsert::upsert_table;
+use crate::schema::{department, department_member, parliamentary_group};
+
+/// A structural unit of the Seimas: committee, commission or faction.
+#[derive(Insertable, Debug, PartialEq, Queryable, AsChangeset)]
+#[diesel(table_name = department)]
+pub struct Department {
+    pub id: i32,
+    pub name: String,
+    pub short_name: Option<String>,
+    pub kind: DepartmentKind,
+}
+
+/// One stint of a member in a department during a parliament.
+#[derive(Insertable, Debug, Queryable)]
+#[diesel(table_name = department_member)]
+pub struct DepartmentMember {
+    pub department_id: i32,
+    pub parliament: i32,
+    pub position: i32,
+    pub politician_id: i32,
+    pub duties: Option<String>,
+    pub from: Option<NaiveDate>,
+    pub to: Option<NaiveDate>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, DbEnum)]
+#[DieselTypePath = "crate::schema::sql_types::PqDepartmentKind"]
+pub enum DepartmentKind {
+    Committee,
+    Commission,
+    Faction,
+    Other,
+}
+
+/// A parliamentary group (parlamentinė grupė), known only from the offices of its members.
+///
+/// Groups are numbered apart from departments, so the same ID can stand for both.
+#[derive(Insertable, Debug, PartialEq, Queryable, AsChangeset)]
+#[diesel(table_name = parliamentary_group)]
+pub struct ParliamentaryGroup {
+    pub id: i32,
+    pub name: String,
+}
+
+upsert_table!(Department, department, (id: i32), [name, short_name, kind]);
+upsert_table!(ParliamentaryGroup, parliamentary_group, (id: i32), [name]);
+
+
+impl Department {
+    /// Registers a department known only from the offices of a member, leaving one that was already crawled untouched.
+    ///
+    /// Returns the kind stored, which for a crawled department is the one its own endpoint lists it under.
+    pub fn ensure(conn: &mut PgConnection, id: i32, name: &str, kind: DepartmentKind) -> Result<DepartmentKind, diesel::result::Error> {
+        diesel::insert_into(department::table)
+            .values(Department { id, name: name.to_owned(), short_name: None, kind })
+            .on_conflict(department::id)
+            .do_nothing()
+            .execute(conn)?;
+        department::table.find(id).select(department::kind).first(conn)
+    }
+}
+
+
+impl DepartmentMember {
+    /// Replaces the composition history of a department during one parliament.
+    pub fn replace(conn: &mut PgConnection, department_id: i32, parliament: i32, members: &[DepartmentMember]) -> Result<usize, diesel::result::Error> {
+        debug!("Saving {} member(s) of department {} in parliament {}", members.len(), department_id, parliament);
+        diesel::delete(department_member::table.filter(department_member::department_id.eq(department_id).and(department_member::parliament.eq(parliament)))).execute(conn)?;
+        if members.is_empty() {
+            return Ok(0);
+        }
+        diesel::insert_into(department_member::table).values(members).execute(conn)
+    }
+}
@@ -0,0 +1,98 @@
+use diesel::prelude::*;
+use chrono::NaiveDate;
+use log::debug;
+
+use crate::models::upsert::upsert_table;
+use crate::schema::{faction, faction_membership};
+
+/// A parliamentary faction (frakcija), also listed in `department`.
+#[derive(Insertable, Debug, PartialEq, Queryable, AsChangeset)]
+#[diesel(table_name = faction)]
+pub struct Faction {
+    pub id: i32,
+    pub parliament: i32,
+    pub name: String,
+    pub short_name: Option<String>,
+}
+
+/// A member's stint in a faction; `to` is open while the member still belongs to it.
+#[derive(Insertable, Debug, PartialEq, Queryable, AsChangeset)]
+#[diesel(table_name = faction_membership)]
+pub struct FactionMembership {
+    pub politician_id: i32,
+    pub parliament: i32,
+    pub faction_id: i32,
+    pub from: NaiveDate,
+    pub to: Option<NaiveDate>,
+    pub duties: Option<String>,
+}
+
+table! {
+    vote_faction (vote_id, person_id) {
+        vote_id -> Int4,
+        person_id -> Int4,
+        faction_id -> Int4,
+    }
+}
+
+upsert_table!(Faction, faction, (id: i32), [parliament, name, short_name]);
+upsert_table!(FactionMembership, faction_membership, (politician_id: i32, parliament: i32, faction_id: i32, from: NaiveDate), [to, duties]);
+
+
+impl Faction {
+    /// Registers a faction known only from the offices of a member, leaving one that was already crawled untouched.
+    pub fn ensure(conn: &mut PgConnection, id: i32, parliament: i32, name: &str) -> Result<usize, diesel::result::Error> {
+        diesel::insert_into(faction::table)
+            .values(Faction { id, parliament, name: name.to_owned(), short_name: None })
+            .on_conflict(faction::id)
+            .do_nothing()
+            .execute(conn)
+    }
+}
+
+
+impl FactionMembership {
+    /// Merges stints into the stored history, as listed with either the member or the faction.
+    ///
+    /// A stint overlapping a stored stint of the same member and faction is the same stint seen by
+    /// the other source: both become one row starting at the earlier date, with the end date and
+    /// duties of the latest crawl. Stints of other members and factions are left untouched.
+    pub fn merge(conn: &mut PgConnection, memberships: &[FactionMembership]) -> Result<usize, diesel::result::Error> {
+        debug!("Merging {} faction membership(s)", memberships.len());
+        conn.transaction(|conn| {
+            for membership in memberships {
+                let stored: Vec<FactionMembership> = faction_membership::table
+                    .filter(faction_membership::politician_id.eq(membership.politician_id))
+                    .filter(faction_membership::parliament.eq(membership.parliament))
+                    .filter(faction_membership::faction_id.eq(membership.faction_id))
+                    .load(conn)?;
+                let overlapping: Vec<&FactionMembership> = stored.iter().filter(|stint| stint.overlaps(membership)).collect();
+
+                let mut from = membership.from;
+                let mut duties = membership.duties.clone();
+                for stint in &overlapping {
+                    from = from.min(stint.from);
+                    duties = duties.or_else(|| stint.duties.clone());
+                    diesel::delete(faction_membership::table.find((stint.politician_id, stint.parliament, stint.faction_id, stint.from))).execute(conn)?;
+                }
+                diesel::insert_into(faction_membership::table)
+                    .values(FactionMembership { from, duties, to: membership.to, ..*membership })
+                    .execute(conn)?;
+            }
+            Ok(memberships.len())
+        })
+    }
+
+    /// Whether two stints share at least one day.
+    fn overlaps(&self, other: &FactionMembership) -> bool {
+        self.to.is_none_or(|to| other.from <= to) && other.to.is_none_or(|to| self.from <= to)
+    }
+
+    /// Faction each member was in on the day of a vote.
+    pub fn get_factions_per_vote(conn: &mut PgConnection, vote_id: i32) -> Result<Vec<(i32, i32)>, diesel::result::Error> {
+        vote_faction::table
+            .filter(vote_faction::vote_id.eq(vote_id))
+            .select((vote_faction::person_id, vote_faction::faction_id))
+            .load::<(i32, i32)>(conn)
+    }
+}
@@ -1 +1,3 @@
-pub mod politician;
\ No newline at end of file
+pub mod politician;
+pub mod department;
+pub mod faction;
\ No newline at end of file
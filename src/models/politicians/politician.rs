@@ -1,12 +1,16 @@
-use diesel::{prelude::*, upsert::on_constraint};
+use async_graphql::{Enum, SimpleObject};
+use diesel::prelude::*;
 use chrono::NaiveDate;
 use log::{debug, error};
+use serde::Serialize;
 
-use crate::{schema::{politician, office}, database::connect::establish_connection};
+use crate::models::upsert::upsert_table;
+use crate::schema::{politician, office, politician_office};
 use diesel_derive_enum::DbEnum;
 
-#[derive(Insertable, Queryable, Identifiable, Debug, PartialEq, AsChangeset)]
+#[derive(Insertable, Queryable, Identifiable, Debug, Clone, PartialEq, AsChangeset, Serialize, SimpleObject)]
 #[diesel(table_name = politician)]
+#[graphql(complex)]
 pub struct Politician {
     pub id: i32,
     pub parliament: i32,
@@ -22,121 +26,140 @@ pub struct Politician {
     pub email: Option<String>,
     pub phone: Vec<Option<String>>,
     pub website: Option<String>,
-    pub offices: Vec<Option<i32>>,
 }
 
 #[derive(Insertable, Debug, AsChangeset)]
 #[diesel(table_name = office)]
 pub struct OfficeInsertable {
     pub department_id: Option<i32>,
-    pub department_name: Option<String>,
     pub department_type: Option<DepartmentType>,
     pub duties: Option<String>,
     pub from: Option<NaiveDate>,
     pub to: Option<NaiveDate>,
+    pub group_id: Option<i32>,
 }
 
-#[derive(Debug, Queryable, AsChangeset)]
+#[derive(Debug, Clone, Queryable, AsChangeset, Serialize, SimpleObject)]
 #[diesel(table_name = office)]
+#[graphql(name = "Office")]
 pub struct OfficeQueryable {
     pub id: i32,
     pub department_id: Option<i32>,
-    pub department_name: Option<String>,
     pub department_type: Option<DepartmentType>,
     pub duties: Option<String>,
     pub from: Option<NaiveDate>,
     pub to: Option<NaiveDate>,
+    pub group_id: Option<i32>,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, DbEnum)]
+#[derive(Insertable, Debug, Queryable)]
+#[diesel(table_name = politician_office)]
+pub struct PoliticianOffice {
+    pub politician_id: i32,
+    pub parliament: i32,
+    pub office_id: i32,
+    pub position: i32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, DbEnum, Serialize, Enum)]
 #[DieselTypePath = "crate::schema::sql_types::PqDepartmentType"]
+#[serde(rename_all = "snake_case")]
 pub enum DepartmentType {
     Office,
     Group,
 }
 
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, DbEnum)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, DbEnum, Serialize, Enum)]
 #[DieselTypePath = "crate::schema::sql_types::PqGender"]
 pub enum Gender {
     M,
     F,
 }
 
+upsert_table!(Politician, politician, (id: i32, parliament: i32), [name, surname, gender, from, to, party, elected_type, biography_link, term_count, email, phone, website]);
+
 
 impl Politician {
-    pub fn save(&self, conn: &mut PgConnection) -> Result<Option<Politician>, diesel::result::Error> {
-        debug!("Saving {:?}", self);
-        let result = diesel::insert_into(politician::table)
-            .values(self)
-            .on_conflict(on_constraint("politician_pkey"))
-            .do_update()
-            .set(self)
-            .get_result::<Politician>(conn).optional();
-
-        match result {
-            Ok(data) => { Ok(data) }
-            Err(error) => {
-                match &error {
-                    diesel::result::Error::DatabaseError(error_type, error_info) => {
-                        match (error_type, error_info) {
-                            (diesel::result::DatabaseErrorKind::UniqueViolation, _) => {
-                                Ok(None)
-                            }
-                            (_, _) => {
-                                error!("{:?}\n{:?}", self, error);
-                                return Err(error);
-                            }
-                        }
-                    }
-                    _ => {
-                        error!("{:?}\n{:?}", self, error);
-                        return Err(error);
-                    }
-                }
-            }
-        }
+    /// `(id, name, surname)` of every member of a parliament.
+    pub fn get_names_per_parliament(conn: &mut PgConnection, parliament_id: i32) -> Result<Vec<(i32, String, String)>, diesel::result::Error> {
+        politician::table
+            .filter(politician::parliament.eq(parliament_id))
+            .select((politician::id, politician::name, politician::surname))
+            .load::<(i32, String, String)>(conn)
+    }
+
+    /// Every term of a member, one per parliament, oldest first.
+    pub fn get_terms(conn: &mut PgConnection, politician_id: i32) -> Result<Vec<Politician>, diesel::result::Error> {
+        politician::table.filter(politician::id.eq(politician_id)).order(politician::parliament).load::<Politician>(conn)
     }
 }
 
 
 impl OfficeInsertable {
-    pub fn save(&self, conn: &mut PgConnection) -> Result<Option<OfficeQueryable>, diesel::result::Error> {
+    /// Inserts a new office. Offices have a generated key and nothing to conflict on, so they are never upserted.
+    pub fn insert(&self, conn: &mut PgConnection) -> Result<OfficeQueryable, diesel::result::Error> {
         debug!("Saving {:?}", self);
-        let result = diesel::insert_into(office::table)
+        diesel::insert_into(office::table)
             .values(self)
-            .on_conflict(office::id)
-            .do_update()
-            .set(self)
-            .get_result::<OfficeQueryable>(conn).optional();
-
-        match result {
-            Ok(data) => { Ok(data) }
-            Err(error) => {
-                match &error {
-                    diesel::result::Error::DatabaseError(error_type, error_info) => {
-                        match (error_type, error_info) {
-                            (diesel::result::DatabaseErrorKind::UniqueViolation, _) => {
-                                Ok(None)
-                            }
-                            (_, _) => {
-                                error!("{:?}\n{:?}", self, error);
-                                return Err(error);
-                            }
-                        }
-                    }
-                    _ => {
-                        error!("{:?}\n{:?}", self, error);
-                        return Err(error);
-                    }
-                }
-            }
-        }
+            .get_result::<OfficeQueryable>(conn)
+            .map_err(|err| {
+                error!("{:?}\n{:?}", self, err);
+                err
+            })
     }
+}
+
 
-    pub fn open_save(&self) -> Result<Option<OfficeQueryable>, diesel::result::Error> {
-        let conn = &mut establish_connection();
-        self.save(conn)
+impl OfficeQueryable {
+    fn matches(&self, office: &OfficeInsertable) -> bool {
+        self.department_id == office.department_id
+            && self.department_type == office.department_type
+            && self.duties == office.duties
+            && self.from == office.from
+            && self.to == office.to
+            && self.group_id == office.group_id
     }
 }
 
+
+impl PoliticianOffice {
+    /// Replaces the offices held by a member during one parliament, keeping them in the given order.
+    ///
+    /// Offices the member already holds are reused when identical and deleted once no longer listed,
+    /// so that crawling a member again leaves no offices nothing links to.
+    pub fn replace(conn: &mut PgConnection, politician_id: i32, parliament: i32, offices: &[&OfficeInsertable]) -> Result<usize, diesel::result::Error> {
+        let mut stale = PoliticianOffice::get_offices(conn, politician_id, parliament)?;
+        let mut office_ids = Vec::with_capacity(offices.len());
+        for office in offices {
+            let office_id = match stale.iter().position(|stored| stored.matches(office)) {
+                Some(index) => stale.remove(index).id,
+                None => office.insert(conn)?.id,
+            };
+            office_ids.push(office_id);
+        }
+
+        diesel::delete(politician_office::table.filter(politician_office::politician_id.eq(politician_id).and(politician_office::parliament.eq(parliament)))).execute(conn)?;
+        let stale_ids: Vec<i32> = stale.iter().map(|office| office.id).collect();
+        diesel::delete(office::table.filter(office::id.eq_any(stale_ids))).execute(conn)?;
+        if office_ids.is_empty() {
+            return Ok(0);
+        }
+        let links: Vec<PoliticianOffice> = office_ids
+            .iter()
+            .enumerate()
+            .map(|(position, office_id)| PoliticianOffice { politician_id, parliament, office_id: *office_id, position: position as i32 })
+            .collect();
+        diesel::insert_into(politician_office::table).values(&links).on_conflict_do_nothing().execute(conn)
+    }
+
+    /// Offices held by a member during one parliament, in the order they are listed.
+    pub fn get_offices(conn: &mut PgConnection, politician_id: i32, parliament: i32) -> Result<Vec<OfficeQueryable>, diesel::result::Error> {
+        politician_office::table
+            .inner_join(office::table)
+            .filter(politician_office::politician_id.eq(politician_id).and(politician_office::parliament.eq(parliament)))
+            .order(politician_office::position)
+            .select(office::all_columns)
+            .load::<OfficeQueryable>(conn)
+    }
+}
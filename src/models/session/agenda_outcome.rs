@@ -0,0 +1,71 @@
+use diesel::prelude::*;
+use diesel_derive_enum::DbEnum;
+use log::debug;
+
+use crate::schema::{agenda_outcome, agenda_outcome_document, agenda_outcome_presenter};
+
+/// How one agenda question was resolved according to the meeting protocol.
+#[derive(Insertable, Debug, Queryable)]
+#[diesel(table_name = agenda_outcome)]
+pub struct AgendaOutcome {
+    pub meeting_id: i32,
+    pub position: i32,
+    pub nr: Option<String>,
+    pub agenda_item_id: Option<i32>,
+    pub document_key: Option<i32>,
+    pub title: String,
+    pub outcome: Option<OutcomeType>,
+    pub decision: Option<String>,
+}
+
+/// Member or official presenting a question, in the order the protocol names them.
+#[derive(Insertable, Debug, Queryable)]
+#[diesel(table_name = agenda_outcome_presenter)]
+pub struct AgendaOutcomePresenter {
+    pub meeting_id: i32,
+    pub outcome_position: i32,
+    pub position: i32,
+    pub name: String,
+}
+
+/// Registration number of a document mentioned with a question, e.g. `XIIP-1234(2)`.
+#[derive(Insertable, Debug, Queryable)]
+#[diesel(table_name = agenda_outcome_document)]
+pub struct AgendaOutcomeDocument {
+    pub meeting_id: i32,
+    pub outcome_position: i32,
+    pub position: i32,
+    pub document_number: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, DbEnum)]
+#[DieselTypePath = "crate::schema::sql_types::PqAgendaOutcome"]
+pub enum OutcomeType {
+    Approved,
+    Rejected,
+    ReturnedForImprovement,
+    Postponed,
+    Other,
+}
+
+
+impl AgendaOutcome {
+    /// Replaces the outcomes stored for a meeting with a freshly parsed protocol,
+    /// their presenters and documents go with them.
+    pub fn replace_for_meeting(
+        conn: &mut PgConnection,
+        meeting_id: i32,
+        outcomes: &[AgendaOutcome],
+        presenters: &[AgendaOutcomePresenter],
+        documents: &[AgendaOutcomeDocument],
+    ) -> Result<usize, diesel::result::Error> {
+        debug!("Saving {} agenda outcomes of meeting {}", outcomes.len(), meeting_id);
+        conn.transaction(|conn| {
+            diesel::delete(agenda_outcome::table.filter(agenda_outcome::meeting_id.eq(meeting_id))).execute(conn)?;
+            diesel::insert_into(agenda_outcome::table).values(outcomes).execute(conn)?;
+            diesel::insert_into(agenda_outcome_presenter::table).values(presenters).execute(conn)?;
+            diesel::insert_into(agenda_outcome_document::table).values(documents).execute(conn)?;
+            Ok(outcomes.len())
+        })
+    }
+}
@@ -1,22 +1,23 @@
+use async_graphql::SimpleObject;
 use diesel::prelude::*;
 use chrono::NaiveDateTime;
-use log::{error, debug};
+use serde::Serialize;
 
-use crate::{schema::{meeting_data, agenda_item, vote, speech, registration}, database::connect::establish_connection};
+use crate::models::upsert::upsert_table;
+use crate::schema::{meeting_data, agenda_item, vote, speech, registration, meeting_agenda_item, meeting_registration, agenda_vote, agenda_speech};
 
-#[derive(Insertable, Debug, Queryable, AsChangeset)]
+#[derive(Insertable, Debug, Clone, PartialEq, Queryable, AsChangeset, Serialize, SimpleObject)]
 #[diesel(table_name = meeting_data)]
+#[graphql(complex)]
 pub struct MeetingData {
     pub id: i32,
     pub from: Option<NaiveDateTime>,
     pub to: Option<NaiveDateTime>,
-
-    pub agenda: Vec<Option<i32>>,
-    pub registrations: Vec<Option<i32>>,
 }
 
-#[derive(Insertable, Debug, Queryable, AsChangeset)]
+#[derive(Insertable, Debug, Clone, PartialEq, Queryable, AsChangeset, Serialize, SimpleObject)]
 #[diesel(table_name = agenda_item)]
+#[graphql(complex)]
 pub struct AgendaItem {
     pub id: i32,
     pub agenda_state_id: Option<i32>,
@@ -28,13 +29,11 @@ pub struct AgendaItem {
     pub agenda_type: Option<String>,
     pub from: Option<NaiveDateTime>,
     pub to: Option<NaiveDateTime>,
-
-    pub speeches: Vec<Option<i32>>,
-    pub voting: Vec<Option<i32>>,
 }
 
-#[derive(Insertable, Debug, Queryable, AsChangeset)]
+#[derive(Insertable, Debug, Clone, PartialEq, Queryable, AsChangeset, Serialize, SimpleObject)]
 #[diesel(table_name = vote)]
+#[graphql(complex)]
 pub struct Vote {
     pub id: i32,
     pub summary: Option<String>,
@@ -43,7 +42,7 @@ pub struct Vote {
     pub to: Option<NaiveDateTime>,
 }
 
-#[derive(Insertable, Debug, Queryable, AsChangeset)]
+#[derive(Insertable, Debug, Clone, PartialEq, Queryable, AsChangeset, Serialize, SimpleObject)]
 #[diesel(table_name = speech)]
 pub struct Speech {
     pub id: i32,
@@ -55,8 +54,9 @@ pub struct Speech {
     pub to: Option<NaiveDateTime>,
 }
 
-#[derive(Insertable, Debug, Queryable, AsChangeset)]
+#[derive(Insertable, Debug, Clone, PartialEq, Queryable, AsChangeset, Serialize, SimpleObject)]
 #[diesel(table_name = registration)]
+#[graphql(complex)]
 pub struct Registration {
     pub id: i32,
     pub result: Option<String>,
@@ -64,218 +64,103 @@ pub struct Registration {
     pub to: Option<NaiveDateTime>,
 }
 
+/// Declares an ordered link table between a parent row and its children.
+macro_rules! link_table {
+    ($name:ident, $table:ident, $parent:ident, $child:ident) => {
+        #[derive(Insertable, Debug, Queryable)]
+        #[diesel(table_name = $table)]
+        pub struct $name {
+            pub $parent: i32,
+            pub $child: i32,
+            pub position: i32,
+        }
 
-impl MeetingData {
-    pub fn save(&self, conn: &mut PgConnection) -> Result<Option<MeetingData>, diesel::result::Error> {
-        debug!("Saving {:?}", self);
-        let result = diesel::insert_into(meeting_data::table)
-            .values(self)
-            .on_conflict(meeting_data::id)
-            .do_update()
-            .set(self)
-            .get_result::<MeetingData>(conn).optional();
-
-        match result {
-            Ok(data) => { Ok(data) }
-            Err(error) => {
-                match &error {
-                    diesel::result::Error::DatabaseError(error_type, error_info) => {
-                        match (error_type, error_info) {
-                            (diesel::result::DatabaseErrorKind::UniqueViolation, _) => {
-                                Ok(None)
-                            }
-                            (_, _) => {
-                                error!("{:?}\n{:?}", self, error);
-                                return Err(error);
-                            }
-                        }
-                    }
-                    _ => {
-                        error!("{:?}\n{:?}", self, error);
-                        return Err(error);
-                    }
+        impl $name {
+            /// Replaces the children of a parent, keeping them in the given order.
+            pub fn replace(conn: &mut PgConnection, parent_id: i32, child_ids: &[i32]) -> Result<usize, diesel::result::Error> {
+                diesel::delete($table::table.filter($table::$parent.eq(parent_id))).execute(conn)?;
+                if child_ids.is_empty() {
+                    return Ok(0);
                 }
+                let links: Vec<$name> = child_ids
+                    .iter()
+                    .enumerate()
+                    .map(|(position, child_id)| $name { $parent: parent_id, $child: *child_id, position: position as i32 })
+                    .collect();
+                diesel::insert_into($table::table).values(&links).on_conflict_do_nothing().execute(conn)
             }
-        }
-    }
 
-    pub fn open_save(&self) -> Result<Option<MeetingData>, diesel::result::Error> {
-        let conn = &mut establish_connection();
-        self.save(conn)
-    }
+            /// Children of the given parents, in order.
+            pub fn get_ids(conn: &mut PgConnection, parent_ids: &[i32]) -> Result<Vec<i32>, diesel::result::Error> {
+                $table::table
+                    .filter($table::$parent.eq_any(parent_ids))
+                    .order(($table::$parent, $table::position))
+                    .select($table::$child)
+                    .load::<i32>(conn)
+            }
+        }
+    };
 }
 
+link_table!(MeetingAgendaItem, meeting_agenda_item, meeting_id, agenda_item_id);
+link_table!(MeetingRegistration, meeting_registration, meeting_id, registration_id);
+link_table!(AgendaVote, agenda_vote, agenda_item_id, vote_id);
+link_table!(AgendaSpeech, agenda_speech, agenda_item_id, speech_id);
 
-impl AgendaItem {
-    pub fn save(&self, conn: &mut PgConnection) -> Result<Option<AgendaItem>, diesel::result::Error> {
-        debug!("Saving {:?}", self);
-        let result = diesel::insert_into(agenda_item::table)
-            .values(self)
-            .on_conflict(agenda_item::id)
-            .do_update()
-            .set(self)
-            .get_result::<AgendaItem>(conn).optional();
+upsert_table!(MeetingData, meeting_data, (id: i32), [from, to]);
+upsert_table!(AgendaItem, agenda_item, (id: i32), [agenda_state_id, agenda_group_id, document_key, nr, name, state, agenda_type, from, to]);
+upsert_table!(Vote, vote, (id: i32), [summary, result, from, to]);
+upsert_table!(Speech, speech, (id: i32), [discussion_id, person_id, person, office, from, to]);
+upsert_table!(Registration, registration, (id: i32), [result, from, to]);
 
-        match result {
-            Ok(data) => { Ok(data) }
-            Err(error) => {
-                match &error {
-                    diesel::result::Error::DatabaseError(error_type, error_info) => {
-                        match (error_type, error_info) {
-                            (diesel::result::DatabaseErrorKind::UniqueViolation, _) => {
-                                Ok(None)
-                            }
-                            (_, _) => {
-                                error!("{:?}\n{:?}", self, error);
-                                return Err(error);
-                            }
-                        }
-                    }
-                    _ => {
-                        error!("{:?}\n{:?}", self, error);
-                        return Err(error);
-                    }
-                }
-            }
-        }
+
+impl MeetingData {
+    pub fn get_agenda_ids(conn: &mut PgConnection, meeting_ids: &[i32]) -> Result<Vec<i32>, diesel::result::Error> {
+        MeetingAgendaItem::get_ids(conn, meeting_ids)
     }
 
-    pub fn open_save(&self) -> Result<Option<AgendaItem>, diesel::result::Error> {
-        let conn = &mut establish_connection();
-        self.save(conn)
+    pub fn get_registration_ids(conn: &mut PgConnection, meeting_ids: &[i32]) -> Result<Vec<i32>, diesel::result::Error> {
+        MeetingRegistration::get_ids(conn, meeting_ids)
     }
 }
 
 
-impl Vote {
-    pub fn save(&self, conn: &mut PgConnection) -> Result<Option<Vote>, diesel::result::Error> {
-        debug!("Saving {:?}", self);
-        let result = diesel::insert_into(vote::table)
-            .values(self)
-            .on_conflict(vote::id)
-            .do_update()
-            .set(self)
-            .get_result::<Vote>(conn).optional();
+impl AgendaItem {
+    pub fn get_vote_ids(conn: &mut PgConnection, agenda_ids: &[i32]) -> Result<Vec<i32>, diesel::result::Error> {
+        AgendaVote::get_ids(conn, agenda_ids)
+    }
 
-        match result {
-            Ok(data) => { Ok(data) }
-            Err(error) => {
-                match &error {
-                    diesel::result::Error::DatabaseError(error_type, error_info) => {
-                        match (error_type, error_info) {
-                            (diesel::result::DatabaseErrorKind::UniqueViolation, _) => {
-                                Ok(None)
-                            }
-                            (_, _) => {
-                                error!("{:?}\n{:?}", self, error);
-                                return Err(error);
-                            }
-                        }
-                    }
-                    _ => {
-                        error!("{:?}\n{:?}", self, error);
-                        return Err(error);
-                    }
-                }
-            }
-        }
+    pub fn get_agenda_items(conn: &mut PgConnection, agenda_ids: &[i32]) -> Result<Vec<AgendaItem>, diesel::result::Error> {
+        agenda_item::table.filter(agenda_item::id.eq_any(agenda_ids)).order(agenda_item::id).load::<AgendaItem>(conn)
     }
 
-    pub fn open_save(&self) -> Result<Option<Vote>, diesel::result::Error> {
-        let conn = &mut establish_connection();
-        self.save(conn)
+    pub fn get_speech_ids(conn: &mut PgConnection, agenda_ids: &[i32]) -> Result<Vec<i32>, diesel::result::Error> {
+        AgendaSpeech::get_ids(conn, agenda_ids)
     }
+}
 
 
-    pub fn get_vote_ids() -> Result<Vec<i32>, diesel::result::Error> {
-        let conn = &mut establish_connection();
+impl Vote {
+    pub fn get_vote_ids(conn: &mut PgConnection) -> Result<Vec<i32>, diesel::result::Error> {
         vote::table.select(vote::id).load::<i32>(conn)
     }
 }
 
 
 impl Speech {
-    pub fn save(&self, conn: &mut PgConnection) -> Result<Option<Speech>, diesel::result::Error> {
-        debug!("Saving {:?}", self);
-        let result = diesel::insert_into(speech::table)
-            .values(self)
-            .on_conflict(speech::id)
-            .do_update()
-            .set(self)
-            .get_result::<Speech>(conn).optional();
-
-        match result {
-            Ok(data) => { Ok(data) }
-            Err(error) => {
-                match &error {
-                    diesel::result::Error::DatabaseError(error_type, error_info) => {
-                        match (error_type, error_info) {
-                            (diesel::result::DatabaseErrorKind::UniqueViolation, _) => {
-                                Ok(None)
-                            }
-                            (_, _) => {
-                                error!("{:?}\n{:?}", self, error);
-                                return Err(error);
-                            }
-                        }
-                    }
-                    _ => {
-                        error!("{:?}\n{:?}", self, error);
-                        return Err(error);
-                    }
-                }
-            }
-        }
-    }
-
-    pub fn open_save(&self) -> Result<Option<Speech>, diesel::result::Error> {
-        let conn = &mut establish_connection();
-        self.save(conn)
+    /// `(id, person_id)` of the given speeches in the order they were held.
+    pub fn get_speakers_in_order(conn: &mut PgConnection, speech_ids: &[i32]) -> Result<Vec<(i32, Option<i32>)>, diesel::result::Error> {
+        speech::table
+            .filter(speech::id.eq_any(speech_ids))
+            .order((speech::from.asc(), speech::id.asc()))
+            .select((speech::id, speech::person_id))
+            .load::<(i32, Option<i32>)>(conn)
     }
 }
 
 
 impl Registration {
-    pub fn save(&self, conn: &mut PgConnection) -> Result<Option<Registration>, diesel::result::Error> {
-        debug!("Saving {:?}", self);
-        let result = diesel::insert_into(registration::table)
-            .values(self)
-            .on_conflict(registration::id)
-            .do_update()
-            .set(self)
-            .get_result::<Registration>(conn).optional();
-
-        match result {
-            Ok(data) => { Ok(data) }
-            Err(error) => {
-                match &error {
-                    diesel::result::Error::DatabaseError(error_type, error_info) => {
-                        match (error_type, error_info) {
-                            (diesel::result::DatabaseErrorKind::UniqueViolation, _) => {
-                                Ok(None)
-                            }
-                            (_, _) => {
-                                error!("{:?}\n{:?}", self, error);
-                                return Err(error);
-                            }
-                        }
-                    }
-                    _ => {
-                        error!("{:?}\n{:?}", self, error);
-                        return Err(error);
-                    }
-                }
-            }
-        }
-    }
-
-    pub fn open_save(&self) -> Result<Option<Registration>, diesel::result::Error> {
-        let conn = &mut establish_connection();
-        self.save(conn)
-    }
-
-    pub fn get_registration_ids() -> Result<Vec<i32>, diesel::result::Error> {
-        let conn = &mut establish_connection();
+    pub fn get_registration_ids(conn: &mut PgConnection) -> Result<Vec<i32>, diesel::result::Error> {
         registration::table.select(registration::id).load::<i32>(conn)
     }
 }
@@ -3,4 +3,6 @@ pub mod parliament;
 pub mod meetings;
 pub mod meeting_data;
 pub mod voting_data;
-pub mod registration_data;
\ No newline at end of file
+pub mod registration_data;
+pub mod speech_text;
+pub mod agenda_outcome;
\ No newline at end of file
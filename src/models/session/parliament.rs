@@ -1,10 +1,13 @@
+use async_graphql::SimpleObject;
 use chrono::NaiveDate;
 use diesel::prelude::*;
-use log::{error, trace};
-use crate::{schema::parliament, database::connect::establish_connection};
+use serde::Serialize;
+use crate::models::upsert::upsert_table;
+use crate::schema::parliament;
 
-#[derive(Insertable, Debug, Queryable)]
+#[derive(Insertable, Debug, Clone, PartialEq, Queryable, Serialize, SimpleObject)]
 #[diesel(table_name = parliament)]
+#[graphql(complex)]
 pub struct Parliament {
     pub id: i32,
     pub name: Option<String>,
@@ -12,47 +15,16 @@ pub struct Parliament {
     pub to: Option<NaiveDate>,
 }
 
+upsert_table!(Parliament, parliament, (id: i32), [name, from, to]);
 
-impl Parliament {
-    pub fn save(&self, conn: &mut PgConnection) -> Result<Option<Parliament>, diesel::result::Error> {
-        trace!("Saving {:?}", self);
-        let result = diesel::insert_into(parliament::table)
-            .values(self)
-            .on_conflict(parliament::id)
-            .do_nothing()
-            .get_result::<Parliament>(conn).optional();
-
-        match result {
-            Ok(data) => { Ok(data) }
-            Err(error) => {
-                match &error {
-                    diesel::result::Error::DatabaseError(error_type, error_info) => {
-                        match (error_type, error_info) {
-                            (diesel::result::DatabaseErrorKind::UniqueViolation, _) => {
-                                Ok(None)
-                            }
-                            (_, _) => {
-                                error!("{:?}\n{:?}", self, error);
-                                return Err(error);
-                            }
-                        }
-                    }
-                    _ => {
-                        error!("{:?}\n{:?}", self, error);
-                        return Err(error);
-                    }
-                }
-            }
-        }
-    }
 
-    pub fn open_save(&self) -> Result<Option<Parliament>, diesel::result::Error> {
-        let conn = &mut establish_connection();
-        self.save(conn)
+impl Parliament {
+    pub fn get_parliaments_ids(conn: &mut PgConnection) -> Result<Vec<i32>, diesel::result::Error> {
+        parliament::table.select(parliament::id).load::<i32>(conn)
     }
 
-    pub fn get_parliaments_ids() -> Result<Vec<i32>, diesel::result::Error> {
-        let conn = &mut establish_connection();
-        parliament::table.select(parliament::id).load::<i32>(conn)
+    /// The most recent parliament, i.e. the one with the highest `kadencijos_id`.
+    pub fn get_current_id(conn: &mut PgConnection) -> Result<Option<i32>, diesel::result::Error> {
+        parliament::table.select(diesel::dsl::max(parliament::id)).first::<Option<i32>>(conn)
     }
 }
@@ -1,9 +1,11 @@
-use diesel::{prelude::*, upsert::on_constraint};
-use log::{debug, error};
+use async_graphql::SimpleObject;
+use diesel::prelude::*;
+use serde::Serialize;
 
-use crate::{schema::registration_data, database::connect::establish_connection};
+use crate::models::upsert::upsert_table;
+use crate::schema::registration_data;
 
-#[derive(Insertable, Debug, Queryable, AsChangeset)]
+#[derive(Insertable, Debug, Clone, PartialEq, Queryable, AsChangeset, Serialize, SimpleObject)]
 #[diesel(table_name = registration_data)]
 pub struct RegistrationData {
     pub id: i32,
@@ -16,51 +18,18 @@ table! {
         rid -> Int4,
     }
 }
+upsert_table!(RegistrationData, registration_data, (id: i32, person_id: i32), [registered]);
 
 impl RegistrationData {
-    pub fn save(&self, conn: &mut PgConnection) -> Result<Option<RegistrationData>, diesel::result::Error> {
-        debug!("Saving {:?}", self);
-        let result = diesel::insert_into(registration_data::table)
-            .values(self)
-            .on_conflict(on_constraint("registration_data_pkey"))
-            .do_update()
-            .set(self)
-            .get_result::<RegistrationData>(conn).optional();
-
-        match result {
-            Ok(data) => { Ok(data) }
-            Err(error) => {
-                match &error {
-                    diesel::result::Error::DatabaseError(error_type, error_info) => {
-                        match (error_type, error_info) {
-                            (diesel::result::DatabaseErrorKind::UniqueViolation, _) => {
-                                Ok(None)
-                            }
-                            (_, _) => {
-                                error!("{:?}\n{:?}", self, error);
-                                return Err(error);
-                            }
-                        }
-                    }
-                    _ => {
-                        error!("{:?}\n{:?}", self, error);
-                        return Err(error);
-                    }
-                }
-            }
-        }
-    }
-
     
-    pub fn get_missing_registration_ids() -> Result<Vec<i32>, diesel::result::Error> {
-        let conn = &mut establish_connection();
+    pub fn get_missing_registration_ids(conn: &mut PgConnection) -> Result<Vec<i32>, diesel::result::Error> {
         missing_registration_ids::table.select(missing_registration_ids::rid).load::<i32>(conn)
     }
 
-
-    pub fn open_save(&self) -> Result<Option<RegistrationData>, diesel::result::Error> {
-        let conn = &mut establish_connection();
-        self.save(conn)
+    /// Whether each member listed in a registration was registered.
+    pub fn get_members(conn: &mut PgConnection, registration_id: i32) -> Result<Vec<RegistrationData>, diesel::result::Error> {
+        registration_data::table.filter(registration_data::id.eq(registration_id)).order(registration_data::person_id).load::<RegistrationData>(conn)
     }
+
 }
 
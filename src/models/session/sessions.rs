@@ -1,12 +1,15 @@
+use async_graphql::SimpleObject;
 use diesel::prelude::*;
 use chrono::{NaiveDate};
-use log::{debug, error};
+use serde::Serialize;
 
-use crate::{schema::sessions, database::connect::establish_connection};
+use crate::models::upsert::upsert_table;
+use crate::schema::sessions;
 
 
-#[derive(Insertable, Debug, Queryable, AsChangeset)]
+#[derive(Insertable, Debug, Clone, PartialEq, Queryable, AsChangeset, Serialize, SimpleObject)]
 #[diesel(table_name = sessions)]
+#[graphql(name = "Session", complex)]
 pub struct Sessions {
     pub id: i32,
     pub num: i32,
@@ -16,49 +19,16 @@ pub struct Sessions {
     pub parliament: i32,
 }
 
+upsert_table!(Sessions, sessions, (id: i32), [num, name, from, to, parliament]);
 
-impl Sessions {
-    pub fn save(&self, conn: &mut PgConnection) -> Result<Option<Sessions>, diesel::result::Error> {
-        debug!("Saving {:?}", self);
-        let result = diesel::insert_into(sessions::table)
-            .values(self)
-            .on_conflict(sessions::id)
-            .do_update()
-            .set(self)
-            .get_result::<Sessions>(conn).optional();
-
-        match result {
-            Ok(data) => { Ok(data) }
-            Err(error) => {
-                match &error {
-                    diesel::result::Error::DatabaseError(error_type, error_info) => {
-                        match (error_type, error_info) {
-                            (diesel::result::DatabaseErrorKind::UniqueViolation, _) => {
-                                Ok(None)
-                            }
-                            (_, _) => {
-                                error!("{:?}\n{:?}", self, error);
-                                return Err(error);
-                            }
-                        }
-                    }
-                    _ => {
-                        error!("{:?}\n{:?}", self, error);
-                        return Err(error);
-                    }
-                }
-            }
-        }
-    }
 
-    pub fn open_save(&self) -> Result<Option<Sessions>, diesel::result::Error> {
-        let conn = &mut establish_connection();
-        self.save(conn)
+impl Sessions {
+    pub fn get_sessions_per_parliament(conn: &mut PgConnection, parliament_id: i32) -> Result<Vec<i32>, diesel::result::Error> {
+        sessions::table.filter(sessions::parliament.eq(parliament_id)).select(sessions::id).load::<i32>(conn)
     }
 
-    pub fn get_sessions_per_parliament(parliament_id: i32) -> Result<Vec<i32>, diesel::result::Error> {
-        let conn = &mut establish_connection();
-        sessions::table.filter(sessions::parliament.eq(parliament_id)).select(sessions::id).load::<i32>(conn)
+    pub fn get_parliament_id(conn: &mut PgConnection, session_id: i32) -> Result<i32, diesel::result::Error> {
+        sessions::table.find(session_id).select(sessions::parliament).first::<i32>(conn)
     }
 }
 
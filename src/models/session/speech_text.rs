@@ -0,0 +1,36 @@
+use diesel::prelude::*;
+use log::debug;
+
+use crate::schema::speech_text;
+
+/// One speaker turn of a meeting stenogram.
+#[derive(Insertable, Debug, Queryable)]
+#[diesel(table_name = speech_text)]
+pub struct SpeechText {
+    pub meeting_id: i32,
+    pub position: i32,
+    pub speaker: String,
+    pub faction: Option<String>,
+    pub chair: bool,
+    pub person_id: Option<i32>,
+    pub speech_id: Option<i32>,
+    pub text: String,
+    /// Parliament of the meeting, which `person_id` is a member of.
+    pub parliament: i32,
+}
+
+
+impl SpeechText {
+    /// Replaces the turns stored for a meeting, as re-segmenting a stenogram can change their number.
+    pub fn replace_for_meeting(conn: &mut PgConnection, meeting_id: i32, turns: &[SpeechText]) -> Result<usize, diesel::result::Error> {
+        debug!("Saving {} speech turns of meeting {}", turns.len(), meeting_id);
+        conn.transaction(|conn| {
+            diesel::delete(speech_text::table.filter(speech_text::meeting_id.eq(meeting_id))).execute(conn)?;
+            // Postgres caps a statement at 65535 bind parameters.
+            for chunk in turns.chunks(4096) {
+                diesel::insert_into(speech_text::table).values(chunk).execute(conn)?;
+            }
+            Ok(turns.len())
+        })
+    }
+}
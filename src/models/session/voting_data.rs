@@ -1,11 +1,14 @@
-use diesel::{prelude::*, upsert::on_constraint};
+use async_graphql::{Enum, SimpleObject};
+use diesel::prelude::*;
 use diesel_derive_enum::DbEnum;
-use log::{error, debug};
+use serde::Serialize;
 
-use crate::{schema::{vote_data}, database::connect::establish_connection};
+use crate::models::upsert::upsert_table;
+use crate::schema::vote_data;
 
-#[derive(Insertable, Debug, Queryable, AsChangeset)]
+#[derive(Insertable, Debug, Clone, PartialEq, Queryable, AsChangeset, Serialize, SimpleObject)]
 #[diesel(table_name = vote_data)]
+#[graphql(complex)]
 pub struct VoteData {
     pub id: i32,
     pub person_id: i32,
@@ -13,8 +16,9 @@ pub struct VoteData {
 }
 
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, DbEnum)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, DbEnum, Serialize, Enum)]
 #[DieselTypePath = "crate::schema::sql_types::PqVoteType"]
+#[serde(rename_all = "snake_case")]
 pub enum VoteType {
     For,
     Against,
@@ -27,48 +31,31 @@ table! {
     }
 }
 
-impl VoteData {
-    pub fn save(&self, conn: &mut PgConnection) -> Result<Option<VoteData>, diesel::result::Error> {
-        debug!("Saving {:?}", self);
-        let result = diesel::insert_into(vote_data::table)
-            .values(self)
-            .on_conflict(on_constraint("vote_data_pkey"))
-            .do_update()
-            .set(self)
-            .get_result::<VoteData>(conn).optional();
-
-        match result {
-            Ok(session) => { Ok(session) }
-            Err(error) => {
-                match &error {
-                    diesel::result::Error::DatabaseError(error_type, error_info) => {
-                        match (error_type, error_info) {
-                            (diesel::result::DatabaseErrorKind::UniqueViolation, _) => {
-                                Ok(None)
-                            }
-                            (_, _) => {
-                                error!("{:?}\n{:?}", self, error);
-                                return Err(error);
-                            }
-                        }
-                    }
-                    _ => {
-                        error!("{:?}\n{:?}", self, error);
-                        return Err(error);
-                    }
-                }
-            }
-        }
+table! {
+    vote_scope (vote_id) {
+        vote_id -> Int4,
+        session_id -> Int4,
+        parliament_id -> Int4,
     }
+}
+upsert_table!(VoteData, vote_data, (id: i32, person_id: i32), [vote]);
 
-    pub fn get_missing_vote_ids() -> Result<Vec<i32>, diesel::result::Error> {
-        let conn = &mut establish_connection();
+impl VoteData {
+    pub fn get_missing_vote_ids(conn: &mut PgConnection) -> Result<Vec<i32>, diesel::result::Error> {
         missing_vote_ids::table.select(missing_vote_ids::vid).load::<i32>(conn)
     }
 
-    pub fn open_save(&self) -> Result<Option<VoteData>, diesel::result::Error> {
-        let conn = &mut establish_connection();
-        self.save(conn)
+    /// `(vote_id, parliament_id)` of the given votes, as held in the meetings of the parliament.
+    pub fn get_parliament_ids(conn: &mut PgConnection, vote_ids: &[i32]) -> Result<Vec<(i32, i32)>, diesel::result::Error> {
+        vote_scope::table
+            .filter(vote_scope::vote_id.eq_any(vote_ids))
+            .select((vote_scope::vote_id, vote_scope::parliament_id))
+            .load::<(i32, i32)>(conn)
+    }
+
+    /// Ballot of every member listed in the results of a vote.
+    pub fn get_ballots(conn: &mut PgConnection, vote_id: i32) -> Result<Vec<VoteData>, diesel::result::Error> {
+        vote_data::table.filter(vote_data::id.eq(vote_id)).order(vote_data::person_id).load::<VoteData>(conn)
     }
 }
 
@@ -0,0 +1,148 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt::Debug;
+use std::hash::Hash;
+
+use diesel::{Connection, PgConnection};
+use log::{debug, error};
+
+/// Rows are written in chunks to stay well below the 65535 bind parameters Postgres accepts per statement.
+pub(crate) const BATCH_SIZE: usize = 1000;
+
+/// What an upsert did to a row.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UpsertOutcome {
+    Inserted,
+    Updated,
+    Unchanged,
+}
+
+/// Counts of a batch upsert.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct UpsertSummary {
+    pub inserted: usize,
+    pub updated: usize,
+    pub unchanged: usize,
+}
+
+impl UpsertSummary {
+    /// Rows that were inserted or updated.
+    pub fn written(&self) -> usize {
+        self.inserted + self.updated
+    }
+
+    pub fn total(&self) -> usize {
+        self.inserted + self.updated + self.unchanged
+    }
+
+    fn outcome(&self) -> UpsertOutcome {
+        if self.inserted > 0 {
+            UpsertOutcome::Inserted
+        } else if self.updated > 0 {
+            UpsertOutcome::Updated
+        } else {
+            UpsertOutcome::Unchanged
+        }
+    }
+}
+
+impl std::ops::AddAssign for UpsertSummary {
+    fn add_assign(&mut self, other: UpsertSummary) {
+        self.inserted += other.inserted;
+        self.updated += other.updated;
+        self.unchanged += other.unchanged;
+    }
+}
+
+/// Insert-or-update keyed on the primary key of a table, implemented with `upsert_table!`.
+///
+/// Rows are compared with what is stored before writing, so that unchanged rows are
+/// neither rewritten nor reported as updated. A conflicting row is overwritten as a
+/// whole, `None` fields included.
+pub trait Upsert: Sized + PartialEq + Debug {
+    /// Values of the conflict target.
+    type Key: Eq + Hash + Clone;
+
+    fn key(&self) -> Self::Key;
+
+    /// Stored rows with the given keys.
+    fn find(conn: &mut PgConnection, keys: &[Self::Key]) -> Result<Vec<Self>, diesel::result::Error>;
+
+    /// Inserts the rows, overwriting the ones that conflict.
+    fn write(conn: &mut PgConnection, rows: &[&Self]) -> Result<usize, diesel::result::Error>;
+
+    fn upsert(&self, conn: &mut PgConnection) -> Result<UpsertOutcome, diesel::result::Error> {
+        let summary = Self::upsert_batch(conn, std::slice::from_ref(self))?;
+        Ok(summary.outcome())
+    }
+
+    /// Upserts all rows in one transaction. When a key occurs more than once, the last row wins.
+    fn upsert_batch(conn: &mut PgConnection, rows: &[Self]) -> Result<UpsertSummary, diesel::result::Error> {
+        // Postgres refuses to touch the same row twice in one statement.
+        let mut seen = HashSet::new();
+        let mut unique: Vec<&Self> = rows.iter().rev().filter(|row| seen.insert(row.key())).collect();
+        unique.reverse();
+
+        conn.transaction(|conn| {
+            let mut summary = UpsertSummary::default();
+            for chunk in unique.chunks(BATCH_SIZE) {
+                let keys: Vec<Self::Key> = chunk.iter().map(|row| row.key()).collect();
+                let stored: HashMap<Self::Key, Self> = Self::find(conn, &keys)?.into_iter().map(|row| (row.key(), row)).collect();
+
+                let mut changed: Vec<&Self> = Vec::new();
+                for row in chunk {
+                    match stored.get(&row.key()) {
+                        None => summary.inserted += 1,
+                        Some(current) if current != *row => summary.updated += 1,
+                        Some(_) => {
+                            summary.unchanged += 1;
+                            continue;
+                        }
+                    }
+                    changed.push(row);
+                }
+                if changed.is_empty() {
+                    continue;
+                }
+                debug!("Saving {} row(s), first {:?}", changed.len(), changed[0]);
+                Self::write(conn, &changed).map_err(|err| {
+                    error!("{:?}\n{:?}", changed, err);
+                    err
+                })?;
+            }
+            Ok(summary)
+        })
+    }
+}
+
+/// Implements `Upsert` for a model whose fields are all columns of its table, in order.
+///
+/// The key columns are the conflict target, the remaining columns are overwritten on conflict.
+macro_rules! upsert_table {
+    ($model:ty, $table:ident, ($first:ident: $first_type:ty $(, $key:ident: $key_type:ty)*), [$($column:ident),+ $(,)?]) => {
+        impl $crate::models::upsert::Upsert for $model {
+            type Key = ($first_type, $($key_type,)*);
+
+            fn key(&self) -> Self::Key {
+                (self.$first.clone(), $(self.$key.clone(),)*)
+            }
+
+            fn find(conn: &mut PgConnection, keys: &[Self::Key]) -> Result<Vec<Self>, diesel::result::Error> {
+                let first: Vec<$first_type> = keys.iter().map(|key| key.0.clone()).collect();
+                let keys: std::collections::HashSet<&Self::Key> = keys.iter().collect();
+                let rows = $table::table.filter($table::$first.eq_any(first)).load::<$model>(conn)?;
+                Ok(rows.into_iter().filter(|row| keys.contains(&row.key())).collect())
+            }
+
+            fn write(conn: &mut PgConnection, rows: &[&Self]) -> Result<usize, diesel::result::Error> {
+                diesel::insert_into($table::table)
+                    .values(rows.iter().map(|row| *row).collect::<Vec<&$model>>())
+                    .on_conflict(($table::$first, $($table::$key,)*))
+                    .do_update()
+                    .set(($($table::$column.eq(diesel::upsert::excluded($table::$column)),)+))
+                    .execute(conn)
+            }
+        }
+    };
+}
+
+pub(crate) use upsert_table;
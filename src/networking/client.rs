@@ -0,0 +1,215 @@
+use std::future::Future;
+use std::sync::OnceLock;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use log::{debug, warn};
+use rand::Rng;
+use reqwest::{header::{HeaderValue, RETRY_AFTER}, Response, StatusCode, Url};
+use tokio::sync::Mutex;
+use tokio::time::{sleep_until, Instant};
+
+use crate::error::{Error, Result};
+
+#[derive(Debug, Clone)]
+pub struct ClientConfig {
+    pub timeout: Duration,
+    pub connect_timeout: Duration,
+    pub max_retries: u32,
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+    pub requests_per_second: f64,
+}
+
+impl Default for ClientConfig {
+    fn default() -> Self {
+        ClientConfig {
+            timeout: Duration::from_secs(60),
+            connect_timeout: Duration::from_secs(10),
+            max_retries: 5,
+            base_delay: Duration::from_millis(500),
+            max_delay: Duration::from_secs(60),
+            requests_per_second: 8.0,
+        }
+    }
+}
+
+
+static CLIENT: OnceLock<HttpClient> = OnceLock::new();
+
+/// Configures the shared client. Only the first call has any effect.
+pub fn init(config: ClientConfig) {
+    if CLIENT.set(HttpClient::new(config)).is_err() {
+        debug!("HTTP client already initialised");
+    }
+}
+
+pub fn client() -> &'static HttpClient {
+    CLIENT.get_or_init(|| HttpClient::new(ClientConfig::default()))
+}
+
+
+/// Connection-reusing HTTP client shared by every request to the Seimas servers.
+///
+/// Requests are spaced out to stay within `requests_per_second`, and transient failures
+/// (timeouts, connection errors, 408, 429 and 5xx responses) are retried with exponential
+/// backoff and jitter, honouring `Retry-After` when the server sends one.
+#[derive(Debug)]
+pub struct HttpClient {
+    client: reqwest::Client,
+    config: ClientConfig,
+    next_slot: Mutex<Instant>,
+}
+
+impl HttpClient {
+    pub fn new(config: ClientConfig) -> HttpClient {
+        let client = reqwest::Client::builder()
+            .timeout(config.timeout)
+            .connect_timeout(config.connect_timeout)
+            .pool_idle_timeout(Duration::from_secs(90))
+            .build()
+            .expect("Failed to build HTTP client");
+
+        HttpClient {
+            client,
+            config,
+            next_slot: Mutex::new(Instant::now()),
+        }
+    }
+
+    pub async fn get_text(&self, link: &str) -> Result<String> {
+        self.get(link, Response::text).await
+    }
+
+    pub async fn get_bytes(&self, link: &str) -> Result<Vec<u8>> {
+        let bytes = self.get(link, Response::bytes).await?;
+        Ok(bytes.to_vec())
+    }
+
+    /// Sends the request and reads the body with `read`, retrying when either fails, since a large
+    /// document is as likely to time out or be cut off while its body is read as before.
+    async fn get<T, F, Fut>(&self, link: &str, read: F) -> Result<T>
+    where
+        F: Fn(Response) -> Fut,
+        Fut: Future<Output = reqwest::Result<T>>,
+    {
+        let url = Url::parse(link).map_err(|error| Error::InvalidUrl { url: link.to_owned(), reason: error.to_string() })?;
+        let mut attempt = 0;
+
+        loop {
+            self.wait_for_slot().await;
+            let retry_in = match self.client.get(url.clone()).send().await {
+                Ok(response) if response.status().is_success() => match read(response).await {
+                    Ok(body) => return Ok(body),
+                    Err(error) if is_transient(&error) && attempt < self.config.max_retries => {
+                        warn!("reading {} failed: {}, retrying ({}/{})", link, error, attempt + 1, self.config.max_retries);
+                        self.backoff(attempt)
+                    }
+                    Err(error) => return Err(Error::Network { url: link.to_owned(), source: error }),
+                },
+                Ok(response) if is_retryable(response.status()) && attempt < self.config.max_retries => {
+                    warn!("{} returned {}, retrying ({}/{})", link, response.status(), attempt + 1, self.config.max_retries);
+                    response.headers().get(RETRY_AFTER).and_then(retry_after)
+                        .map(|delay| delay.min(self.config.max_delay))
+                        .unwrap_or_else(|| self.backoff(attempt))
+                }
+                Ok(response) => return Err(Error::Http { url: link.to_owned(), status: response.status().as_u16() }),
+                Err(error) if is_transient(&error) && attempt < self.config.max_retries => {
+                    warn!("{} failed: {}, retrying ({}/{})", link, error, attempt + 1, self.config.max_retries);
+                    self.backoff(attempt)
+                }
+                Err(error) => return Err(Error::Network { url: link.to_owned(), source: error }),
+            };
+
+            tokio::time::sleep(retry_in).await;
+            attempt += 1;
+        }
+    }
+
+    async fn wait_for_slot(&self) {
+        let interval = match Duration::try_from_secs_f64(1.0 / self.config.requests_per_second) {
+            Ok(interval) if self.config.requests_per_second > 0.0 => interval,
+            _ => return,
+        };
+        let slot = {
+            let mut next_slot = self.next_slot.lock().await;
+            let slot = (*next_slot).max(Instant::now());
+            *next_slot = slot + interval;
+            slot
+        };
+        sleep_until(slot).await;
+    }
+
+    fn backoff(&self, attempt: u32) -> Duration {
+        let exponential = self.config.base_delay.saturating_mul(2u32.saturating_pow(attempt));
+        let capped = exponential.min(self.config.max_delay);
+        let half = capped / 2;
+        half + half.mul_f64(rand::thread_rng().gen::<f64>())
+    }
+}
+
+fn is_transient(error: &reqwest::Error) -> bool {
+    error.is_timeout() || error.is_connect() || error.is_request() || error.is_body()
+}
+
+fn is_retryable(status: StatusCode) -> bool {
+    status == StatusCode::REQUEST_TIMEOUT || status == StatusCode::TOO_MANY_REQUESTS || status.is_server_error()
+}
+
+/// Delay asked for by a `Retry-After` header, in seconds or as a date. A date that has passed allows retrying at once.
+fn retry_after(value: &HeaderValue) -> Option<Duration> {
+    let value = value.to_str().ok()?.trim();
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+    let date = DateTime::parse_from_rfc2822(value).ok()?;
+    Some((date.with_timezone(&Utc) - Utc::now()).to_std().unwrap_or(Duration::ZERO))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_retry_after_in_seconds() {
+        assert_eq!(retry_after(&HeaderValue::from_static("120")), Some(Duration::from_secs(120)));
+        assert_eq!(retry_after(&HeaderValue::from_static(" 0 ")), Some(Duration::ZERO));
+        assert_eq!(retry_after(&HeaderValue::from_static("soon")), None);
+    }
+
+    #[test]
+    fn reads_retry_after_as_a_date() {
+        let date = (Utc::now() + chrono::Duration::seconds(30)).to_rfc2822();
+        let delay = retry_after(&HeaderValue::from_str(&date).unwrap()).unwrap();
+        assert!(delay > Duration::from_secs(25) && delay <= Duration::from_secs(30), "{:?}", delay);
+
+        assert_eq!(retry_after(&HeaderValue::from_static("Wed, 21 Oct 2015 07:28:00 GMT")), Some(Duration::ZERO));
+    }
+
+    #[test]
+    fn retries_timeouts_throttling_and_server_errors() {
+        assert!(is_retryable(StatusCode::REQUEST_TIMEOUT));
+        assert!(is_retryable(StatusCode::TOO_MANY_REQUESTS));
+        assert!(is_retryable(StatusCode::BAD_GATEWAY));
+        assert!(!is_retryable(StatusCode::NOT_FOUND));
+        assert!(!is_retryable(StatusCode::FORBIDDEN));
+    }
+
+    #[test]
+    fn backs_off_within_half_the_cap_and_the_cap() {
+        let config = ClientConfig { base_delay: Duration::from_millis(500), max_delay: Duration::from_secs(60), ..ClientConfig::default() };
+        let client = HttpClient::new(config);
+        for attempt in 0..4 {
+            let cap = Duration::from_millis(500 * 2u64.pow(attempt));
+            for _ in 0..50 {
+                let delay = client.backoff(attempt);
+                assert!(delay >= cap / 2 && delay <= cap, "attempt {}: {:?}", attempt, delay);
+            }
+        }
+        for attempt in [7, 20, 40, u32::MAX] {
+            let delay = client.backoff(attempt);
+            assert!(delay >= Duration::from_secs(30) && delay <= Duration::from_secs(60), "attempt {}: {:?}", attempt, delay);
+        }
+    }
+}
@@ -1,81 +1,88 @@
-use std::path::Path;
-use std::{error::Error, fs::File};
+use std::path::{Path, PathBuf};
+use std::fs::File;
 
-use std::io::Write;
-use log::{info, error, debug};
+use std::io::{Cursor, Write};
+use log::{info, debug};
 use reqwest::Url;
 
-use async_process::Command;
+use crate::error::{Error, Result};
+use crate::networking::client::client;
+use crate::parser::document::odt::read_odt;
 
-async fn get_document(doc_id: &str, filename: &str) -> Result<bool, Box<dyn Error + Send + Sync>> {
+async fn get_document(doc_id: &str, filename: &str) -> Result<()> {
     let filepath = format!("documents/{}.txt", filename);
     if Path::new(&filepath).exists() {
         debug!("File exists: {}", filepath);
-        return Ok(false)
+        return Ok(())
     }
-    let filepath = format!("documents/{}.odt", filename);
-    info!("Downloading file: {}...", filepath);
+    info!("Downloading file: {}...", filename);
 
     let link = format!("https://e-seimas.lrs.lt/rs/legalact/TAK/{}/format/OO3_ODT/", doc_id);
 
-    let url = Url::parse(&link)?;
-    let response = reqwest::get(url).await?;
-    
-
+    let bytes = client().get_bytes(&link).await?;
 
     let docpath = Path::new("documents");
     if !docpath.exists() {
         std::fs::create_dir_all(docpath)?;
     }
-    let mut file = File::create(filepath.clone())?;
-
-    
-    let bytes = response.bytes().await?;
-
-    file.write_all(&bytes)
-            .or(Err(format!("Error while writing to file")))?;
-    
-    match convert_odt_to_txt(&filepath).await {
-        Ok(_) => {}
-        Err(error) => {
-            error!("Error converting odt to txt: {:?}", error);
-        }
-    }
+
+    let name = filename.to_owned();
+    tokio::task::spawn_blocking(move || convert_odt(&bytes, &name)).await??;
     debug!("Downloaded file: {}", filepath);
-    Ok(true)
+    Ok(())
 }
 
-pub async fn get_protocol_document(link: String, session_id: i32, meeting_num: i32) -> Result<(), Box<dyn Error + Send + Sync>> {
-    let url = Url::parse(&link)?;
-    let doc_id = url
-            .path_segments()
-            .and_then(|segments| segments.last())
-            .and_then(|name| if name.is_empty() { None } else { Some(name) })
-            .unwrap();
-    let filename = format!("protocol_{}_{}_{}", session_id, meeting_num, doc_id);
-    get_document(&doc_id, &filename).await?;
+/// Writes the text of an ODT document to `documents/<filename>.txt` and its paragraphs to `documents/<filename>.json`.
+fn convert_odt(bytes: &[u8], filename: &str) -> Result<()> {
+    let document = read_odt(Cursor::new(bytes), filename)?;
+
+    let mut file = File::create(format!("documents/{}.json", filename))?;
+    serde_json::to_writer(&mut file, &document)?;
+    file.write_all(b"\n")?;
+
+    // The text file is written last, as its presence marks the document as downloaded.
+    let mut file = File::create(format!("documents/{}.txt", filename))?;
+    file.write_all(document.text().as_bytes())?;
     Ok(())
 }
 
-pub async fn get_stenogram_document(link: String, session_id: i32, meeting_num: i32)-> Result<(), Box<dyn Error + Send + Sync>> {
-    let url = Url::parse(&link)?;
-    let doc_id = url
-            .path_segments()
-            .and_then(|segments| segments.last())
-            .and_then(|name| if name.is_empty() { None } else { Some(name) })
-            .unwrap();
-    let filename = format!("stenogram_{}_{}_{}", session_id, meeting_num, doc_id);
-    if get_document(&doc_id, &filename).await? {
-        let fullname = format!("documents/{}.txt", filename);
-        debug!("Fixing stenogram file {}...", fullname);
-        Command::new("sed").arg("-i").arg("s/­//g").arg(fullname).output().await?;
-    }
-    Ok(())
+fn document_id(link: &str) -> Result<String> {
+    let invalid = |reason: &str| Error::InvalidUrl { url: link.to_owned(), reason: reason.to_owned() };
+    let url = Url::parse(link).map_err(|error| invalid(&error.to_string()))?;
+    url
+        .path_segments()
+        .and_then(|mut segments| segments.next_back())
+        .and_then(|name| if name.is_empty() { None } else { Some(name.to_owned()) })
+        .ok_or_else(|| invalid("no document ID in path"))
+}
+
+pub async fn get_protocol_document(link: String, session_id: i32, meeting_num: i32) -> Result<()> {
+    let doc_id = document_id(&link)?;
+    let filename = document_filename("protocol", &doc_id, session_id, meeting_num);
+    get_document(&doc_id, &filename).await
 }
 
+pub async fn get_stenogram_document(link: String, session_id: i32, meeting_num: i32) -> Result<()> {
+    let doc_id = document_id(&link)?;
+    let filename = document_filename("stenogram", &doc_id, session_id, meeting_num);
+    get_document(&doc_id, &filename).await
+}
 
-pub async fn convert_odt_to_txt(filename: &str) -> Result<(), Box<dyn Error>> {
-    Command::new("libreoffice").arg("--convert-to").arg("txt").arg(filename).arg("--outdir").arg("documents").output().await?;
-    Command::new("rm").arg(filename).output().await?;
-    Ok(())
+fn document_filename(kind: &str, doc_id: &str, session_id: i32, meeting_num: i32) -> String {
+    format!("{}_{}_{}_{}", kind, session_id, meeting_num, doc_id)
+}
+
+fn document_text_path(kind: &str, link: &str, session_id: i32, meeting_num: i32) -> Result<PathBuf> {
+    let doc_id = document_id(link)?;
+    Ok(Path::new("documents").join(format!("{}.txt", document_filename(kind, &doc_id, session_id, meeting_num))))
+}
+
+/// Where `get_protocol_document` leaves the text of a protocol.
+pub fn protocol_text_path(link: &str, session_id: i32, meeting_num: i32) -> Result<PathBuf> {
+    document_text_path("protocol", link, session_id, meeting_num)
+}
+
+/// Where `get_stenogram_document` leaves the text of a stenogram.
+pub fn stenogram_text_path(link: &str, session_id: i32, meeting_num: i32) -> Result<PathBuf> {
+    document_text_path("stenogram", link, session_id, meeting_num)
 }
@@ -0,0 +1,121 @@
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::sync::OnceLock;
+
+use log::{debug, trace};
+use sha2::{Digest, Sha256};
+
+/// How `url_request` obtains responses.
+#[derive(Debug)]
+pub enum RequestMode {
+    /// Always hit the network.
+    Live,
+    /// Hit the network and save every response to the store.
+    Record(FixtureStore),
+    /// Never hit the network, serve responses from the store.
+    Replay(FixtureStore),
+}
+
+static MODE: OnceLock<RequestMode> = OnceLock::new();
+
+/// Sets the request mode for the whole process. Only the first call has any effect.
+pub fn set_mode(mode: RequestMode) {
+    if MODE.set(mode).is_err() {
+        debug!("Request mode already set");
+    }
+}
+
+pub fn mode() -> &'static RequestMode {
+    MODE.get_or_init(|| RequestMode::Live)
+}
+
+
+/// Content-addressed on-disk store of raw responses, keyed by the SHA-256 of the request URL.
+///
+/// Each response lives in `<root>/<first two hex chars>/<hash>.xml`, next to a `<hash>.url`
+/// file holding the original URL.
+#[derive(Debug, Clone)]
+pub struct FixtureStore {
+    root: PathBuf,
+}
+
+impl FixtureStore {
+    pub fn new(root: impl Into<PathBuf>) -> FixtureStore {
+        FixtureStore { root: root.into() }
+    }
+
+    pub fn key(url: &str) -> String {
+        format!("{:x}", Sha256::digest(url.as_bytes()))
+    }
+
+    fn path(&self, url: &str, extension: &str) -> PathBuf {
+        let key = FixtureStore::key(url);
+        self.root.join(&key[..2]).join(format!("{}.{}", key, extension))
+    }
+
+    pub fn load(&self, url: &str) -> io::Result<Option<String>> {
+        let path = self.path(url, "xml");
+        trace!("Loading fixture {} for {}", path.display(), url);
+        match fs::read_to_string(&path) {
+            Ok(body) => Ok(Some(body)),
+            Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(error) => Err(error),
+        }
+    }
+
+    pub fn save(&self, url: &str, body: &str) -> io::Result<()> {
+        let path = self.path(url, "xml");
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        debug!("Saving fixture {} for {}", path.display(), url);
+
+        // The URL goes first, so that a response in place can always be traced back to its request.
+        fs::write(self.path(url, "url"), url)?;
+        let tmp_path = path.with_extension("xml.tmp");
+        fs::write(&tmp_path, body)?;
+        fs::rename(&tmp_path, &path)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const URL: &str = "https://apps.lrs.lt/sip/p2b.ad_seimo_kadencijos";
+
+    #[test]
+    fn loads_what_was_saved() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = FixtureStore::new(dir.path());
+        store.save(URL, "<SeimoInformacija/>").unwrap();
+        assert_eq!(store.load(URL).unwrap().as_deref(), Some("<SeimoInformacija/>"));
+
+        store.save(URL, "<SeimoInformacija></SeimoInformacija>").unwrap();
+        assert_eq!(store.load(URL).unwrap().as_deref(), Some("<SeimoInformacija></SeimoInformacija>"));
+    }
+
+    #[test]
+    fn shards_by_the_first_two_characters_of_the_key() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = FixtureStore::new(dir.path());
+        store.save(URL, "<SeimoInformacija/>").unwrap();
+
+        let key = FixtureStore::key(URL);
+        assert_eq!(key.len(), 64);
+        let shard = dir.path().join(&key[..2]);
+        assert_eq!(fs::read_to_string(shard.join(format!("{}.xml", key))).unwrap(), "<SeimoInformacija/>");
+        assert_eq!(fs::read_to_string(shard.join(format!("{}.url", key))).unwrap(), URL);
+        assert!(!shard.join(format!("{}.xml.tmp", key)).exists());
+    }
+
+    #[test]
+    fn loads_nothing_for_an_unrecorded_url() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = FixtureStore::new(dir.path());
+        store.save(URL, "<SeimoInformacija/>").unwrap();
+        assert_eq!(store.load("https://apps.lrs.lt/sip/p2b.ad_seimo_sesijos?kadencijos_id=10").unwrap(), None);
+    }
+}
@@ -1,2 +1,4 @@
 pub mod request;
-pub mod download;
\ No newline at end of file
+pub mod download;
+pub mod fixture;
+pub mod client;
\ No newline at end of file
@@ -1,9 +1,46 @@
-use reqwest::Url;
-use std::error::Error;
+use crate::error::{Error, Result};
+use crate::networking::client::client;
+use crate::networking::fixture::{self, RequestMode};
 
-pub async fn url_request(link: &str) -> Result<String, Box<dyn Error + Send + Sync>> {
+pub async fn url_request(link: &str) -> Result<String> {
+    request(fixture::mode(), link).await
+}
 
-    let url = Url::parse(link)?;
-    let response = reqwest::get(url).await?;
-    return Ok(response.text().await?);
-}
\ No newline at end of file
+async fn request(mode: &RequestMode, link: &str) -> Result<String> {
+    match mode {
+        RequestMode::Live => fetch(link).await,
+        RequestMode::Record(store) => {
+            let body = fetch(link).await?;
+            store.save(link, &body)?;
+            Ok(body)
+        }
+        RequestMode::Replay(store) => {
+            store.load(link)?.ok_or_else(|| Error::MissingFixture(link.to_owned()))
+        }
+    }
+}
+
+async fn fetch(link: &str) -> Result<String> {
+    client().get_text(link).await
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::networking::fixture::FixtureStore;
+
+    #[tokio::test]
+    async fn replays_recorded_responses_only() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = FixtureStore::new(dir.path());
+        store.save("https://apps.lrs.lt/sip/p2b.ad_seimo_kadencijos", "<SeimoInformacija/>").unwrap();
+        let mode = RequestMode::Replay(store);
+
+        assert_eq!(request(&mode, "https://apps.lrs.lt/sip/p2b.ad_seimo_kadencijos").await.unwrap(), "<SeimoInformacija/>");
+        match request(&mode, "https://apps.lrs.lt/sip/p2b.ad_seimo_sesijos?kadencijos_id=10").await {
+            Err(Error::MissingFixture(url)) => assert_eq!(url, "https://apps.lrs.lt/sip/p2b.ad_seimo_sesijos?kadencijos_id=10"),
+            other => panic!("expected a missing fixture, got {:?}", other),
+        }
+    }
+}
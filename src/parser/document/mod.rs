@@ -0,0 +1,3 @@
+pub mod odt;
+pub mod protocol;
+pub mod stenogram;
\ No newline at end of file
@@ -0,0 +1,415 @@
+use std::collections::HashMap;
+use std::io::{Read, Seek};
+
+use serde::Serialize;
+use xml::reader::{EventReader, ParserConfig, XmlEvent};
+use zip::ZipArchive;
+
+use crate::error::{Error, Result};
+use crate::parser::util::parse_attributes;
+
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BlockKind {
+    Heading,
+    Paragraph,
+    ListItem,
+    TableCell,
+}
+
+/// A run of text sharing the same character formatting.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Span {
+    pub text: String,
+    /// Named character style, direct formatting is only reflected in `bold` and `italic`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub style: Option<String>,
+    pub bold: bool,
+    pub italic: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Paragraph {
+    pub kind: BlockKind,
+    /// Outline level of headings.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub level: Option<u8>,
+    /// Named paragraph style, automatic styles are resolved to the style they derive from.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub style: Option<String>,
+    pub spans: Vec<Span>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct OdtDocument {
+    pub paragraphs: Vec<Paragraph>,
+}
+
+#[derive(Debug, Default)]
+struct StyleInfo {
+    parent: Option<String>,
+    automatic: bool,
+    bold: Option<bool>,
+    italic: Option<bool>,
+}
+
+#[derive(Debug, Default)]
+struct Styles {
+    styles: HashMap<String, StyleInfo>,
+}
+
+#[derive(Debug, Clone, Default)]
+struct Format {
+    style: Option<String>,
+    bold: bool,
+    italic: bool,
+}
+
+
+impl Paragraph {
+    pub fn text(&self) -> String {
+        self.spans.iter().map(|span| span.text.as_str()).collect()
+    }
+
+    fn push_text(&mut self, text: &str, format: &Format) {
+        match self.spans.last_mut() {
+            Some(span) if span.style == format.style && span.bold == format.bold && span.italic == format.italic => {
+                span.text.push_str(text);
+            }
+            _ => self.spans.push(Span {
+                text: text.to_owned(),
+                style: format.style.clone(),
+                bold: format.bold,
+                italic: format.italic,
+            }),
+        }
+    }
+
+    /// Appends character data, collapsing whitespace the way ODF consumers do and dropping soft hyphens.
+    fn push_characters(&mut self, characters: &str, format: &Format) {
+        let mut after_space = self.spans.last().is_none_or(|span| span.text.ends_with([' ', '\n', '\t']));
+        let mut text = String::with_capacity(characters.len());
+        for c in characters.chars() {
+            match c {
+                '\u{ad}' | '\u{200b}' | '\u{feff}' => {}
+                ' ' | '\t' | '\n' | '\r' | '\u{a0}' => {
+                    if !after_space {
+                        text.push(' ');
+                        after_space = true;
+                    }
+                }
+                _ => {
+                    text.push(c);
+                    after_space = false;
+                }
+            }
+        }
+        if !text.is_empty() {
+            self.push_text(&text, format);
+        }
+    }
+
+    fn finish(mut self) -> Option<Paragraph> {
+        while let Some(span) = self.spans.last_mut() {
+            let trimmed = span.text.trim_end_matches(' ').len();
+            span.text.truncate(trimmed);
+            if !span.text.is_empty() {
+                break;
+            }
+            self.spans.pop();
+        }
+        if self.spans.is_empty() {
+            None
+        } else {
+            Some(self)
+        }
+    }
+}
+
+impl OdtDocument {
+    /// Plain text, one line per paragraph.
+    pub fn text(&self) -> String {
+        let mut text = String::new();
+        for paragraph in &self.paragraphs {
+            text.push_str(&paragraph.text());
+            text.push('\n');
+        }
+        text
+    }
+}
+
+impl Styles {
+    fn resolve<T>(&self, name: &str, property: impl Fn(&StyleInfo) -> Option<T>) -> Option<T> {
+        let mut current = self.styles.get(name);
+        // Parent chains are short, the limit only guards against cycles in broken documents.
+        for _ in 0..16 {
+            let style = current?;
+            if let Some(value) = property(style) {
+                return Some(value);
+            }
+            current = style.parent.as_ref().and_then(|parent| self.styles.get(parent));
+        }
+        None
+    }
+
+    fn format(&self, name: Option<&String>) -> Format {
+        match name {
+            Some(name) => Format {
+                style: self.display_name(name),
+                bold: self.resolve(name, |style| style.bold).unwrap_or(false),
+                italic: self.resolve(name, |style| style.italic).unwrap_or(false),
+            },
+            None => Format::default(),
+        }
+    }
+
+    /// Name of the first non-automatic style in the chain, with ODF's `_20_` escapes turned back into spaces.
+    fn display_name(&self, name: &str) -> Option<String> {
+        let mut name = name;
+        while let Some(style) = self.styles.get(name) {
+            match &style.parent {
+                _ if !style.automatic => break,
+                Some(parent) if parent != name => name = parent,
+                _ => return None,
+            }
+        }
+        Some(name.replace("_20_", " "))
+    }
+}
+
+
+/// Reads an ODT document from a zip archive.
+pub fn read_odt<R: Read + Seek>(reader: R, context: &str) -> Result<OdtDocument> {
+    let zip_error = |source| Error::Zip { context: context.to_owned(), source };
+    let mut archive = ZipArchive::new(reader).map_err(zip_error)?;
+
+    let mut styles = Styles::default();
+    if let Some(xml) = read_entry(&mut archive, "styles.xml", context)? {
+        let mut paragraphs = Vec::new();
+        parse_content(xml.as_bytes(), &mut styles, &mut paragraphs, context)?;
+    }
+    let xml = read_entry(&mut archive, "content.xml", context)?.ok_or_else(|| Error::Zip {
+        context: context.to_owned(),
+        source: zip::result::ZipError::FileNotFound,
+    })?;
+
+    let mut paragraphs = Vec::new();
+    parse_content(xml.as_bytes(), &mut styles, &mut paragraphs, context)?;
+    Ok(OdtDocument { paragraphs })
+}
+
+fn read_entry<R: Read + Seek>(archive: &mut ZipArchive<R>, name: &str, context: &str) -> Result<Option<String>> {
+    let mut entry = match archive.by_name(name) {
+        Ok(entry) => entry,
+        Err(zip::result::ZipError::FileNotFound) => return Ok(None),
+        Err(source) => return Err(Error::Zip { context: context.to_owned(), source }),
+    };
+    let mut xml = String::new();
+    entry.read_to_string(&mut xml)?;
+    Ok(Some(xml))
+}
+
+/// Collects style definitions and paragraphs of `styles.xml` or `content.xml`.
+fn parse_content<R: Read>(source: R, styles: &mut Styles, paragraphs: &mut Vec<Paragraph>, context: &str) -> Result<()> {
+    let config = ParserConfig::new().whitespace_to_characters(true).cdata_to_characters(true);
+    let eventreader = EventReader::new_with_config(source, config);
+
+    let mut automatic = false;
+    let mut style: Option<(String, StyleInfo)> = None;
+    let mut open: Vec<(Paragraph, Format)> = Vec::new();
+    let mut spans: Vec<Format> = Vec::new();
+    let mut list_depth = 0;
+    let mut cell_depth = 0;
+    let mut skip_depth = 0;
+
+    for e in eventreader {
+        match e {
+            Ok(XmlEvent::StartElement { name, attributes, .. }) => {
+                if skip_depth > 0 {
+                    skip_depth += 1;
+                    continue;
+                }
+                match name.local_name.as_str() {
+                    "automatic-styles" => automatic = true,
+                    "style" => {
+                        let keyvaluepairs = parse_attributes(attributes);
+                        if let Some(style_name) = keyvaluepairs.get("name") {
+                            style = Some((style_name.to_owned(), StyleInfo {
+                                parent: keyvaluepairs.get("parent-style-name").map(|x| x.to_owned()),
+                                automatic,
+                                ..StyleInfo::default()
+                            }));
+                        }
+                    }
+                    "text-properties" => {
+                        if let Some((_, info)) = style.as_mut() {
+                            let keyvaluepairs = parse_attributes(attributes);
+                            info.bold = keyvaluepairs.get("font-weight").map(|x| x == "bold" || x.parse::<u16>().is_ok_and(|weight| weight >= 600));
+                            info.italic = keyvaluepairs.get("font-style").map(|x| x == "italic" || x == "oblique");
+                        }
+                    }
+                    "list-item" | "list-header" => list_depth += 1,
+                    "table-cell" => cell_depth += 1,
+                    // Footnotes, comments and deleted revisions are not part of the running text.
+                    "note" | "annotation" | "tracked-changes" => skip_depth = 1,
+                    "h" | "p" => {
+                        let keyvaluepairs = parse_attributes(attributes);
+                        let kind = if name.local_name == "h" {
+                            BlockKind::Heading
+                        } else if cell_depth > 0 {
+                            BlockKind::TableCell
+                        } else if list_depth > 0 {
+                            BlockKind::ListItem
+                        } else {
+                            BlockKind::Paragraph
+                        };
+                        let level = match kind {
+                            BlockKind::Heading => Some(keyvaluepairs.get("outline-level").and_then(|x| x.parse().ok()).unwrap_or(1)),
+                            _ => None,
+                        };
+                        let format = styles.format(keyvaluepairs.get("style-name"));
+                        let paragraph = Paragraph { kind, level, style: format.style, spans: Vec::new() };
+                        open.push((paragraph, Format { style: None, ..format }));
+                    }
+                    "span" | "a" => {
+                        let keyvaluepairs = parse_attributes(attributes);
+                        let outer = spans.last().or(open.last().map(|(_, format)| format)).cloned().unwrap_or_default();
+                        let format = match keyvaluepairs.get("style-name") {
+                            Some(style_name) => {
+                                let own = styles.format(Some(style_name));
+                                Format {
+                                    style: own.style,
+                                    bold: styles.resolve(style_name, |style| style.bold).unwrap_or(outer.bold),
+                                    italic: styles.resolve(style_name, |style| style.italic).unwrap_or(outer.italic),
+                                }
+                            }
+                            None => outer,
+                        };
+                        spans.push(format);
+                    }
+                    "s" | "tab" | "line-break" => {
+                        if let Some((paragraph, paragraph_format)) = open.last_mut() {
+                            let format = spans.last().unwrap_or(paragraph_format);
+                            match name.local_name.as_str() {
+                                "s" => {
+                                    let keyvaluepairs = parse_attributes(attributes);
+                                    let count: usize = keyvaluepairs.get("c").and_then(|x| x.parse().ok()).unwrap_or(1);
+                                    paragraph.push_text(&" ".repeat(count), format);
+                                }
+                                "tab" => paragraph.push_text("\t", format),
+                                _ => paragraph.push_text("\n", format),
+                            }
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            Ok(XmlEvent::EndElement { name }) => {
+                if skip_depth > 0 {
+                    skip_depth -= 1;
+                    continue;
+                }
+                match name.local_name.as_str() {
+                    "automatic-styles" => automatic = false,
+                    "style" => {
+                        if let Some((style_name, info)) = style.take() {
+                            styles.styles.insert(style_name, info);
+                        }
+                    }
+                    "list-item" | "list-header" => list_depth -= 1,
+                    "table-cell" => cell_depth -= 1,
+                    "h" | "p" => {
+                        if let Some(paragraph) = open.pop().and_then(|(paragraph, _)| paragraph.finish()) {
+                            paragraphs.push(paragraph);
+                        }
+                    }
+                    "span" | "a" => {
+                        spans.pop();
+                    }
+                    _ => {}
+                }
+            }
+            Ok(XmlEvent::Characters(characters)) => {
+                if skip_depth > 0 {
+                    continue;
+                }
+                if let Some((paragraph, paragraph_format)) = open.last_mut() {
+                    paragraph.push_characters(&characters, spans.last().unwrap_or(paragraph_format));
+                }
+            }
+            Err(e) => {
+                return Err(Error::Xml { context: context.to_owned(), source: e });
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Cursor, Write};
+
+    use zip::write::{FileOptions, ZipWriter};
+
+    use super::*;
+
+    fn document(body: &str) -> String {
+        format!(
+            r#"<office:document-content xmlns:office="urn:oasis:names:tc:opendocument:xmlns:office:1.0" xmlns:style="urn:oasis:names:tc:opendocument:xmlns:style:1.0" xmlns:text="urn:oasis:names:tc:opendocument:xmlns:text:1.0" xmlns:fo="urn:oasis:names:tc:opendocument:xmlns:xsl-fo-compatible:1.0">{}</office:document-content>"#,
+            body
+        )
+    }
+
+    fn parse(body: &str) -> OdtDocument {
+        let mut paragraphs = Vec::new();
+        parse_content(document(body).as_bytes(), &mut Styles::default(), &mut paragraphs, "test").unwrap();
+        OdtDocument { paragraphs }
+    }
+
+    #[test]
+    fn normalises_whitespace_and_drops_soft_hyphens() {
+        let odt = parse("<office:body><text:p>  Sei\u{ad}mo \n\t na&#xAD;rys\u{a0} kal<text:span>ba </text:span> </text:p></office:body>");
+        assert_eq!(odt.text(), "Seimo narys kalba\n");
+    }
+
+    #[test]
+    fn keeps_explicit_spaces_tabs_and_line_breaks() {
+        let odt = parse(r#"<office:body><text:p>a<text:s text:c="3"/>b<text:tab/>c<text:line-break/>d<text:s/></text:p></office:body>"#);
+        assert_eq!(odt.text(), "a   b\tc\nd\n");
+    }
+
+    #[test]
+    fn skips_notes_and_empty_paragraphs() {
+        let odt = parse(
+            r#"<office:body><text:p>Pirmas<text:note><text:note-body><text:p>išnaša</text:p></text:note-body></text:note></text:p><text:p> </text:p><text:p>Antras</text:p></office:body>"#,
+        );
+        assert_eq!(odt.text(), "Pirmas\nAntras\n");
+    }
+
+    #[test]
+    fn resolves_block_kinds_and_automatic_styles() {
+        let odt = parse(
+            r#"<office:automatic-styles><style:style style:name="T1" style:parent-style-name="Strong"><style:text-properties fo:font-weight="bold"/></style:style></office:automatic-styles>
+            <office:body><text:h text:outline-level="2">Darbotvarkė</text:h><text:list><text:list-item><text:p>Punktas <text:span text:style-name="T1">svarbus</text:span></text:p></text:list-item></text:list></office:body>"#,
+        );
+        assert_eq!(odt.paragraphs.len(), 2);
+        assert_eq!((odt.paragraphs[0].kind, odt.paragraphs[0].level), (BlockKind::Heading, Some(2)));
+        let item = &odt.paragraphs[1];
+        assert_eq!(item.kind, BlockKind::ListItem);
+        assert_eq!(item.spans.len(), 2);
+        assert_eq!(item.spans[1], Span { text: "svarbus".to_owned(), style: Some("Strong".to_owned()), bold: true, italic: false });
+    }
+
+    #[test]
+    fn reads_content_from_the_archive() {
+        let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+        zip.start_file("content.xml", FileOptions::default()).unwrap();
+        zip.write_all(document("<office:body><text:p>Posėdis pradedamas</text:p></office:body>").as_bytes()).unwrap();
+        let archive = zip.finish().unwrap();
+
+        let odt = read_odt(Cursor::new(archive.into_inner()), "test").unwrap();
+        assert_eq!(odt.text(), "Posėdis pradedamas\n");
+    }
+}
@@ -0,0 +1,226 @@
+use std::path::PathBuf;
+use std::sync::OnceLock;
+
+use diesel::PgConnection;
+use log::{debug, info};
+use regex::Regex;
+
+use crate::database::context::Context;
+use crate::error::{Error, Result};
+use crate::models::session::agenda_outcome::{AgendaOutcome, AgendaOutcomeDocument, AgendaOutcomePresenter, OutcomeType};
+use crate::models::session::meeting_data::{AgendaItem, MeetingData};
+use crate::models::session::meetings::Meetings;
+
+/// One agenda question as recorded in a meeting protocol.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProtocolItem {
+    /// Question number as in `agenda_item.nr`, e.g. `2-1`.
+    pub nr: Option<String>,
+    pub title: String,
+    pub presenters: Vec<String>,
+    /// Text of the last decision recorded for the question.
+    pub decision: Option<String>,
+    pub outcome: Option<OutcomeType>,
+    /// Registration numbers of the documents mentioned, e.g. `XIIP-1234(2)`.
+    pub document_numbers: Vec<String>,
+}
+
+struct Patterns {
+    header: Regex,
+    timed_header: Regex,
+    presenters: Regex,
+    decision: Regex,
+    document: Regex,
+}
+
+fn patterns() -> &'static Patterns {
+    static PATTERNS: OnceLock<Patterns> = OnceLock::new();
+    PATTERNS.get_or_init(|| Patterns {
+        header: Regex::new(r"^(?P<nr>\d{1,3}(?:[-.]\d{1,3}){0,2}[a-z]?)\.\s+(?:\d{1,2}\s*val\.\s*\d{1,2}\s*min\.\s*)?(?P<title>\S.*)$").unwrap(),
+        timed_header: Regex::new(r"^\d{1,3}(?:[-.]\d{1,3}){0,2}[a-z]?\.\s+\d{1,2}\s*val\.\s*\d{1,2}\s*min\.").unwrap(),
+        presenters: Regex::new(r"^Pranešėj(?:as|a|ai|os)\s*[–—:-]\s*(?P<names>.+)$").unwrap(),
+        decision: Regex::new(r"(?i)^(?:(?:nutarta|sprendimas)\s*[:.–—-]?\s*(?P<text>.+)|(?:(?:ne)?pritart|atmest|grąžint|atidėt|(?:ne)?priimt).*)$").unwrap(),
+        document: Regex::new(r"\b[IVXLC]{1,6}P?-\d{1,5}(?:\(\d+\))?(?:ES)?").unwrap(),
+    })
+}
+
+/// Maps the wording of a decision onto an outcome.
+pub fn classify(decision: &str) -> OutcomeType {
+    let decision = decision.to_lowercase();
+    if decision.contains("grąžint") {
+        OutcomeType::ReturnedForImprovement
+    } else if decision.contains("nepritar") || decision.contains("atmest") || decision.contains("nepriimt") {
+        OutcomeType::Rejected
+    } else if decision.contains("atidė") {
+        OutcomeType::Postponed
+    } else if decision.contains("pritar") || decision.contains("priimt") {
+        OutcomeType::Approved
+    } else {
+        OutcomeType::Other
+    }
+}
+
+fn push_unique(values: &mut Vec<String>, value: &str) {
+    if !values.iter().any(|x| x == value) {
+        values.push(value.to_owned());
+    }
+}
+
+/// Splits the text of a protocol into agenda questions.
+///
+/// Question headers carry the time the question was taken up; numbered lines without
+/// a time are only treated as headers in protocols that never print times.
+pub fn extract(text: &str) -> Vec<ProtocolItem> {
+    let patterns = patterns();
+    let lines: Vec<String> = text.lines().map(|line| line.replace('\u{ad}', "").trim().to_owned()).collect();
+    let timed = lines.iter().any(|line| patterns.timed_header.is_match(line));
+
+    let mut items: Vec<ProtocolItem> = Vec::new();
+    for line in lines.iter().filter(|line| !line.is_empty()) {
+        if !timed || patterns.timed_header.is_match(line) {
+            if let Some(captures) = patterns.header.captures(line) {
+                let mut item = ProtocolItem {
+                    nr: Some(captures["nr"].replace('.', "-")),
+                    title: captures["title"].to_owned(),
+                    presenters: Vec::new(),
+                    decision: None,
+                    outcome: None,
+                    document_numbers: Vec::new(),
+                };
+                for document in patterns.document.find_iter(&item.title) {
+                    push_unique(&mut item.document_numbers, document.as_str());
+                }
+                items.push(item);
+                continue;
+            }
+        }
+
+        let item = match items.last_mut() {
+            Some(item) => item,
+            None => continue,
+        };
+        for document in patterns.document.find_iter(line) {
+            push_unique(&mut item.document_numbers, document.as_str());
+        }
+        if let Some(captures) = patterns.presenters.captures(line) {
+            for name in captures["names"].split([',', ';']).flat_map(|x| x.split(" ir ")) {
+                let name = name.trim().trim_end_matches('.');
+                if !name.is_empty() {
+                    push_unique(&mut item.presenters, name);
+                }
+            }
+        } else if let Some(captures) = patterns.decision.captures(line) {
+            let decision = captures.name("text").map_or(line.as_str(), |text| text.as_str());
+            item.outcome = Some(classify(decision));
+            item.decision = Some(decision.to_owned());
+        }
+    }
+    items
+}
+
+
+/// Extracts the agenda outcomes of a downloaded protocol into `agenda_outcome`.
+pub async fn parse_protocol(ctx: Context, meeting: Meetings, path: PathBuf) -> Result<usize> {
+    info!("Parsing protocol: {}", path.display());
+    let text = tokio::fs::read_to_string(&path).await?;
+    let items = extract(&text);
+    if items.is_empty() {
+        return Err(Error::Other(format!("no agenda questions found in protocol of meeting {}", meeting.id)));
+    }
+    let saved = ctx.run(move |conn| save_items(conn, &meeting, items)).await?;
+    Ok(saved)
+}
+
+fn save_items(conn: &mut PgConnection, meeting: &Meetings, items: Vec<ProtocolItem>) -> Result<usize> {
+    let agenda_ids = MeetingData::get_agenda_ids(conn, &[meeting.id])?;
+    let agenda = AgendaItem::get_agenda_items(conn, &agenda_ids)?;
+
+    let mut outcomes = Vec::with_capacity(items.len());
+    let mut presenters = Vec::new();
+    let mut documents = Vec::new();
+    for (position, item) in items.into_iter().enumerate() {
+        let position = position as i32;
+        let agenda_item = item.nr.as_ref().and_then(|nr| {
+            agenda.iter().find(|agenda_item| agenda_item.nr.as_deref().map(|x| x.trim().trim_end_matches('.').replace('.', "-")).as_ref() == Some(nr))
+        });
+        if agenda_item.is_none() {
+            debug!("Protocol question {:?} of meeting {} has no agenda item", item.nr, meeting.id);
+        }
+        presenters.extend(item.presenters.into_iter().enumerate().map(|(index, name)| AgendaOutcomePresenter {
+            meeting_id: meeting.id,
+            outcome_position: position,
+            position: index as i32,
+            name,
+        }));
+        documents.extend(item.document_numbers.into_iter().enumerate().map(|(index, document_number)| AgendaOutcomeDocument {
+            meeting_id: meeting.id,
+            outcome_position: position,
+            position: index as i32,
+            document_number,
+        }));
+        outcomes.push(AgendaOutcome {
+            meeting_id: meeting.id,
+            position,
+            nr: item.nr,
+            agenda_item_id: agenda_item.map(|agenda_item| agenda_item.id),
+            document_key: agenda_item.and_then(|agenda_item| agenda_item.document_key),
+            title: item.title,
+            outcome: item.outcome,
+            decision: item.decision,
+        });
+    }
+    let saved = AgendaOutcome::replace_for_meeting(conn, meeting.id, &outcomes, &presenters, &documents).map_err(Error::database(&format!("protocol of meeting {}", meeting.id)))?;
+    Ok(saved)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn classifies_decisions() {
+        assert_eq!(classify("Pritarta."), OutcomeType::Approved);
+        assert_eq!(classify("Priimta"), OutcomeType::Approved);
+        assert_eq!(classify("Atmesta."), OutcomeType::Rejected);
+        assert_eq!(classify("Nepritarta"), OutcomeType::Rejected);
+        assert_eq!(classify("Grąžinta tobulinti iniciatoriams."), OutcomeType::ReturnedForImprovement);
+        assert_eq!(classify("Svarstymas atidėtas."), OutcomeType::Postponed);
+        assert_eq!(classify("Informacija išklausyta."), OutcomeType::Other);
+    }
+
+    #[test]
+    fn extracts_timed_questions_with_presenters_documents_and_decisions() {
+        let protocol = "LIETUVOS RESPUBLIKOS SEIMO POSĖDŽIO PROTOKOLAS NR. 1\n\
+            1. Posėdžio pradžia 10 val.\n\
+            1. 10 val. 02 min. Civilinio kodekso pakeitimo įstatymo projektas Nr. XIP-1234(2) (pateikimas)\n\
+            Pranešėjas – A. Kubilius.\n\
+            Nutarta: pri\u{ad}tarta po pateikimo.\n\
+            2-1. 10 val. 30 min. Mokesčių įstatymo projektas XIP-77\n\
+            Pranešėjai – J. Jonaitis, P. Petraitis ir R. Rimaitė.\n\
+            Atmesta.\n\
+            2-2. 11 val. 00 min. Nutarimo projektas XIP-78, XIP-78\n\
+            Grąžinta tobulinti.\n";
+        let items = extract(protocol);
+        assert_eq!(items.len(), 3);
+
+        assert_eq!(items[0].nr.as_deref(), Some("1"));
+        assert_eq!(items[0].presenters, ["A. Kubilius"]);
+        assert_eq!(items[0].document_numbers, ["XIP-1234(2)"]);
+        assert_eq!(items[0].decision.as_deref(), Some("pritarta po pateikimo."));
+        assert_eq!(items[0].outcome, Some(OutcomeType::Approved));
+
+        assert_eq!(items[1].nr.as_deref(), Some("2-1"));
+        assert_eq!(items[1].presenters, ["J. Jonaitis", "P. Petraitis", "R. Rimaitė"]);
+        assert_eq!(items[1].outcome, Some(OutcomeType::Rejected));
+
+        assert_eq!(items[2].document_numbers, ["XIP-78"]);
+        assert_eq!(items[2].outcome, Some(OutcomeType::ReturnedForImprovement));
+    }
+
+    #[test]
+    fn treats_numbered_lines_as_questions_when_no_times_are_printed() {
+        let items = extract("1. Darbotvarkės tvirtinimas\nPritarta.\n2.1. Seimo nutarimo projektas\nNepritarta.\n");
+        let outcomes: Vec<(Option<&str>, Option<OutcomeType>)> = items.iter().map(|item| (item.nr.as_deref(), item.outcome)).collect();
+        assert_eq!(outcomes, [(Some("1"), Some(OutcomeType::Approved)), (Some("2-1"), Some(OutcomeType::Rejected))]);
+    }
+}
@@ -0,0 +1,294 @@
+use std::path::PathBuf;
+use std::sync::OnceLock;
+
+use diesel::PgConnection;
+use log::{debug, info};
+use regex::Regex;
+
+use crate::database::context::Context;
+use crate::error::{Error, Result};
+use crate::models::politicians::politician::Politician;
+use crate::models::session::meeting_data::{AgendaItem, MeetingData, Speech};
+use crate::models::session::meetings::Meetings;
+use crate::models::session::sessions::Sessions;
+use crate::models::session::speech_text::SpeechText;
+
+const UPPER: &str = "A-ZĄČĘĖĮŠŲŪŽ";
+
+/// A speaker turn: the label that opens it and everything said until the next label.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Turn {
+    /// Label as printed, e.g. `A. KUBILIUS (TS-LKDF*)`, `PIRMININKAS (V. LANDSBERGIS)` or `PIRMININKAS`.
+    pub speaker: String,
+    pub name: Option<SpeakerName>,
+    pub faction: Option<String>,
+    /// Whether the turn belongs to whoever is chairing the sitting.
+    pub chair: bool,
+    pub text: String,
+}
+
+/// Speaker as printed in stenograms: initials of the given names and the surname in capitals.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SpeakerName {
+    pub initials: Vec<char>,
+    pub surname: String,
+}
+
+struct Patterns {
+    member: Regex,
+    chair: Regex,
+    voices: Regex,
+    name: Regex,
+}
+
+fn patterns() -> &'static Patterns {
+    static PATTERNS: OnceLock<Patterns> = OnceLock::new();
+    PATTERNS.get_or_init(|| {
+        let name = format!(r"(?:[{u}]\.\s?){{1,3}}[{u}][{u}\-]+(?:\s[{u}][{u}\-]+)?", u = UPPER);
+        Patterns {
+            member: Regex::new(&format!(r"^(?P<name>{})(?:\s\((?P<faction>[^)]{{1,40}})\))?\.(?:\s+(?P<rest>.*))?$", name)).unwrap(),
+            chair: Regex::new(&format!(r"^(?P<role>PIRMININK[{u}]*(?:\s+PAVADUOTOJ[{u}]*)?)(?:\s*\((?P<name>[^)]+)\))?\.(?:\s+(?P<rest>.*))?$", u = UPPER)).unwrap(),
+            voices: Regex::new(&format!(r"^(?P<role>BALS(?:AS|AI)\s(?:SALĖJE|IŠ\sSALĖS)|[{u}]+\sSALĖJE)\.(?:\s+(?P<rest>.*))?$", u = UPPER)).unwrap(),
+            name: Regex::new(&format!(r"^{}$", name)).unwrap(),
+        }
+    })
+}
+
+impl SpeakerName {
+    fn parse(name: &str) -> Option<SpeakerName> {
+        let name = name.trim();
+        if !patterns().name.is_match(name) {
+            return None;
+        }
+        let (initials, surname) = name.rsplit_once('.')?;
+        Some(SpeakerName {
+            initials: initials.chars().filter(|c| c.is_alphabetic()).collect(),
+            surname: surname.trim().to_owned(),
+        })
+    }
+
+    /// Finds the one member whose surname and given-name initials match.
+    fn resolve(&self, politicians: &[(i32, String, String)]) -> Option<i32> {
+        let candidates: Vec<&(i32, String, String)> = politicians
+            .iter()
+            .filter(|(_, _, surname)| surname.to_uppercase() == self.surname)
+            .filter(|(_, name, _)| {
+                let initials: Vec<char> = name
+                    .split(|c: char| c.is_whitespace() || c == '-')
+                    .filter_map(|part| part.chars().next())
+                    .flat_map(char::to_uppercase)
+                    .collect();
+                initials.starts_with(&self.initials) || (self.initials.len() == 1 && initials.first() == self.initials.first())
+            })
+            .collect();
+        match candidates.as_slice() {
+            [(id, _, _)] => Some(*id),
+            _ => None,
+        }
+    }
+}
+
+
+/// Splits the text of a stenogram into speaker turns, dropping the header before the first one.
+pub fn segment(text: &str) -> Vec<Turn> {
+    let patterns = patterns();
+    let mut turns: Vec<Turn> = Vec::new();
+
+    for line in text.lines() {
+        let line = line.replace('\u{ad}', "");
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        let turn = if let Some(captures) = patterns.chair.captures(line) {
+            let name = captures.name("name").map(|name| name.as_str());
+            Some((Turn {
+                speaker: match name {
+                    Some(name) => format!("{} ({})", &captures["role"], name),
+                    None => captures["role"].to_owned(),
+                },
+                name: name.and_then(SpeakerName::parse),
+                faction: None,
+                chair: true,
+                text: String::new(),
+            }, captures.name("rest")))
+        } else if let Some(captures) = patterns.member.captures(line) {
+            Some((Turn {
+                speaker: line[..captures.name("faction").map_or(captures["name"].len(), |faction| faction.end() + 1)].to_owned(),
+                name: SpeakerName::parse(&captures["name"]),
+                faction: captures.name("faction").map(|faction| faction.as_str().to_owned()),
+                chair: false,
+                text: String::new(),
+            }, captures.name("rest")))
+        } else {
+            patterns.voices.captures(line).map(|captures| (Turn {
+                speaker: captures["role"].to_owned(),
+                name: None,
+                faction: None,
+                chair: false,
+                text: String::new(),
+            }, captures.name("rest")))
+        };
+
+        match turn {
+            Some((mut turn, rest)) => {
+                if let Some(rest) = rest {
+                    turn.text.push_str(rest.as_str().trim());
+                }
+                turns.push(turn);
+            }
+            None => {
+                if let Some(turn) = turns.last_mut() {
+                    if !turn.text.is_empty() {
+                        turn.text.push('\n');
+                    }
+                    turn.text.push_str(line);
+                }
+            }
+        }
+    }
+    turns
+}
+
+/// Matches turns to the meeting's `speech` rows, walking both in time order.
+///
+/// A turn takes the next unmatched speech of the same person; follow-up turns of the
+/// current speaker (answers to questions, after the chair cuts in) stay on the same speech.
+fn link_speeches(person_ids: &[Option<i32>], speeches: &[(i32, Option<i32>)]) -> Vec<Option<i32>> {
+    let mut cursor = 0;
+    let mut current: Option<(i32, i32)> = None;
+    person_ids
+        .iter()
+        .map(|person_id| {
+            let person_id = (*person_id)?;
+            if let Some(offset) = speeches[cursor..].iter().position(|(_, speaker)| *speaker == Some(person_id)) {
+                let next_is_own = offset == 0;
+                if next_is_own || current.map(|(speaker, _)| speaker) != Some(person_id) {
+                    let (speech_id, _) = speeches[cursor + offset];
+                    cursor += offset + 1;
+                    current = Some((person_id, speech_id));
+                    return Some(speech_id);
+                }
+            }
+            current.filter(|(speaker, _)| *speaker == person_id).map(|(_, speech_id)| speech_id)
+        })
+        .collect()
+}
+
+
+/// Segments a downloaded stenogram and stores its turns in `speech_text`.
+pub async fn parse_stenogram(ctx: Context, meeting: Meetings, path: PathBuf) -> Result<usize> {
+    info!("Parsing stenogram: {}", path.display());
+    let text = tokio::fs::read_to_string(&path).await?;
+    let context = format!("stenogram of meeting {}", meeting.id);
+    let turns = segment(&text);
+    if turns.is_empty() {
+        return Err(Error::Other(format!("no speaker turns found in {}", context)));
+    }
+    let saved = ctx.run(move |conn| save_turns(conn, &meeting, turns)).await?;
+    Ok(saved)
+}
+
+fn save_turns(conn: &mut PgConnection, meeting: &Meetings, turns: Vec<Turn>) -> Result<usize> {
+    let parliament_id = Sessions::get_parliament_id(conn, meeting.session).map_err(Error::database("session of a stenogram"))?;
+    let politicians = Politician::get_names_per_parliament(conn, parliament_id)?;
+    let agenda_ids = MeetingData::get_agenda_ids(conn, &[meeting.id])?;
+    let speech_ids = AgendaItem::get_speech_ids(conn, &agenda_ids)?;
+    let speeches = Speech::get_speakers_in_order(conn, &speech_ids)?;
+
+    let person_ids: Vec<Option<i32>> = turns
+        .iter()
+        .map(|turn| turn.name.as_ref().and_then(|name| name.resolve(&politicians)))
+        .collect();
+    let speech_links = link_speeches(
+        &turns.iter().zip(&person_ids).map(|(turn, person_id)| if turn.chair { None } else { *person_id }).collect::<Vec<_>>(),
+        &speeches,
+    );
+
+    let unresolved = turns.iter().zip(&person_ids).filter(|(turn, person_id)| turn.name.is_some() && person_id.is_none()).count();
+    if unresolved > 0 {
+        debug!("{} turn(s) of meeting {} could not be matched to a member", unresolved, meeting.id);
+    }
+
+    let rows: Vec<SpeechText> = turns
+        .into_iter()
+        .zip(person_ids)
+        .zip(speech_links)
+        .enumerate()
+        .map(|(position, ((turn, person_id), speech_id))| SpeechText {
+            meeting_id: meeting.id,
+            position: position as i32,
+            speaker: turn.speaker,
+            faction: turn.faction,
+            chair: turn.chair,
+            person_id,
+            speech_id,
+            text: turn.text,
+            parliament: parliament_id,
+        })
+        .collect();
+    let saved = SpeechText::replace_for_meeting(conn, meeting.id, &rows).map_err(Error::database(&format!("stenogram of meeting {}", meeting.id)))?;
+    Ok(saved)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const STENOGRAM: &str = "LIETUVOS RESPUBLIKOS SEIMO\nRYTINIO POSĖDŽIO NR. 1\nSTENOGRAMA\n\n\
+        PIRMININKAS (V. LANDSBERGIS). Pradedame posėdį.\n\
+        A. KUBILIUS (TS-LKDF*). Gerbiamieji kolegos,\n\
+        siūlau pritarti pro\u{ad}jektui.\n\
+        BALSAI SALĖJE. Teisingai!\n\
+        PIRMININKAS. Ačiū. Kviečiu balsuoti.\n\
+        PIRMININKO PAVADUOTOJAS (Č. JURŠĖNAS). Dėl vedimo tvarkos.\n\
+        J. V. PALECKIS. Klausimas.\n";
+
+    #[test]
+    fn segments_member_chair_and_voices_lines() {
+        let turns = segment(STENOGRAM);
+        let speakers: Vec<(&str, bool)> = turns.iter().map(|turn| (turn.speaker.as_str(), turn.chair)).collect();
+        assert_eq!(
+            speakers,
+            [
+                ("PIRMININKAS (V. LANDSBERGIS)", true),
+                ("A. KUBILIUS (TS-LKDF*)", false),
+                ("BALSAI SALĖJE", false),
+                ("PIRMININKAS", true),
+                ("PIRMININKO PAVADUOTOJAS (Č. JURŠĖNAS)", true),
+                ("J. V. PALECKIS", false),
+            ]
+        );
+
+        assert_eq!(turns[0].name, Some(SpeakerName { initials: vec!['V'], surname: "LANDSBERGIS".to_owned() }));
+        assert_eq!(turns[1].faction.as_deref(), Some("TS-LKDF*"));
+        assert_eq!(turns[1].text, "Gerbiamieji kolegos,\nsiūlau pritarti projektui.");
+        assert_eq!(turns[2].name, None);
+        assert_eq!((turns[3].name.as_ref(), turns[3].text.as_str()), (None, "Ačiū. Kviečiu balsuoti."));
+        assert_eq!(turns[5].name, Some(SpeakerName { initials: vec!['J', 'V'], surname: "PALECKIS".to_owned() }));
+    }
+
+    #[test]
+    fn resolves_speakers_by_surname_and_initials() {
+        let politicians = vec![
+            (1, "Andrius".to_owned(), "Kubilius".to_owned()),
+            (2, "Justas Vincas".to_owned(), "Paleckis".to_owned()),
+            (3, "Rasa".to_owned(), "Juknevičienė".to_owned()),
+            (4, "Remigijus".to_owned(), "Juknevičienė".to_owned()),
+        ];
+        let resolve = |name: &str| SpeakerName::parse(name).and_then(|name| name.resolve(&politicians));
+        assert_eq!(resolve("A. KUBILIUS"), Some(1));
+        assert_eq!(resolve("J. V. PALECKIS"), Some(2));
+        assert_eq!(resolve("J. PALECKIS"), Some(2));
+        assert_eq!(resolve("R. JUKNEVIČIENĖ"), None);
+        assert_eq!(resolve("Kubilius"), None);
+    }
+
+    #[test]
+    fn links_follow_up_turns_to_the_current_speech() {
+        let speeches = [(10, Some(1)), (11, Some(2)), (12, Some(1))];
+        let turns = [Some(1), None, Some(1), Some(2), Some(3), Some(1)];
+        assert_eq!(link_speeches(&turns, &speeches), [Some(10), None, Some(10), Some(11), None, Some(12)]);
+    }
+}
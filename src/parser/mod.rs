@@ -1,3 +1,4 @@
 pub mod session;
 pub mod politicians;
+pub mod document;
 pub mod util;
\ No newline at end of file
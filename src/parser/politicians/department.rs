@@ -0,0 +1,125 @@
+use std::io::Read;
+use chrono::NaiveDate;
+use diesel::{Connection, PgConnection};
+use log::{debug, info};
+use xml::reader::{EventReader, XmlEvent};
+
+use networking::request::*;
+use crate::database::context::Context;
+use crate::models::politicians::department::{Department, DepartmentKind, DepartmentMember};
+use crate::models::politicians::faction::{Faction, FactionMembership};
+use crate::models::upsert::Upsert;
+use crate::networking;
+
+use crate::error::{Error, Result};
+use crate::parser::util::{parse_attributes, parse_attribute, get_attribute};
+
+/// Structural-unit endpoints of a parliament and the kind of department each lists.
+const ENDPOINTS: [(&str, DepartmentKind); 3] = [
+    ("ad_seimo_komitetai", DepartmentKind::Committee),
+    ("ad_seimo_komisijos", DepartmentKind::Commission),
+    ("ad_seimo_frakcijos", DepartmentKind::Faction),
+];
+
+pub async fn get_departments(ctx: Context, parliament_id: i32) -> Result<usize> {
+    let mut saved = 0;
+    for (endpoint, kind) in ENDPOINTS {
+        info!("Downloading {:?} departments of parliament {}", kind, parliament_id);
+        let link = format!("https://apps.lrs.lt/sip/p2b.{}?kadencijos_id={}", endpoint, parliament_id);
+        let xmlstring = url_request(&link).await?;
+        let context = format!("{:?} departments of parliament {}", kind, parliament_id);
+        saved += ctx.run(move |conn| parse_departments(conn, EventReader::from_str(&xmlstring), parliament_id, kind, &context)).await?;
+    }
+    Ok(saved)
+}
+
+/// Every element carrying `padalinio_id` opens a department, elements carrying `asmens_id` inside it are its members.
+fn parse_departments<R: Read> (conn: &mut PgConnection, eventreader: EventReader<R>, parliament_id: i32, kind: DepartmentKind, context: &str) -> Result<usize> {
+    let mut departments: Vec<(Department, Vec<DepartmentMember>)> = Vec::new();
+    let mut open_element: Option<String> = None;
+
+    for e in eventreader {
+        match e {
+            Ok(XmlEvent::StartElement { name, attributes, .. }) => {
+                let keyvaluepairs = parse_attributes(attributes);
+                let element = name.local_name.as_str();
+                if keyvaluepairs.contains_key("asmens_id") {
+                    let (department, members) = match (&open_element, departments.last_mut()) {
+                        (Some(_), Some(current)) => current,
+                        _ => continue,
+                    };
+                    members.push(DepartmentMember {
+                        department_id: department.id,
+                        parliament: parliament_id,
+                        position: members.len() as i32,
+                        politician_id: parse_attribute(&keyvaluepairs, element, "asmens_id", context)?,
+                        duties: keyvaluepairs.get("pareigos").map(|x| x.to_owned()),
+                        from: keyvaluepairs.get("data_nuo").and_then(|x| NaiveDate::parse_from_str(x, "%Y-%m-%d").ok()),
+                        to: keyvaluepairs.get("data_iki").and_then(|x| NaiveDate::parse_from_str(x, "%Y-%m-%d").ok()),
+                    });
+                } else if keyvaluepairs.contains_key("padalinio_id") {
+                    let department = Department {
+                        id: parse_attribute(&keyvaluepairs, element, "padalinio_id", context)?,
+                        name: get_attribute(&keyvaluepairs, element, "padalinio_pavadinimas", context)?.to_owned(),
+                        short_name: keyvaluepairs.get("padalinio_pavadinimo_santrumpa").map(|x| x.to_owned()),
+                        kind,
+                    };
+                    departments.push((department, Vec::new()));
+                    open_element = Some(name.local_name);
+                }
+            }
+            Ok(XmlEvent::EndElement { name }) if open_element.as_deref() == Some(name.local_name.as_str()) => {
+                open_element = None;
+            }
+            Err(e) => {
+                return Err(Error::Xml { context: context.to_owned(), source: e });
+            }
+            _ => {}
+        }
+    }
+
+    let (departments, members): (Vec<Department>, Vec<Vec<DepartmentMember>>) = departments.into_iter().unzip();
+    conn.transaction(|conn| {
+        let summary = Department::upsert_batch(conn, &departments)?;
+        debug!("{}: {} inserted, {} updated, {} unchanged", context, summary.inserted, summary.updated, summary.unchanged);
+        for (department, members) in departments.iter().zip(&members) {
+            DepartmentMember::replace(conn, department.id, parliament_id, members)?;
+        }
+        if kind == DepartmentKind::Faction {
+            save_factions(conn, parliament_id, &departments, &members)?;
+        }
+        Ok::<_, diesel::result::Error>(())
+    }).map_err(Error::database(context))?;
+    Ok(departments.len())
+}
+
+/// Mirrors faction departments into `faction` and merges their composition into `faction_membership`.
+fn save_factions(conn: &mut PgConnection, parliament_id: i32, departments: &[Department], members: &[Vec<DepartmentMember>]) -> std::result::Result<(), diesel::result::Error> {
+    let factions: Vec<Faction> = departments
+        .iter()
+        .map(|department| Faction {
+            id: department.id,
+            parliament: parliament_id,
+            name: department.name.clone(),
+            short_name: department.short_name.clone(),
+        })
+        .collect();
+    Faction::upsert_batch(conn, &factions)?;
+
+    for (faction, members) in factions.iter().zip(members) {
+        // A stint without a start date cannot be placed on the timeline.
+        let memberships: Vec<FactionMembership> = members
+            .iter()
+            .filter_map(|member| Some(FactionMembership {
+                politician_id: member.politician_id,
+                parliament: parliament_id,
+                faction_id: faction.id,
+                from: member.from?,
+                to: member.to,
+                duties: member.duties.clone(),
+            }))
+            .collect();
+        FactionMembership::merge(conn, &memberships)?;
+    }
+    Ok(())
+}
@@ -1 +1,2 @@
-pub mod politician;
\ No newline at end of file
+pub mod politician;
+pub mod department;
\ No newline at end of file
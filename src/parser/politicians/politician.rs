@@ -1,26 +1,32 @@
-use std::error::Error;
 use std::io::Read;
 use chrono::NaiveDate;
+use diesel::{Connection, PgConnection};
 use xml::reader::{EventReader, XmlEvent};
 
 use networking::request::*;
-use crate::database::connect::establish_connection;
-use crate::models::politicians::politician::{Politician, OfficeInsertable, Gender, DepartmentType};
+use crate::database::context::Context;
+use crate::models::politicians::politician::{Politician, PoliticianOffice, OfficeInsertable, Gender, DepartmentType};
+use crate::models::politicians::department::{Department, DepartmentKind, ParliamentaryGroup};
+use crate::models::politicians::faction::{Faction, FactionMembership};
+use crate::models::upsert::Upsert;
 use crate::networking;
 
-use crate::parser::util::parse_attributes;
+use crate::error::{Error, Result};
+use crate::parser::util::{parse_attributes, parse_attribute, get_attribute, require_parent};
 
-pub async fn get_politicians(parliament_id: i32) -> Result<(), Box<dyn Error + Send + Sync>> {
+pub async fn get_politicians(ctx: Context, parliament_id: i32) -> Result<usize> {
     let link = format!("https://apps.lrs.lt/sip/p2b.ad_seimo_nariai?kadencijos_id={}", parliament_id);
     let xmlstring = url_request(&link).await?;
-    parse_politicians(EventReader::from_str(&xmlstring));
-    Ok(())
+    let context = format!("members of parliament {}", parliament_id);
+    let saved = ctx.run(move |conn| parse_politicians(conn, EventReader::from_str(&xmlstring), &context)).await?;
+    Ok(saved)
 }
 
-fn parse_politicians<R: Read> (eventreader: EventReader<R>) {
-    let conn = &mut establish_connection();
+fn parse_politicians<R: Read> (conn: &mut PgConnection, eventreader: EventReader<R>, context: &str) -> Result<usize> {
+    let mut saved = 0;
     let mut parliament_id: Option<i32> = None;
     let mut politician: Option<Politician> = None;
+    let mut offices: Vec<(OfficeInsertable, String)> = Vec::new();
 
     for e in eventreader {
         match e {
@@ -28,21 +34,21 @@ fn parse_politicians<R: Read> (eventreader: EventReader<R>) {
                 match name.local_name.as_str() {
                     "SeimoNarys" => {
                         let keyvaluepairs = parse_attributes(attributes);
-                        let id: i32 = keyvaluepairs["asmens_id"].parse().unwrap();
-                        let parliament: i32 = parliament_id.unwrap();
-                        let name: String = keyvaluepairs["vardas"].to_owned();
-                        let surname: String = keyvaluepairs["pavardė"].to_owned();
-                        let gender: Option<Gender> = if keyvaluepairs["lytis"] == "V" {
-                            Some(Gender::M)
-                        } else {
-                            Some(Gender::F)
+                        let id: i32 = parse_attribute(&keyvaluepairs, "SeimoNarys", "asmens_id", context)?;
+                        let parliament: i32 = require_parent(parliament_id, "SeimoNarys", "SeimoKadencija", context)?;
+                        let name: String = parse_attribute(&keyvaluepairs, "SeimoNarys", "vardas", context)?;
+                        let surname: String = parse_attribute(&keyvaluepairs, "SeimoNarys", "pavardė", context)?;
+                        let gender: Option<Gender> = match keyvaluepairs.get("lytis").map(|x| x.as_str()) {
+                            Some("V") => Some(Gender::M),
+                            Some(_) => Some(Gender::F),
+                            None => None,
                         };
-                        let from: Option<NaiveDate> = NaiveDate::parse_from_str(&keyvaluepairs["data_nuo"], "%Y-%m-%d").ok();
-                        let to: Option<NaiveDate> = NaiveDate::parse_from_str(&keyvaluepairs["data_iki"], "%Y-%m-%d").ok();
-                        let party: Option<String> = Some(keyvaluepairs["iškėlusi_partija"].to_owned());
-                        let elected_type: Option<String> = Some(keyvaluepairs["išrinkimo_būdas"].to_owned());
-                        let biography_link: Option<String> = keyvaluepairs.get("biografijos_nuoroda").and_then(|x| Some(x.to_owned()));
-                        let term_count: Option<i32> = keyvaluepairs["kadencijų_skaičius"].parse().ok();
+                        let from: Option<NaiveDate> = keyvaluepairs.get("data_nuo").and_then(|x| NaiveDate::parse_from_str(x, "%Y-%m-%d").ok());
+                        let to: Option<NaiveDate> = keyvaluepairs.get("data_iki").and_then(|x| NaiveDate::parse_from_str(x, "%Y-%m-%d").ok());
+                        let party: Option<String> = keyvaluepairs.get("iškėlusi_partija").map(|x| x.to_owned());
+                        let elected_type: Option<String> = keyvaluepairs.get("išrinkimo_būdas").map(|x| x.to_owned());
+                        let biography_link: Option<String> = keyvaluepairs.get("biografijos_nuoroda").map(|x| x.to_owned());
+                        let term_count: Option<i32> = keyvaluepairs.get("kadencijų_skaičius").and_then(|x| x.parse().ok());
 
                         politician = Some(Politician{
                             id,
@@ -59,44 +65,39 @@ fn parse_politicians<R: Read> (eventreader: EventReader<R>) {
                             email: None,
                             phone: vec![],
                             website: None,
-                            offices: vec![],
                         });
+                        offices = vec![];
                     },
                     "Pareigos" => {
                         let keyvaluepairs = parse_attributes(attributes);
-                        let (department_id, department_type) = if keyvaluepairs.contains_key("padalinio_id") {
-                            (keyvaluepairs["padalinio_id"].parse().ok(), Some(DepartmentType::Office))
-                        } else {
-                            (keyvaluepairs["parlamentinės_grupės_id"].parse().ok(),  Some(DepartmentType::Group))
-                        };
-                        
-                        let department_name = if keyvaluepairs.contains_key("padalinio_pavadinimas") {
-                            Some(keyvaluepairs["padalinio_pavadinimas"].to_owned())
+                        let (department_id, group_id, department_type) = if keyvaluepairs.contains_key("padalinio_id") {
+                            (keyvaluepairs["padalinio_id"].parse().ok(), None, Some(DepartmentType::Office))
                         } else {
-                            Some(keyvaluepairs["parlamentinės_grupės_pavadinimas"].to_owned())
+                            (None, keyvaluepairs.get("parlamentinės_grupės_id").and_then(|x| x.parse().ok()), Some(DepartmentType::Group))
                         };
 
-                        let duties = Some(keyvaluepairs["pareigos"].to_owned());
-                        let from: Option<NaiveDate> = NaiveDate::parse_from_str(&keyvaluepairs["data_nuo"], "%Y-%m-%d").ok();
-                        let to: Option<NaiveDate> = NaiveDate::parse_from_str(&keyvaluepairs["data_iki"], "%Y-%m-%d").ok();
+                        let department_name = keyvaluepairs.get("padalinio_pavadinimas")
+                            .or_else(|| keyvaluepairs.get("parlamentinės_grupės_pavadinimas"))
+                            .map(|x| x.to_owned());
+
+                        let duties = keyvaluepairs.get("pareigos").map(|x| x.to_owned());
+                        let from: Option<NaiveDate> = keyvaluepairs.get("data_nuo").and_then(|x| NaiveDate::parse_from_str(x, "%Y-%m-%d").ok());
+                        let to: Option<NaiveDate> = keyvaluepairs.get("data_iki").and_then(|x| NaiveDate::parse_from_str(x, "%Y-%m-%d").ok());
 
                         let office = OfficeInsertable {
                             department_id,
-                            department_name,
                             department_type,
                             duties,
                             from,
                             to,
+                            group_id,
                         };
-                        let office = office.save(conn).unwrap().unwrap();
-                        if let Some(unwrapped_politician) = &mut politician {
-                            unwrapped_politician.offices.push(Some(office.id));
-                        }
+                        offices.push((office, department_name.unwrap_or_default()));
                     }
                     "Kontaktai" => {
                         let keyvaluepairs = parse_attributes(attributes);
-                        let contact_type = keyvaluepairs["rūšis"].to_owned();
-                        let contact_value = keyvaluepairs["reikšmė"].to_owned();
+                        let contact_type = get_attribute(&keyvaluepairs, "Kontaktai", "rūšis", context)?.to_owned();
+                        let contact_value = get_attribute(&keyvaluepairs, "Kontaktai", "reikšmė", context)?.to_owned();
                         match contact_type.as_str() {
                             "El. p." => {
                                 if let Some(unwrapped_politician) = &mut politician {
@@ -120,7 +121,7 @@ fn parse_politicians<R: Read> (eventreader: EventReader<R>) {
                     }
                     "SeimoKadencija" => {
                         let keyvaluepairs = parse_attributes(attributes);
-                        parliament_id = keyvaluepairs["kadencijos_id"].parse().ok();
+                        parliament_id = Some(parse_attribute(&keyvaluepairs, "SeimoKadencija", "kadencijos_id", context)?);
                     }
                     _ => {
 
@@ -133,8 +134,10 @@ fn parse_politicians<R: Read> (eventreader: EventReader<R>) {
                         parliament_id = None;
                     },
                     "SeimoNarys" => {
-                        if let Some(unwrapped_politician) = &mut politician {
-                            unwrapped_politician.save(conn).unwrap();
+                        if let Some(unwrapped_politician) = &politician {
+                            let person_context = format!("{}, person {}", context, unwrapped_politician.id);
+                            conn.transaction(|conn| save_politician(conn, unwrapped_politician, &offices)).map_err(Error::database(&person_context))?;
+                            saved += 1;
                         }
                         politician = None;
                     },
@@ -144,10 +147,79 @@ fn parse_politicians<R: Read> (eventreader: EventReader<R>) {
                 }
             }
             Err(e) => {
-                println!("Error: {}", e);
-                break;
+                return Err(Error::Xml { context: context.to_owned(), source: e });
             }
             _ => {}
         }
     }
+    Ok(saved)
+}
+
+
+/// Writes a member together with the offices and faction stints listed with it, along with the
+/// departments and groups those offices belong to.
+fn save_politician(conn: &mut PgConnection, politician: &Politician, offices: &[(OfficeInsertable, String)]) -> std::result::Result<(), diesel::result::Error> {
+    politician.upsert(conn)?;
+
+    let mut factions: Vec<FactionMembership> = Vec::new();
+    for (office, name) in offices {
+        if let Some(group_id) = office.group_id {
+            ParliamentaryGroup { id: group_id, name: name.clone() }.upsert(conn)?;
+        }
+        if let Some(department_id) = office.department_id {
+            let kind = Department::ensure(conn, department_id, name, department_kind(name))?;
+            if kind == DepartmentKind::Faction {
+                Faction::ensure(conn, department_id, politician.parliament, name)?;
+                // A stint without a start date cannot be placed on the timeline.
+                if let Some(from) = office.from {
+                    factions.push(FactionMembership {
+                        politician_id: politician.id,
+                        parliament: politician.parliament,
+                        faction_id: department_id,
+                        from,
+                        to: office.to,
+                        duties: office.duties.clone(),
+                    });
+                }
+            }
+        }
+    }
+
+    let offices: Vec<&OfficeInsertable> = offices.iter().map(|(office, _)| office).collect();
+    PoliticianOffice::replace(conn, politician.id, politician.parliament, &offices)?;
+    FactionMembership::merge(conn, &factions)?;
+    Ok(())
+}
+
+
+/// Kind of a department seen in the offices of a member before its own endpoint is crawled. Factions are
+/// only told apart from other structural units by their name, e.g. `Liberalų sąjūdžio frakcija` or `Mišri Seimo narių grupė`.
+fn department_kind(name: &str) -> DepartmentKind {
+    let name = name.to_lowercase();
+    if name.contains("frakcij") || name.starts_with("mišri seimo narių grupė") {
+        DepartmentKind::Faction
+    } else {
+        DepartmentKind::Other
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tells_factions_by_their_name() {
+        assert_eq!(department_kind("Liberalų sąjūdžio frakcija"), DepartmentKind::Faction);
+        assert_eq!(department_kind("Tėvynės sąjungos-Lietuvos krikščionių demokratų frakcija"), DepartmentKind::Faction);
+        assert_eq!(department_kind("Mišri Seimo narių grupė"), DepartmentKind::Faction);
+        assert_eq!(department_kind("MIŠRI SEIMO NARIŲ GRUPĖ"), DepartmentKind::Faction);
+    }
+
+    #[test]
+    fn leaves_other_units_unclassified() {
+        assert_eq!(department_kind("Biudžeto ir finansų komitetas"), DepartmentKind::Other);
+        assert_eq!(department_kind("Etikos ir procedūrų komisija"), DepartmentKind::Other);
+        assert_eq!(department_kind(""), DepartmentKind::Other);
+    }
 }
@@ -1,38 +1,67 @@
-use std::error::Error;
 use std::io::Read;
 use chrono::NaiveDateTime;
 use log::{info, debug, error};
+use diesel::{Connection, PgConnection};
 use xml::reader::{EventReader, XmlEvent};
 
 use networking::request::*;
 
 
-use crate::database::connect::establish_connection;
-use crate::models::session::meeting_data::{AgendaItem, Vote, Speech, Registration, MeetingData};
-use crate::parser::util::parse_attributes;
+use crate::database::context::Context;
+use crate::models::session::meeting_data::{AgendaItem, Vote, Speech, Registration, MeetingData, MeetingAgendaItem, MeetingRegistration, AgendaVote, AgendaSpeech};
+use crate::models::upsert::Upsert;
+use crate::error::{Error, Result};
+use crate::parser::util::{parse_attributes, parse_attribute};
 use crate::networking;
 
 
+/// An agenda question with the votes and speeches held under it.
+#[derive(Debug)]
+struct AgendaTree {
+    item: AgendaItem,
+    votes: Vec<Vote>,
+    speeches: Vec<Speech>,
+}
+
+/// Everything the course of a meeting describes, kept in memory until it is written at once.
+#[derive(Debug)]
+struct MeetingTree {
+    meeting: MeetingData,
+    agenda: Vec<AgendaTree>,
+    registrations: Vec<Registration>,
+    /// Votes and speeches outside any agenda item, saved without an agenda link.
+    votes: Vec<Vote>,
+    speeches: Vec<Speech>,
+}
 
-pub async fn get_meeting_data(meeting_id: i32) -> Result<(), Box<dyn Error + Send + Sync>> {
+
+pub async fn get_meeting_data(ctx: Context, meeting_id: i32) -> Result<usize> {
     info!("Downloading meeting data: {}", meeting_id);    
     
     let link = format!("https://apps.lrs.lt/sip/p2b.ad_seimo_posedzio_eiga_full?posedzio_id={}", meeting_id);
     let xmlstring = url_request(&link).await?;
-    parse_meeting_data(EventReader::from_str(&xmlstring));
+    let context = format!("meeting {}", meeting_id);
+    let saved = ctx.run(move |conn| {
+        match parse_meeting_data(EventReader::from_str(&xmlstring), meeting_id, &context)? {
+            Some(tree) => save_meeting_tree(conn, tree, &context),
+            None => Ok(0),
+        }
+    }).await?;
     debug!("Done getting meeting data {}", meeting_id);
-    Ok(())
+    Ok(saved)
 }
 
-fn parse_meeting_data<R: Read> (eventreader: EventReader<R>) {
-    let conn = &mut establish_connection();
-    let mut meeting_id: Option<i32> = None;
+/// Reads the course of one meeting, which must be the one requested. Votes and speeches are
+/// attributed to the agenda item they are nested in.
+fn parse_meeting_data<R: Read> (eventreader: EventReader<R>, meeting_id: i32, context: &str) -> Result<Option<MeetingTree>> {
+    let mut tree: Option<MeetingTree> = None;
+    let mut agenda: Vec<AgendaTree> = Vec::new();
+    let mut registrations: Vec<Registration> = Vec::new();
+    let mut loose_votes: Vec<Vote> = Vec::new();
+    let mut loose_speeches: Vec<Speech> = Vec::new();
 
-    let mut agenda: Vec<Option<i32>> = Vec::new();
-    let mut registrations: Vec<Option<i32>> = Vec::new();
-
-    let mut speeches: Vec<Option<i32>> = Vec::new();
-    let mut voting: Vec<Option<i32>> = Vec::new();
+    let mut speeches: Vec<Speech> = Vec::new();
+    let mut voting: Vec<Vote> = Vec::new();
 
     let mut current_element: Option<String> = None;
     let mut current_parent_element: Option<String> = None;
@@ -200,20 +229,26 @@ fn parse_meeting_data<R: Read> (eventreader: EventReader<R>) {
             Ok(XmlEvent::StartElement { name, attributes, .. }) => {
                 match name.local_name.as_str() {
                     "posedis" => {
+                        if tree.is_some() || meeting_item.is_some() {
+                            return Err(Error::Other(format!("{} contains more than one <posedis>", context)));
+                        }
                         let keyvaluepairs = parse_attributes(attributes);
-                        meeting_id = keyvaluepairs["pos_id"].parse().ok();
+                        let id: i32 = parse_attribute(&keyvaluepairs, "posedis", "pos_id", context)?;
+                        if id != meeting_id {
+                            return Err(Error::Other(format!("{} returned the course of meeting {}", context, id)));
+                        }
                         meeting_item = Some(MeetingData {
-                            id: meeting_id.unwrap(),
+                            id,
                             from: None,
                             to: None,
-                            agenda: vec![],
-                            registrations: vec![],
                         });
                         current_parent_element = Some("meeting_item".to_owned());
                     },
                     "darbotvarkes-klausimas" => {
+                        loose_votes.append(&mut voting);
+                        loose_speeches.append(&mut speeches);
                         let keyvaluepairs = parse_attributes(attributes);
-                        let id: i32 = keyvaluepairs["svarst_kl_stad_id"].parse().unwrap();
+                        let id: i32 = parse_attribute(&keyvaluepairs, "darbotvarkes-klausimas", "svarst_kl_stad_id", context)?;
                         let agenda_state_id: Option<i32> = keyvaluepairs
                             .get("kl_stad_id").and_then(|x| x.parse().ok());
                         let agenda_group_id: Option<i32> = keyvaluepairs
@@ -231,15 +266,12 @@ fn parse_meeting_data<R: Read> (eventreader: EventReader<R>) {
                             agenda_type:None,
                             from: None,
                             to: None,
-                            speeches: vec![],
-                            voting: vec![],
                         });
                         current_parent_element = Some("agenda_item".to_owned());
-                        agenda.push(Some(id));
                     },
                     "balsavimas" => {
                         let keyvaluepairs = parse_attributes(attributes);
-                        let id: i32 = keyvaluepairs["bals_id"].parse().unwrap();
+                        let id: i32 = parse_attribute(&keyvaluepairs, "balsavimas", "bals_id", context)?;
                         vote_item = Some(Vote {
                             id,
                             summary: None,
@@ -248,18 +280,15 @@ fn parse_meeting_data<R: Read> (eventreader: EventReader<R>) {
                             to: None,
                         });
                         current_parent_element = Some("vote_item".to_owned());
-                        voting.push(Some(id));
                     },
                     "kalbetojas" => {
                         let keyvaluepairs = parse_attributes(attributes);
-                        let id: i32 = keyvaluepairs["klb_id"].parse().unwrap();
-                        let discussion_id: Option<i32> = keyvaluepairs["diskus_id"].parse().ok();
-
-                        let person_id: Option<i32> = if keyvaluepairs.contains_key("asm_id") {
-                            keyvaluepairs["asm_id"].parse().ok()
-                        } else {
-                            keyvaluepairs["pran_id"].parse().ok()
-                        };
+                        let id: i32 = parse_attribute(&keyvaluepairs, "kalbetojas", "klb_id", context)?;
+                        let discussion_id: Option<i32> = keyvaluepairs
+                            .get("diskus_id").and_then(|x| x.parse().ok());
+
+                        let person_id: Option<i32> = keyvaluepairs
+                            .get("asm_id").or_else(|| keyvaluepairs.get("pran_id")).and_then(|x| x.parse().ok());
                         
                         speech_item = Some(Speech {
                             id,
@@ -271,11 +300,10 @@ fn parse_meeting_data<R: Read> (eventreader: EventReader<R>) {
                             to: None,
                         });
                         current_parent_element = Some("speech_item".to_owned());
-                        speeches.push(Some(id));
                     },
                     "registracija" => {
                         let keyvaluepairs = parse_attributes(attributes);
-                        let id: i32 = keyvaluepairs["reg_id"].parse().unwrap();
+                        let id: i32 = parse_attribute(&keyvaluepairs, "registracija", "reg_id", context)?;
 
                         registration_item = Some(Registration {
                             id,
@@ -284,7 +312,6 @@ fn parse_meeting_data<R: Read> (eventreader: EventReader<R>) {
                             to: None,
                         });
                         current_parent_element = Some("registration_item".to_owned());
-                        registrations.push(Some(id));
                     },
                     _ => {
 
@@ -295,37 +322,43 @@ fn parse_meeting_data<R: Read> (eventreader: EventReader<R>) {
             Ok(XmlEvent::EndElement { name }) => {
                 match name.local_name.as_str() {
                     "posedis" => {
-                        if let Some(unwrapped_item) = &mut meeting_item {
-                            unwrapped_item.agenda = agenda;
-                            unwrapped_item.registrations = registrations;
-                            unwrapped_item.save(conn).unwrap();
+                        if let Some(meeting) = meeting_item.take() {
+                            loose_votes.append(&mut voting);
+                            loose_speeches.append(&mut speeches);
+                            if !loose_votes.is_empty() || !loose_speeches.is_empty() {
+                                debug!("{}: {} vote(s) and {} speech(es) outside any agenda item", context, loose_votes.len(), loose_speeches.len());
+                            }
+                            tree = Some(MeetingTree {
+                                meeting,
+                                agenda: std::mem::take(&mut agenda),
+                                registrations: std::mem::take(&mut registrations),
+                                votes: std::mem::take(&mut loose_votes),
+                                speeches: std::mem::take(&mut loose_speeches),
+                            });
                         }
-                        
-                        registrations = vec![];
-                        agenda = vec![];
                     },
                     "darbotvarkes-klausimas" => {
-                        if let Some(unwrapped_item) = &mut agenda_item {
-                            unwrapped_item.speeches = speeches;
-                            unwrapped_item.voting = voting;
-                            unwrapped_item.save(conn).unwrap();
+                        if let Some(item) = agenda_item.take() {
+                            agenda.push(AgendaTree {
+                                item,
+                                votes: std::mem::take(&mut voting),
+                                speeches: std::mem::take(&mut speeches),
+                            });
                         }
-                        speeches = vec![];
-                        voting = vec![];
                     },
                     "balsavimas" => {
-                        if let Some(unwrapped_item) = &mut vote_item {
-                            unwrapped_item.save(conn).unwrap();
+                        if let Some(item) = vote_item.take() {
+                            voting.push(item);
                         }
                     },
                     "kalbetojas" => {
-                        if let Some(unwrapped_item) = &mut speech_item {
-                            unwrapped_item.save(conn).unwrap();
+                        if let Some(item) = speech_item.take() {
+                            speeches.push(item);
                         }
                     },
                     "registracija" => {
-                        if let Some(unwrapped_item) = &mut registration_item {
-                            unwrapped_item.save(conn).unwrap();
+                        if let Some(item) = registration_item.take() {
+                            registrations.push(item);
                         }
                     },
                     _ => {
@@ -335,10 +368,113 @@ fn parse_meeting_data<R: Read> (eventreader: EventReader<R>) {
                 current_element = None;
             }
             Err(e) => {
-                error!("{:?}", e);
-                break;
+                return Err(Error::Xml { context: context.to_owned(), source: e });
             }
             _ => {}
         }
     }
+    Ok(tree)
+}
+
+/// Writes a meeting with all its agenda items, votes, speeches and registrations in one transaction.
+fn save_meeting_tree(conn: &mut PgConnection, tree: MeetingTree, context: &str) -> Result<usize> {
+    let meeting_id = tree.meeting.id;
+    let agenda_ids: Vec<i32> = tree.agenda.iter().map(|agenda| agenda.item.id).collect();
+    let registration_ids: Vec<i32> = tree.registrations.iter().map(|registration| registration.id).collect();
+    let mut items = Vec::with_capacity(tree.agenda.len());
+    let mut votes = tree.votes;
+    let mut speeches = tree.speeches;
+    let mut links = Vec::with_capacity(tree.agenda.len());
+    for agenda in tree.agenda {
+        links.push((
+            agenda.item.id,
+            agenda.votes.iter().map(|vote| vote.id).collect::<Vec<i32>>(),
+            agenda.speeches.iter().map(|speech| speech.id).collect::<Vec<i32>>(),
+        ));
+        items.push(agenda.item);
+        votes.extend(agenda.votes);
+        speeches.extend(agenda.speeches);
+    }
+
+    conn.transaction(|conn| {
+        tree.meeting.upsert(conn)?;
+        AgendaItem::upsert_batch(conn, &items)?;
+        Vote::upsert_batch(conn, &votes)?;
+        Speech::upsert_batch(conn, &speeches)?;
+        Registration::upsert_batch(conn, &tree.registrations)?;
+
+        MeetingAgendaItem::replace(conn, meeting_id, &agenda_ids)?;
+        MeetingRegistration::replace(conn, meeting_id, &registration_ids)?;
+        for (agenda_id, vote_ids, speech_ids) in &links {
+            AgendaVote::replace(conn, *agenda_id, vote_ids)?;
+            AgendaSpeech::replace(conn, *agenda_id, speech_ids)?;
+        }
+        Ok::<_, diesel::result::Error>(())
+    }).map_err(Error::database(context))?;
+    Ok(1)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(xml: &str, meeting_id: i32) -> Result<Option<MeetingTree>> {
+        parse_meeting_data(EventReader::from_str(xml), meeting_id, "meeting")
+    }
+
+    #[test]
+    fn keeps_agenda_items_votes_and_speeches_in_order() {
+        let xml = r#"<?xml version="1.0"?><posedis pos_id="1"><pradzia>2008-11-17 10:00:00</pradzia>
+            <darbotvarkes-klausimas svarst_kl_stad_id="12" dok_key="5"><nr>1.</nr><pavadinimas>A</pavadinimas>
+            <kalbetojas klb_id="22" asm_id="7"><asmuo>X</asmuo></kalbetojas><kalbetojas klb_id="21" pran_id="8"><asmuo>Y</asmuo></kalbetojas>
+            <balsavimas bals_id="32"><aprasas>v</aprasas></balsavimas><balsavimas bals_id="31"></balsavimas></darbotvarkes-klausimas>
+            <darbotvarkes-klausimas svarst_kl_stad_id="11"><nr>2.</nr><registracija reg_id="41"><antraste>r</antraste></registracija></darbotvarkes-klausimas>
+            </posedis>"#;
+        let tree = parse(xml, 1).unwrap().unwrap();
+        assert_eq!(tree.meeting.id, 1);
+        assert_eq!(tree.meeting.from.map(|from| from.to_string()).as_deref(), Some("2008-11-17 10:00:00"));
+
+        let agenda: Vec<i32> = tree.agenda.iter().map(|agenda| agenda.item.id).collect();
+        assert_eq!(agenda, vec![12, 11]);
+        assert_eq!(tree.agenda[0].item.nr.as_deref(), Some("1."));
+        assert_eq!(tree.agenda[0].item.document_key, Some(5));
+        assert_eq!(tree.agenda[0].votes.iter().map(|vote| vote.id).collect::<Vec<_>>(), vec![32, 31]);
+        assert_eq!(tree.agenda[0].votes[0].summary.as_deref(), Some("v"));
+        assert_eq!(tree.agenda[0].speeches.iter().map(|speech| (speech.id, speech.person_id)).collect::<Vec<_>>(), vec![(22, Some(7)), (21, Some(8))]);
+        assert!(tree.agenda[1].votes.is_empty() && tree.agenda[1].speeches.is_empty());
+        assert_eq!(tree.registrations.iter().map(|registration| registration.id).collect::<Vec<_>>(), vec![41]);
+        assert!(tree.votes.is_empty() && tree.speeches.is_empty());
+    }
+
+    #[test]
+    fn keeps_votes_and_speeches_outside_agenda_items() {
+        let xml = r#"<posedis pos_id="1">
+            <balsavimas bals_id="30"></balsavimas>
+            <darbotvarkes-klausimas svarst_kl_stad_id="11"><balsavimas bals_id="31"></balsavimas></darbotvarkes-klausimas>
+            <kalbetojas klb_id="20"></kalbetojas><balsavimas bals_id="32"></balsavimas>
+            </posedis>"#;
+        let tree = parse(xml, 1).unwrap().unwrap();
+        assert_eq!(tree.agenda.len(), 1);
+        assert_eq!(tree.agenda[0].votes.iter().map(|vote| vote.id).collect::<Vec<_>>(), vec![31]);
+        assert_eq!(tree.votes.iter().map(|vote| vote.id).collect::<Vec<_>>(), vec![30, 32]);
+        assert_eq!(tree.speeches.iter().map(|speech| speech.id).collect::<Vec<_>>(), vec![20]);
+    }
+
+    #[test]
+    fn rejects_the_course_of_another_meeting() {
+        let error = parse(r#"<posedis pos_id="2"></posedis>"#, 1).unwrap_err();
+        assert_eq!(error.to_string(), "meeting returned the course of meeting 2");
+    }
+
+    #[test]
+    fn rejects_more_than_one_meeting() {
+        let error = parse(r#"<eiga><posedis pos_id="1"></posedis><posedis pos_id="1"></posedis></eiga>"#, 1).unwrap_err();
+        assert_eq!(error.to_string(), "meeting contains more than one <posedis>");
+    }
+
+    #[test]
+    fn returns_nothing_without_a_meeting() {
+        assert!(parse(r#"<eiga></eiga>"#, 1).unwrap().is_none());
+    }
 }
@@ -1,32 +1,35 @@
-use std::error::Error;
 use std::io::Read;
 use chrono::NaiveDateTime;
 use log::info;
+use diesel::PgConnection;
 use xml::reader::{EventReader, XmlEvent};
 
 use networking::request::*;
 use models::session::meetings::Meetings;
+use models::upsert::Upsert;
 
-use crate::database::connect::establish_connection;
-use crate::parser::util::parse_attributes;
+use crate::database::context::Context;
+use crate::error::{Error, Result};
+use crate::parser::util::{parse_attributes, parse_attribute, require_parent};
 
 use crate::{networking, models};
 
 
 
-pub async fn get_meetings(session_id: i32) -> Result<(), Box<dyn Error + Send + Sync>> {
+pub async fn get_meetings(ctx: Context, session_id: i32) -> Result<usize> {
     info!("Downloading meetings: {}", session_id);
     
     let link = format!("https://apps.lrs.lt/sip/p2b.ad_seimo_posedziai?sesijos_id={}", session_id);
     let xmlstring = url_request(&link).await?;
-    parse_meetings(EventReader::from_str(&xmlstring));
-    Ok(())
+    let context = format!("meetings of session {}", session_id);
+    let saved = ctx.run(move |conn| parse_meetings(conn, EventReader::from_str(&xmlstring), &context)).await?;
+    Ok(saved)
 }
 
 
 
-fn parse_meetings<R: Read> (eventreader: EventReader<R>) {
-    let conn = &mut establish_connection();
+fn parse_meetings<R: Read> (conn: &mut PgConnection, eventreader: EventReader<R>, context: &str) -> Result<usize> {
+    let mut saved = 0;
     let mut session_id: Option<i32> = None;
     let mut meeting: Option<Meetings> = None;
     for e in eventreader {
@@ -35,19 +38,19 @@ fn parse_meetings<R: Read> (eventreader: EventReader<R>) {
                 match name.local_name.as_str() {
                     "SeimoPosėdis" => {
                         let keyvaluepairs = parse_attributes(attributes);
-                        let meeting_id: i32 = keyvaluepairs["posėdžio_id"].parse().unwrap();
-                        let meeting_num: i32 = keyvaluepairs["numeris"].parse().unwrap();
-                        let meeting_type: String = keyvaluepairs["tipas"].to_owned();
-                        let meeting_from: Option<NaiveDateTime> = NaiveDateTime::parse_from_str(&keyvaluepairs["pradžia"], "%Y-%m-%d %H:%M").ok();
-                        let meeting_to: Option<NaiveDateTime> = NaiveDateTime::parse_from_str(&keyvaluepairs["pabaiga"], "%Y-%m-%d %H:%M").ok();
+                        let meeting_id: i32 = parse_attribute(&keyvaluepairs, "SeimoPosėdis", "posėdžio_id", context)?;
+                        let meeting_num: i32 = parse_attribute(&keyvaluepairs, "SeimoPosėdis", "numeris", context)?;
+                        let meeting_type: String = parse_attribute(&keyvaluepairs, "SeimoPosėdis", "tipas", context)?;
+                        let meeting_from: Option<NaiveDateTime> = keyvaluepairs.get("pradžia").and_then(|x| NaiveDateTime::parse_from_str(x, "%Y-%m-%d %H:%M").ok());
+                        let meeting_to: Option<NaiveDateTime> = keyvaluepairs.get("pabaiga").and_then(|x| NaiveDateTime::parse_from_str(x, "%Y-%m-%d %H:%M").ok());
 
                         meeting = Some(Meetings{ 
                             id: meeting_id,
                             num: meeting_num,
-                            meeting_type: meeting_type,
+                            meeting_type,
                             from: meeting_from,
                             to: meeting_to,
-                            session: session_id.unwrap(),
+                            session: require_parent(session_id, "SeimoPosėdis", "SeimoSesija", context)?,
                             protocol_link: None,
                             stenogram_link: None,
                             video_comment: None,
@@ -56,25 +59,25 @@ fn parse_meetings<R: Read> (eventreader: EventReader<R>) {
                     },
                     "SeimoSesija" => {
                         let keyvaluepairs = parse_attributes(attributes);
-                        session_id = keyvaluepairs["sesijos_id"].parse().ok();
+                        session_id = Some(parse_attribute(&keyvaluepairs, "SeimoSesija", "sesijos_id", context)?);
                     }
                     "Protokolas" => {
                         let keyvaluepairs = parse_attributes(attributes);
                         if let Some(unwrapped_meeting) = &mut meeting {
-                            unwrapped_meeting.protocol_link = keyvaluepairs["protokolo_nuoroda"].parse().ok();
+                            unwrapped_meeting.protocol_link = keyvaluepairs.get("protokolo_nuoroda").map(|x| x.to_owned());
                         }
                     }
                     "Stenograma" => {
                         let keyvaluepairs = parse_attributes(attributes);
                         if let Some(unwrapped_meeting) = &mut meeting {
-                            unwrapped_meeting.stenogram_link = keyvaluepairs["stenogramos_nuoroda"].parse().ok();
+                            unwrapped_meeting.stenogram_link = keyvaluepairs.get("stenogramos_nuoroda").map(|x| x.to_owned());
                         }
                     }
                     "VaizdoĮrašas" => {
                         let keyvaluepairs = parse_attributes(attributes);
                         if let Some(unwrapped_meeting) = &mut meeting {
-                            unwrapped_meeting.video_comment = keyvaluepairs["komentaras"].parse().ok();
-                            unwrapped_meeting.video_link = keyvaluepairs["vaizdo_įrašo_nuoroda"].parse().ok();
+                            unwrapped_meeting.video_comment = keyvaluepairs.get("komentaras").map(|x| x.to_owned());
+                            unwrapped_meeting.video_link = keyvaluepairs.get("vaizdo_įrašo_nuoroda").map(|x| x.to_owned());
                         }
                     }
                     _ => {
@@ -89,7 +92,8 @@ fn parse_meetings<R: Read> (eventreader: EventReader<R>) {
                     },
                     "SeimoPosėdis" => {
                         if let Some(unwrapped_meeting) = &mut meeting {
-                            unwrapped_meeting.save(conn).unwrap();
+                            unwrapped_meeting.upsert(conn).map_err(Error::database(&format!("meeting {}", unwrapped_meeting.id)))?;
+                            saved += 1;
                         }
                         meeting = None;
                     },
@@ -99,10 +103,10 @@ fn parse_meetings<R: Read> (eventreader: EventReader<R>) {
                 }
             }
             Err(e) => {
-                println!("Error: {}", e);
-                break;
+                return Err(Error::Xml { context: context.to_owned(), source: e });
             }
             _ => {}
         }
     }
+    Ok(saved)
 }
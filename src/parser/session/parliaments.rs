@@ -1,64 +1,55 @@
-use std::error::Error;
 use std::io::Read;
 use chrono::NaiveDate;
-use log::{error, info};
+use log::info;
+use diesel::PgConnection;
 use xml::reader::{EventReader, XmlEvent};
 
 use networking::request::*;
 use models::session::{parliament::Parliament};
+use models::upsert::Upsert;
 
-use crate::database::connect::establish_connection;
-use crate::parser::util::parse_attributes;
+use crate::database::context::Context;
+use crate::error::{Error, Result};
+use crate::parser::util::{parse_attributes, parse_attribute};
 use crate::{networking, models};
 
 
 
-pub async fn get_parliaments() -> Result<(), Box<dyn Error + Send + Sync>> {
+pub async fn get_parliaments(ctx: Context) -> Result<usize> {
     info!("Downloading parliaments");
-    let link = format!("http://apps.lrs.lt/sip/p2b.ad_seimo_kadencijos");
+    let link = "http://apps.lrs.lt/sip/p2b.ad_seimo_kadencijos";
 
-    let xmlstring = url_request(&link).await?;
-    parse_parliaments(EventReader::from_str(&xmlstring));
-    Ok(())
+    let xmlstring = url_request(link).await?;
+    let saved = ctx.run(move |conn| parse_parliaments(conn, EventReader::from_str(&xmlstring))).await?;
+    Ok(saved)
 }
 
-fn parse_parliaments<R: Read> (eventreader: EventReader<R>) {
-    let conn = &mut establish_connection();
+fn parse_parliaments<R: Read> (conn: &mut PgConnection, eventreader: EventReader<R>) -> Result<usize> {
+    let mut saved = 0;
+    let context = "parliament list";
     for e in eventreader {
         match e {
-            Ok(XmlEvent::StartElement { name, attributes, .. }) => {
-                match name.local_name.as_str() {
-                    "SeimoKadencija" => {
-                        let keyvaluepairs = parse_attributes(attributes);
-                        let id: i32 = keyvaluepairs["kadencijos_id"].parse().unwrap();
-                        let name: Option<String> = keyvaluepairs["pavadinimas"].parse().ok();
-                        let from: Option<NaiveDate> = NaiveDate::parse_from_str(&keyvaluepairs["data_nuo"], "%Y-%m-%d").ok();
-                        let to: Option<NaiveDate> = NaiveDate::parse_from_str(&keyvaluepairs["data_iki"], "%Y-%m-%d").ok();
-
-                        let parliament = Parliament {
-                            id,
-                            name,
-                            from,
-                            to,
-                        };
-                        parliament.save(conn).unwrap();
-                    },
-                    _ => {
-
-                    }
-                }
-            }
-            Ok(XmlEvent::EndElement { name }) => {
-                match name.local_name.as_str() {
-                    _ => {
-
-                    }
-                }
+            Ok(XmlEvent::StartElement { name, attributes, .. }) if name.local_name == "SeimoKadencija" => {
+                let keyvaluepairs = parse_attributes(attributes);
+                let id: i32 = parse_attribute(&keyvaluepairs, "SeimoKadencija", "kadencijos_id", context)?;
+                let name: Option<String> = keyvaluepairs.get("pavadinimas").map(|x| x.to_owned());
+                let from: Option<NaiveDate> = keyvaluepairs.get("data_nuo").and_then(|x| NaiveDate::parse_from_str(x, "%Y-%m-%d").ok());
+                let to: Option<NaiveDate> = keyvaluepairs.get("data_iki").and_then(|x| NaiveDate::parse_from_str(x, "%Y-%m-%d").ok());
+
+                let parliament = Parliament {
+                    id,
+                    name,
+                    from,
+                    to,
+                };
+                parliament.upsert(conn).map_err(Error::database(&format!("parliament {}", id)))?;
+                saved += 1;
             }
             Err(e) => {
-                error!("{:?}", e);
+                return Err(Error::Xml { context: context.to_owned(), source: e });
             }
             _ => {}
         }
     }
+    Ok(saved)
 }
@@ -1,31 +1,34 @@
-use std::error::Error;
 use std::io::Read;
-use log::info;
+use log::{debug, info};
+use diesel::PgConnection;
 use xml::reader::{EventReader, XmlEvent};
 
 use networking::request::*;
 
-use crate::database::connect::establish_connection;
+use crate::database::context::Context;
 use crate::models::session::registration_data::RegistrationData;
-use crate::parser::util::parse_attributes;
+use crate::models::upsert::Upsert;
+use crate::error::{Error, Result};
+use crate::parser::util::{parse_attributes, parse_attribute, require_parent};
 
 use crate::networking;
 
 
 
-pub async fn get_registration_data(registration_id: i32) -> Result<(), Box<dyn Error + Send + Sync>> {
+pub async fn get_registration_data(ctx: Context, registration_id: i32) -> Result<usize> {
     info!("Downloading registration data: {}", registration_id);
     
     let link = format!("https://apps.lrs.lt/sip/p2b.ad_sp_registracijos_rezultatai?registracijos_id={}", registration_id);
     let xmlstring = url_request(&link).await?;
-    parse_registration_data(EventReader::from_str(&xmlstring));
-    Ok(())
+    let context = format!("registration {}", registration_id);
+    let saved = ctx.run(move |conn| parse_registration_data(conn, EventReader::from_str(&xmlstring), &context)).await?;
+    Ok(saved)
 }
 
 
 
-fn parse_registration_data<R: Read> (eventreader: EventReader<R>) {
-    let conn = &mut establish_connection();
+fn parse_registration_data<R: Read> (conn: &mut PgConnection, eventreader: EventReader<R>, context: &str) -> Result<usize> {
+    let mut rows: Vec<RegistrationData> = Vec::new();
     let mut registration_id: Option<i32> = None;
 
     for e in eventreader {
@@ -34,16 +37,16 @@ fn parse_registration_data<R: Read> (eventreader: EventReader<R>) {
                 match name.local_name.as_str() {
                     "SeimoNariųRegistracija" => {
                         let keyvaluepairs = parse_attributes(attributes);
-                        registration_id = keyvaluepairs["registracijos_id"].parse().ok();
+                        registration_id = Some(parse_attribute(&keyvaluepairs, "SeimoNariųRegistracija", "registracijos_id", context)?);
                     }
                     "IndividualusRegistracijosRezultatas" => {
                         let keyvaluepairs = parse_attributes(attributes);
-                        let person_id: i32 = keyvaluepairs["asmens_id"].parse().unwrap();
-                        let registered: Option<bool> = match keyvaluepairs["ar_registravosi"].as_str() {
-                            "Ne" => {
+                        let person_id: i32 = parse_attribute(&keyvaluepairs, "IndividualusRegistracijosRezultatas", "asmens_id", context)?;
+                        let registered: Option<bool> = match keyvaluepairs.get("ar_registravosi").map(|x| x.as_str()) {
+                            Some("Ne") => {
                                 Some(false)
                             },
-                            "Taip" => {
+                            Some("Taip") => {
                                 Some(true)
                             },
                             _ => {
@@ -52,32 +55,29 @@ fn parse_registration_data<R: Read> (eventreader: EventReader<R>) {
                         };
 
                         let registration_data = RegistrationData{ 
-                            id: registration_id.unwrap(),
-                            person_id: person_id,
-                            registered: registered,
+                            id: require_parent(registration_id, "IndividualusRegistracijosRezultatas", "SeimoNariųRegistracija", context)?,
+                            person_id,
+                            registered,
                         };
-                        registration_data.save(conn).unwrap();
+                        rows.push(registration_data);
                     }
                     _ => {
 
                     }
                 }
             }
-            Ok(XmlEvent::EndElement { name }) => {
-                match name.local_name.as_str() {
-                    "SeimoNariųRegistracija" => {
-                        registration_id = None;
-                    },
-                    _ => {
-
-                    }
-                }
+            Ok(XmlEvent::EndElement { name }) if name.local_name == "SeimoNariųRegistracija" => {
+                registration_id = None;
             }
             Err(e) => {
-                println!("Error: {}", e);
-                break;
+                return Err(Error::Xml { context: context.to_owned(), source: e });
             }
             _ => {}
         }
     }
+
+    // Written only once the whole document parsed, in one transaction, so a registration is stored entirely or not at all.
+    let summary = RegistrationData::upsert_batch(conn, &rows).map_err(Error::database(context))?;
+    debug!("{}: {} inserted, {} updated, {} unchanged", context, summary.inserted, summary.updated, summary.unchanged);
+    Ok(summary.total())
 }
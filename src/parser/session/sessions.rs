@@ -1,31 +1,34 @@
-use std::error::Error;
 use std::io::Read;
 use chrono::NaiveDate;
-use log::{error, info};
+use log::info;
+use diesel::PgConnection;
 use xml::reader::{EventReader, XmlEvent};
 
 use networking::request::*;
 use models::session::sessions::Sessions;
+use models::upsert::Upsert;
 
-use crate::database::connect::establish_connection;
-use crate::parser::util::parse_attributes;
+use crate::database::context::Context;
+use crate::error::{Error, Result};
+use crate::parser::util::{parse_attributes, parse_attribute, require_parent};
 use crate::{networking, models};
 
 
 
-pub async fn get_sessions(parliament_id: i32) -> Result<(), Box<dyn Error + Send + Sync>> {
+pub async fn get_sessions(ctx: Context, parliament_id: i32) -> Result<usize> {
     info!("Downloading sessions: {}", parliament_id);
     
     let link = format!("https://apps.lrs.lt/sip/p2b.ad_seimo_sesijos?kadencijos_id={}", parliament_id);
 
     let xmlstring = url_request(&link).await?;
-    parse_sessions(EventReader::from_str(&xmlstring));
-    Ok(())
+    let context = format!("sessions of parliament {}", parliament_id);
+    let saved = ctx.run(move |conn| parse_sessions(conn, EventReader::from_str(&xmlstring), &context)).await?;
+    Ok(saved)
 }
 
 
-fn parse_sessions<R: Read> (eventreader: EventReader<R>) {
-    let conn = &mut establish_connection();
+fn parse_sessions<R: Read> (conn: &mut PgConnection, eventreader: EventReader<R>, context: &str) -> Result<usize> {
+    let mut saved = 0;
     let mut parliament_id: Option<i32> = None;
 
     for e in eventreader {
@@ -34,15 +37,15 @@ fn parse_sessions<R: Read> (eventreader: EventReader<R>) {
                 match name.local_name.as_str() {
                     "SeimoKadencija" => {
                         let keyvaluepairs = parse_attributes(attributes);
-                        parliament_id = keyvaluepairs["kadencijos_id"].parse().ok();
+                        parliament_id = Some(parse_attribute(&keyvaluepairs, "SeimoKadencija", "kadencijos_id", context)?);
                     },
                     "SeimoSesija" => {
                         let keyvaluepairs = parse_attributes(attributes);
-                        let session_id: i32 = keyvaluepairs["sesijos_id"].parse().unwrap();
-                        let session_num: i32 = keyvaluepairs["numeris"].parse().unwrap();
-                        let session_name: String = keyvaluepairs["pavadinimas"].to_owned();
-                        let session_from: Option<NaiveDate> = NaiveDate::parse_from_str(&keyvaluepairs["data_nuo"], "%Y-%m-%d").ok();
-                        let session_to: Option<NaiveDate> = NaiveDate::parse_from_str(&keyvaluepairs["data_iki"], "%Y-%m-%d").ok();
+                        let session_id: i32 = parse_attribute(&keyvaluepairs, "SeimoSesija", "sesijos_id", context)?;
+                        let session_num: i32 = parse_attribute(&keyvaluepairs, "SeimoSesija", "numeris", context)?;
+                        let session_name: String = parse_attribute(&keyvaluepairs, "SeimoSesija", "pavadinimas", context)?;
+                        let session_from: Option<NaiveDate> = keyvaluepairs.get("data_nuo").and_then(|x| NaiveDate::parse_from_str(x, "%Y-%m-%d").ok());
+                        let session_to: Option<NaiveDate> = keyvaluepairs.get("data_iki").and_then(|x| NaiveDate::parse_from_str(x, "%Y-%m-%d").ok());
                         
                         let session = Sessions {
                             id: session_id,
@@ -50,30 +53,25 @@ fn parse_sessions<R: Read> (eventreader: EventReader<R>) {
                             name: session_name,
                             from: session_from,
                             to: session_to,
-                            parliament: parliament_id.unwrap(),
+                            parliament: require_parent(parliament_id, "SeimoSesija", "SeimoKadencija", context)?,
                         };
                         
-                        session.save(conn).unwrap();
+                        session.upsert(conn).map_err(Error::database(&format!("session {}", session_id)))?;
+                        saved += 1;
                     }
                     _ => {
 
                     }
                 }
             }
-            Ok(XmlEvent::EndElement { name }) => {
-                match name.local_name.as_str() {
-                    "SeimoKadencija" => {
-                        parliament_id = None;
-                    },
-                    _ => {
-
-                    }
-                }
+            Ok(XmlEvent::EndElement { name }) if name.local_name == "SeimoKadencija" => {
+                parliament_id = None;
             }
             Err(e) => {
-                error!("{:?}", e);
+                return Err(Error::Xml { context: context.to_owned(), source: e });
             }
             _ => {}
         }
     }
+    Ok(saved)
 }
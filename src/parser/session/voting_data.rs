@@ -1,31 +1,34 @@
-use std::error::Error;
 use std::io::Read;
-use log::{error, info};
+use log::{debug, info};
+use diesel::PgConnection;
 use xml::reader::{EventReader, XmlEvent};
 
 use networking::request::*;
 
-use crate::database::connect::establish_connection;
+use crate::database::context::Context;
 use crate::models::session::voting_data::{VoteData, VoteType};
-use crate::parser::util::parse_attributes;
+use crate::models::upsert::Upsert;
+use crate::error::{Error, Result};
+use crate::parser::util::{parse_attributes, parse_attribute, require_parent};
 
 use crate::networking;
 
 
 
-pub async fn get_voting_data(voting_id: i32) -> Result<(), Box<dyn Error + Send + Sync>> {
+pub async fn get_voting_data(ctx: Context, voting_id: i32) -> Result<usize> {
     info!("Downloading voting data: {}", voting_id);
     
     let link = format!("https://apps.lrs.lt/sip/p2b.ad_sp_balsavimo_rezultatai?balsavimo_id={}", voting_id);
     let xmlstring = url_request(&link).await?;
-    parse_voting_data(EventReader::from_str(&xmlstring));
-    Ok(())
+    let context = format!("vote {}", voting_id);
+    let saved = ctx.run(move |conn| parse_voting_data(conn, EventReader::from_str(&xmlstring), &context)).await?;
+    Ok(saved)
 }
 
 
 
-fn parse_voting_data<R: Read> (eventreader: EventReader<R>) {
-    let conn = &mut establish_connection();
+fn parse_voting_data<R: Read> (conn: &mut PgConnection, eventreader: EventReader<R>, context: &str) -> Result<usize> {
+    let mut rows: Vec<VoteData> = Vec::new();
     let mut voting_id: Option<i32> = None;
 
     for e in eventreader {
@@ -34,19 +37,19 @@ fn parse_voting_data<R: Read> (eventreader: EventReader<R>) {
                 match name.local_name.as_str() {
                     "SeimoNariųBalsavimas" => {
                         let keyvaluepairs = parse_attributes(attributes);
-                        voting_id = keyvaluepairs["balsavimo_id"].parse().ok();
+                        voting_id = Some(parse_attribute(&keyvaluepairs, "SeimoNariųBalsavimas", "balsavimo_id", context)?);
                     }
                     "IndividualusBalsavimoRezultatas" => {
                         let keyvaluepairs = parse_attributes(attributes);
-                        let person_id: i32 = keyvaluepairs["asmens_id"].parse().unwrap();
-                        let vote: Option<VoteType> = match keyvaluepairs["kaip_balsavo"].as_str() {
-                            "Už" => {
+                        let person_id: i32 = parse_attribute(&keyvaluepairs, "IndividualusBalsavimoRezultatas", "asmens_id", context)?;
+                        let vote: Option<VoteType> = match keyvaluepairs.get("kaip_balsavo").map(|x| x.as_str()) {
+                            Some("Už") => {
                                 Some(VoteType::For)
                             },
-                            "Prieš" => {
+                            Some("Prieš") => {
                                 Some(VoteType::Against)
                             },
-                            "Susilaikė" => {
+                            Some("Susilaikė") => {
                                 Some(VoteType::Abstain)
                             },
                             _ => {
@@ -55,32 +58,29 @@ fn parse_voting_data<R: Read> (eventreader: EventReader<R>) {
                         };
 
                         let voting_data = VoteData{ 
-                            id: voting_id.unwrap(),
-                            person_id: person_id,
-                            vote: vote,
+                            id: require_parent(voting_id, "IndividualusBalsavimoRezultatas", "SeimoNariųBalsavimas", context)?,
+                            person_id,
+                            vote,
                         };
-                        voting_data.save(conn).unwrap();
+                        rows.push(voting_data);
                     }
                     _ => {
 
                     }
                 }
             }
-            Ok(XmlEvent::EndElement { name }) => {
-                match name.local_name.as_str() {
-                    "SeimoNariųBalsavimas" => {
-                        voting_id = None;
-                    },
-                    _ => {
-
-                    }
-                }
+            Ok(XmlEvent::EndElement { name }) if name.local_name == "SeimoNariųBalsavimas" => {
+                voting_id = None;
             }
             Err(e) => {
-                error!("Error: {}", e);
-                break;
+                return Err(Error::Xml { context: context.to_owned(), source: e });
             }
             _ => {}
         }
     }
+
+    // Written only once the whole document parsed, in one transaction, so a vote is stored entirely or not at all.
+    let summary = VoteData::upsert_batch(conn, &rows).map_err(Error::database(context))?;
+    debug!("{}: {} inserted, {} updated, {} unchanged", context, summary.inserted, summary.updated, summary.unchanged);
+    Ok(summary.total())
 }
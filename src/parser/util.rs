@@ -1,9 +1,39 @@
 use std::collections::HashMap;
+use std::str::FromStr;
 use xml::attribute::OwnedAttribute;
 
+use crate::error::{Error, Result};
+
 
 pub fn parse_attributes(attributes: Vec<OwnedAttribute> ) -> HashMap<String, String> {
     attributes.iter().map(|x|
         (x.name.local_name.to_owned(), x.value.to_owned())
-    ).collect()    
-}
\ No newline at end of file
+    ).collect()
+}
+
+pub fn get_attribute<'a>(attributes: &'a HashMap<String, String>, element: &str, attribute: &str, context: &str) -> Result<&'a str> {
+    attributes.get(attribute).map(|x| x.as_str()).ok_or_else(|| Error::MissingAttribute {
+        element: element.to_owned(),
+        attribute: attribute.to_owned(),
+        context: context.to_owned(),
+    })
+}
+
+pub fn parse_attribute<T: FromStr>(attributes: &HashMap<String, String>, element: &str, attribute: &str, context: &str) -> Result<T> {
+    let value = get_attribute(attributes, element, attribute, context)?;
+    value.parse().map_err(|_| Error::InvalidValue {
+        element: element.to_owned(),
+        attribute: attribute.to_owned(),
+        value: value.to_owned(),
+        context: context.to_owned(),
+    })
+}
+
+/// Errors out when an element shows up outside of the parent element that carries its ID.
+pub fn require_parent<T: Copy>(parent_id: Option<T>, element: &str, parent: &str, context: &str) -> Result<T> {
+    parent_id.ok_or_else(|| Error::Orphan {
+        element: element.to_owned(),
+        parent: parent.to_owned(),
+        context: context.to_owned(),
+    })
+}
@@ -1,6 +1,18 @@
 // @generated automatically by Diesel CLI.
 
 pub mod sql_types {
+    #[derive(diesel::sql_types::SqlType)]
+    #[diesel(postgres_type(name = "pq_agenda_outcome"))]
+    pub struct PqAgendaOutcome;
+
+    #[derive(diesel::sql_types::SqlType)]
+    #[diesel(postgres_type(name = "pq_crawl_status"))]
+    pub struct PqCrawlStatus;
+
+    #[derive(diesel::sql_types::SqlType)]
+    #[diesel(postgres_type(name = "pq_department_kind"))]
+    pub struct PqDepartmentKind;
+
     #[derive(diesel::sql_types::SqlType)]
     #[diesel(postgres_type(name = "pq_department_type"))]
     pub struct PqDepartmentType;
@@ -9,6 +21,10 @@ pub mod sql_types {
     #[diesel(postgres_type(name = "pq_gender"))]
     pub struct PqGender;
 
+    #[derive(diesel::sql_types::SqlType)]
+    #[diesel(postgres_type(name = "pq_period"))]
+    pub struct PqPeriod;
+
     #[derive(diesel::sql_types::SqlType)]
     #[diesel(postgres_type(name = "pq_vote_type"))]
     pub struct PqVoteType;
@@ -26,8 +42,174 @@ diesel::table! {
         agenda_type -> Nullable<Text>,
         from -> Nullable<Timestamp>,
         to -> Nullable<Timestamp>,
-        speeches -> Array<Nullable<Int4>>,
-        voting -> Array<Nullable<Int4>>,
+    }
+}
+
+diesel::table! {
+    use diesel::sql_types::*;
+    use super::sql_types::PqAgendaOutcome;
+
+    agenda_outcome (meeting_id, position) {
+        meeting_id -> Int4,
+        position -> Int4,
+        nr -> Nullable<Text>,
+        agenda_item_id -> Nullable<Int4>,
+        document_key -> Nullable<Int4>,
+        title -> Text,
+        outcome -> Nullable<PqAgendaOutcome>,
+        decision -> Nullable<Text>,
+    }
+}
+
+diesel::table! {
+    agenda_outcome_document (meeting_id, outcome_position, position) {
+        meeting_id -> Int4,
+        outcome_position -> Int4,
+        position -> Int4,
+        document_number -> Text,
+    }
+}
+
+diesel::table! {
+    agenda_outcome_presenter (meeting_id, outcome_position, position) {
+        meeting_id -> Int4,
+        outcome_position -> Int4,
+        position -> Int4,
+        name -> Text,
+    }
+}
+
+diesel::table! {
+    agenda_speech (agenda_item_id, speech_id) {
+        agenda_item_id -> Int4,
+        speech_id -> Int4,
+        position -> Int4,
+    }
+}
+
+diesel::table! {
+    agenda_vote (agenda_item_id, vote_id) {
+        agenda_item_id -> Int4,
+        vote_id -> Int4,
+        position -> Int4,
+    }
+}
+
+diesel::table! {
+    use diesel::sql_types::*;
+    use super::sql_types::PqCrawlStatus;
+
+    crawl_job (endpoint, entity_id) {
+        endpoint -> Text,
+        entity_id -> Int4,
+        status -> PqCrawlStatus,
+        attempts -> Int4,
+        last_error -> Nullable<Text>,
+        http_status -> Nullable<Int4>,
+        fetched_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    use diesel::sql_types::*;
+    use super::sql_types::PqPeriod;
+
+    cutting_line (period, period_id, dimensions, vote_id) {
+        period -> PqPeriod,
+        period_id -> Int4,
+        dimensions -> Int4,
+        vote_id -> Int4,
+        alpha -> Float8,
+        beta1 -> Float8,
+        beta2 -> Nullable<Float8>,
+        offset -> Float8,
+        angle -> Float8,
+        correct -> Float8,
+    }
+}
+
+diesel::table! {
+    use diesel::sql_types::*;
+    use super::sql_types::PqDepartmentKind;
+
+    department (id) {
+        id -> Int4,
+        name -> Text,
+        short_name -> Nullable<Text>,
+        kind -> PqDepartmentKind,
+    }
+}
+
+diesel::table! {
+    department_member (department_id, parliament, position) {
+        department_id -> Int4,
+        parliament -> Int4,
+        position -> Int4,
+        politician_id -> Int4,
+        duties -> Nullable<Text>,
+        from -> Nullable<Date>,
+        to -> Nullable<Date>,
+    }
+}
+
+diesel::table! {
+    faction (id) {
+        id -> Int4,
+        parliament -> Int4,
+        name -> Text,
+        short_name -> Nullable<Text>,
+    }
+}
+
+diesel::table! {
+    use diesel::sql_types::*;
+    use super::sql_types::PqPeriod;
+
+    faction_cohesion (period, period_id, faction_id) {
+        period -> PqPeriod,
+        period_id -> Int4,
+        faction_id -> Int4,
+        votes -> Int8,
+        ballots -> Int8,
+        rice -> Nullable<Float8>,
+        agreement -> Float8,
+        defection_rate -> Float8,
+    }
+}
+
+diesel::table! {
+    faction_membership (politician_id, parliament, faction_id, from) {
+        politician_id -> Int4,
+        parliament -> Int4,
+        faction_id -> Int4,
+        from -> Date,
+        to -> Nullable<Date>,
+        duties -> Nullable<Text>,
+    }
+}
+
+diesel::table! {
+    use diesel::sql_types::*;
+    use super::sql_types::PqPeriod;
+
+    ideal_point (period, period_id, dimensions, politician_id) {
+        period -> PqPeriod,
+        period_id -> Int4,
+        dimensions -> Int4,
+        politician_id -> Int4,
+        votes -> Int4,
+        coord1 -> Float8,
+        coord2 -> Nullable<Float8>,
+        se1 -> Float8,
+        se2 -> Nullable<Float8>,
+    }
+}
+
+diesel::table! {
+    meeting_agenda_item (meeting_id, agenda_item_id) {
+        meeting_id -> Int4,
+        agenda_item_id -> Int4,
+        position -> Int4,
     }
 }
 
@@ -36,8 +218,14 @@ diesel::table! {
         id -> Int4,
         from -> Nullable<Timestamp>,
         to -> Nullable<Timestamp>,
-        agenda -> Array<Nullable<Int4>>,
-        registrations -> Array<Nullable<Int4>>,
+    }
+}
+
+diesel::table! {
+    meeting_registration (meeting_id, registration_id) {
+        meeting_id -> Int4,
+        registration_id -> Int4,
+        position -> Int4,
     }
 }
 
@@ -56,6 +244,46 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    use diesel::sql_types::*;
+    use super::sql_types::PqPeriod;
+
+    member_attendance (period, period_id, politician_id) {
+        period -> PqPeriod,
+        period_id -> Int4,
+        politician_id -> Int4,
+        sittings -> Int8,
+        sittings_attended -> Int8,
+        days -> Int8,
+        days_absent -> Int8,
+        registrations -> Int8,
+        registered -> Int8,
+        registration_rate -> Nullable<Float8>,
+        votes -> Int8,
+        votes_cast -> Int8,
+        votes_while_registered -> Int8,
+        votes_cast_while_registered -> Int8,
+        voting_share -> Nullable<Float8>,
+    }
+}
+
+diesel::table! {
+    use diesel::sql_types::*;
+    use super::sql_types::PqPeriod;
+
+    member_cohesion (period, period_id, politician_id, faction_id) {
+        period -> PqPeriod,
+        period_id -> Int4,
+        politician_id -> Int4,
+        faction_id -> Int4,
+        votes -> Int8,
+        defections -> Int8,
+        defection_rate -> Float8,
+        rice -> Nullable<Float8>,
+        agreement -> Float8,
+    }
+}
+
 diesel::table! {
     use diesel::sql_types::*;
     use super::sql_types::PqDepartmentType;
@@ -63,11 +291,11 @@ diesel::table! {
     office (id) {
         id -> Int4,
         department_id -> Nullable<Int4>,
-        department_name -> Nullable<Text>,
         department_type -> Nullable<PqDepartmentType>,
         duties -> Nullable<Text>,
         from -> Nullable<Date>,
         to -> Nullable<Date>,
+        group_id -> Nullable<Int4>,
     }
 }
 
@@ -80,6 +308,13 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    parliamentary_group (id) {
+        id -> Int4,
+        name -> Text,
+    }
+}
+
 diesel::table! {
     use diesel::sql_types::*;
     use super::sql_types::PqGender;
@@ -99,7 +334,15 @@ diesel::table! {
         email -> Nullable<Text>,
         phone -> Array<Nullable<Text>>,
         website -> Nullable<Text>,
-        offices -> Array<Nullable<Int4>>,
+    }
+}
+
+diesel::table! {
+    politician_office (politician_id, parliament, office_id) {
+        politician_id -> Int4,
+        parliament -> Int4,
+        office_id -> Int4,
+        position -> Int4,
     }
 }
 
@@ -131,6 +374,20 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    sitting_attendance (meeting_id, politician_id) {
+        meeting_id -> Int4,
+        politician_id -> Int4,
+        day -> Nullable<Date>,
+        registrations -> Int8,
+        registered -> Int8,
+        votes -> Int8,
+        votes_cast -> Int8,
+        votes_while_registered -> Int8,
+        votes_cast_while_registered -> Int8,
+    }
+}
+
 diesel::table! {
     speech (id) {
         id -> Int4,
@@ -143,6 +400,20 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    speech_text (meeting_id, position) {
+        meeting_id -> Int4,
+        position -> Int4,
+        speaker -> Text,
+        faction -> Nullable<Text>,
+        chair -> Bool,
+        person_id -> Nullable<Int4>,
+        speech_id -> Nullable<Int4>,
+        text -> Text,
+        parliament -> Int4,
+    }
+}
+
 diesel::table! {
     vote (id) {
         id -> Int4,
@@ -164,17 +435,63 @@ diesel::table! {
     }
 }
 
+diesel::joinable!(agenda_outcome -> agenda_item (agenda_item_id));
+diesel::joinable!(agenda_outcome -> meetings (meeting_id));
+diesel::joinable!(agenda_speech -> agenda_item (agenda_item_id));
+diesel::joinable!(agenda_speech -> speech (speech_id));
+diesel::joinable!(agenda_vote -> agenda_item (agenda_item_id));
+diesel::joinable!(agenda_vote -> vote (vote_id));
+diesel::joinable!(cutting_line -> vote (vote_id));
+diesel::joinable!(department_member -> department (department_id));
+diesel::joinable!(department_member -> parliament (parliament));
+diesel::joinable!(faction -> department (id));
+diesel::joinable!(faction -> parliament (parliament));
+diesel::joinable!(faction_cohesion -> faction (faction_id));
+diesel::joinable!(faction_membership -> faction (faction_id));
+diesel::joinable!(meeting_agenda_item -> agenda_item (agenda_item_id));
+diesel::joinable!(meeting_agenda_item -> meeting_data (meeting_id));
+diesel::joinable!(meeting_registration -> meeting_data (meeting_id));
+diesel::joinable!(meeting_registration -> registration (registration_id));
+diesel::joinable!(member_cohesion -> faction (faction_id));
+diesel::joinable!(office -> department (department_id));
+diesel::joinable!(office -> parliamentary_group (group_id));
+diesel::joinable!(politician_office -> office (office_id));
+diesel::joinable!(sitting_attendance -> meetings (meeting_id));
+diesel::joinable!(speech_text -> meetings (meeting_id));
+diesel::joinable!(speech_text -> speech (speech_id));
+
 diesel::allow_tables_to_appear_in_same_query!(
     agenda_item,
+    agenda_outcome,
+    agenda_outcome_document,
+    agenda_outcome_presenter,
+    agenda_speech,
+    agenda_vote,
+    crawl_job,
+    cutting_line,
+    department,
+    department_member,
+    faction,
+    faction_cohesion,
+    faction_membership,
+    ideal_point,
+    meeting_agenda_item,
     meeting_data,
+    meeting_registration,
     meetings,
+    member_attendance,
+    member_cohesion,
     office,
     parliament,
+    parliamentary_group,
     politician,
+    politician_office,
     registration,
     registration_data,
     sessions,
+    sitting_attendance,
     speech,
+    speech_text,
     vote,
     vote_data,
 );
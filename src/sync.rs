@@ -0,0 +1,282 @@
+use std::collections::{HashMap, HashSet};
+use std::time::{Duration, Instant};
+
+use log::{error, info, warn};
+
+use crate::analysis;
+use crate::asyncrun;
+use crate::crawler::CrawlOptions;
+use crate::database::context::Context;
+use crate::error::Result;
+use crate::ledger::{self, Endpoint};
+use crate::models::session::{meeting_data::{AgendaItem, MeetingData}, meetings::Meetings, parliament::Parliament, registration_data::RegistrationData, sessions::Sessions, voting_data::VoteData};
+use crate::networking::download::{get_protocol_document, get_stenogram_document, protocol_text_path, stenogram_text_path};
+use crate::parser::document::{protocol, stenogram};
+use crate::parser::session::{meeting_data, meetings, parliaments, registration_data, sessions, voting_data};
+
+#[derive(Debug, Clone)]
+pub struct SyncOptions {
+    /// Parliament to follow, the most recent one when not set.
+    pub parliament_id: Option<i32>,
+    /// Pause between the end of one cycle and the start of the next.
+    pub interval: Duration,
+    /// Run a single cycle and exit.
+    pub once: bool,
+    pub documents: bool,
+    pub crawl: CrawlOptions,
+}
+
+/// What one sync cycle fetched, logged once the cycle is over.
+#[derive(Debug, Default)]
+struct CycleSummary {
+    parliament_id: i32,
+    sessions: usize,
+    new_meetings: usize,
+    changed_meetings: usize,
+    meeting_data: usize,
+    votes: usize,
+    registrations: usize,
+    documents: usize,
+    protocols: usize,
+    stenograms: usize,
+    failed: usize,
+}
+
+/// Meetings LRS added or edited since the previous snapshot, and the meetings whose data should be fetched.
+#[derive(Debug)]
+struct MeetingChanges {
+    new: usize,
+    changed: usize,
+    touched: Vec<Meetings>,
+    /// The touched meetings, then the meetings of the snapshot whose data never made it into the database.
+    fetch_ids: Vec<i32>,
+}
+
+impl MeetingChanges {
+    fn between(before: &HashMap<i32, Meetings>, after: Vec<Meetings>, missing_ids: &[i32]) -> MeetingChanges {
+        let meeting_ids: HashSet<i32> = after.iter().map(|meeting| meeting.id).collect();
+        let mut changes = MeetingChanges { new: 0, changed: 0, touched: Vec::new(), fetch_ids: Vec::new() };
+        for meeting in after {
+            match before.get(&meeting.id) {
+                None => changes.new += 1,
+                Some(previous) if *previous != meeting => changes.changed += 1,
+                Some(_) => continue,
+            }
+            changes.fetch_ids.push(meeting.id);
+            changes.touched.push(meeting);
+        }
+        for meeting_id in missing_ids {
+            if meeting_ids.contains(meeting_id) && !changes.fetch_ids.contains(meeting_id) {
+                changes.fetch_ids.push(*meeting_id);
+            }
+        }
+        changes
+    }
+}
+
+
+/// Polls the current parliament for new sittings until interrupted.
+pub async fn run(ctx: &Context, opts: &SyncOptions) -> Result<()> {
+    let mut cycle = 1;
+    loop {
+        let started = Instant::now();
+        match sync_once(ctx, opts).await {
+            Ok(summary) => info!(
+                "Sync cycle {} (parliament {}) done in {:.1?}: {} session(s), {} new and {} changed meeting(s), {} meeting data, {} vote(s), {} registration(s), {} document(s), {} protocol(s) and {} stenogram(s) parsed, {} failed",
+                cycle, summary.parliament_id, started.elapsed(), summary.sessions, summary.new_meetings, summary.changed_meetings,
+                summary.meeting_data, summary.votes, summary.registrations, summary.documents, summary.protocols, summary.stenograms, summary.failed
+            ),
+            Err(error) => error!("Sync cycle {} failed after {:.1?}: {}", cycle, started.elapsed(), error),
+        }
+        if opts.once {
+            return Ok(());
+        }
+
+        info!("Next sync cycle in {:?}", opts.interval);
+        tokio::select! {
+            _ = tokio::time::sleep(opts.interval) => {}
+            _ = tokio::signal::ctrl_c() => {
+                info!("Interrupted, stopping sync");
+                return Ok(());
+            }
+        }
+        cycle += 1;
+    }
+}
+
+async fn sync_once(ctx: &Context, opts: &SyncOptions) -> Result<CycleSummary> {
+    let crawl = &opts.crawl;
+
+    let parliament_id = match opts.parliament_id {
+        Some(parliament_id) => parliament_id,
+        None => {
+            parliaments::get_parliaments(ctx.clone()).await?;
+            ctx.run(Parliament::get_current_id).await?.ok_or("no parliaments in the database")?
+        }
+    };
+    let mut summary = CycleSummary { parliament_id, ..CycleSummary::default() };
+
+    sessions::get_sessions(ctx.clone(), parliament_id).await?;
+    let session_ids = ctx.run(move |conn| Sessions::get_sessions_per_parliament(conn, parliament_id)).await?;
+    summary.sessions = session_ids.len();
+
+    // Snapshot the meetings before refreshing them, so that the ones LRS added or edited can be told apart.
+    let lookup = session_ids.clone();
+    let before: HashMap<i32, Meetings> = ctx
+        .run(move |conn| Meetings::get_meetings_per_sessions(conn, &lookup))
+        .await?
+        .into_iter()
+        .map(|meeting| (meeting.id, meeting))
+        .collect();
+
+    let mut functions = Vec::new();
+    for session_id in session_ids.clone() {
+        functions.push(ledger::track(ctx.clone(), Endpoint::Meetings, session_id, meetings::get_meetings(ctx.clone(), session_id)));
+    }
+    summary.failed += asyncrun!(functions, crawl.concurrency);
+
+    let after = ctx.run(move |conn| Meetings::get_meetings_per_sessions(conn, &session_ids)).await?;
+    let missing = ctx.run(Meetings::get_missing_meeting_ids).await?;
+    let changes = MeetingChanges::between(&before, after, &missing);
+    summary.new_meetings = changes.new;
+    summary.changed_meetings = changes.changed;
+    let touched = changes.touched;
+
+    let fetch_ids = crawl.pending(ctx, Endpoint::MeetingData, changes.fetch_ids).await?;
+    summary.meeting_data = fetch_ids.len();
+
+    let mut functions = Vec::new();
+    for meeting_id in fetch_ids.clone() {
+        functions.push(ledger::track(ctx.clone(), Endpoint::MeetingData, meeting_id, meeting_data::get_meeting_data(ctx.clone(), meeting_id)));
+    }
+    summary.failed += asyncrun!(functions, crawl.concurrency);
+
+    let lookup = fetch_ids.clone();
+    let vote_ids = ctx
+        .run(move |conn| {
+            let agenda_ids = MeetingData::get_agenda_ids(conn, &lookup)?;
+            let vote_ids = AgendaItem::get_vote_ids(conn, &agenda_ids)?;
+            let missing: HashSet<i32> = VoteData::get_missing_vote_ids(conn)?.into_iter().collect();
+            Ok::<_, diesel::result::Error>(vote_ids.into_iter().filter(|id| missing.contains(id)).collect())
+        })
+        .await?;
+    let vote_ids = crawl.pending(ctx, Endpoint::VotingData, vote_ids).await?;
+    summary.votes = vote_ids.len();
+
+    let mut functions = Vec::new();
+    for vote_id in vote_ids {
+        functions.push(ledger::track(ctx.clone(), Endpoint::VotingData, vote_id, voting_data::get_voting_data(ctx.clone(), vote_id)));
+    }
+    summary.failed += asyncrun!(functions, crawl.concurrency);
+
+    let registration_ids = ctx
+        .run(move |conn| {
+            let registration_ids = MeetingData::get_registration_ids(conn, &fetch_ids)?;
+            let missing: HashSet<i32> = RegistrationData::get_missing_registration_ids(conn)?.into_iter().collect();
+            Ok::<_, diesel::result::Error>(registration_ids.into_iter().filter(|id| missing.contains(id)).collect())
+        })
+        .await?;
+    let registration_ids = crawl.pending(ctx, Endpoint::RegistrationData, registration_ids).await?;
+    summary.registrations = registration_ids.len();
+
+    let mut functions = Vec::new();
+    for registration_id in registration_ids {
+        functions.push(ledger::track(ctx.clone(), Endpoint::RegistrationData, registration_id, registration_data::get_registration_data(ctx.clone(), registration_id)));
+    }
+    summary.failed += asyncrun!(functions, crawl.concurrency);
+
+    if opts.documents {
+        let mut functions = Vec::new();
+        let mut functions2 = Vec::new();
+        for meeting in &touched {
+            if let Some(link) = &meeting.protocol_link {
+                functions.push(get_protocol_document(link.clone(), meeting.session, meeting.num));
+            }
+            if let Some(link) = &meeting.stenogram_link {
+                functions2.push(get_stenogram_document(link.clone(), meeting.session, meeting.num));
+            }
+        }
+        summary.documents += functions.len() + functions2.len();
+        summary.failed += asyncrun!(functions, crawl.concurrency);
+        summary.failed += asyncrun!(functions2, crawl.concurrency);
+
+        let mut functions = Vec::new();
+        for meeting in &touched {
+            let path = match &meeting.protocol_link {
+                Some(link) => protocol_text_path(link, meeting.session, meeting.num)?,
+                None => continue,
+            };
+            if path.exists() {
+                functions.push(protocol::parse_protocol(ctx.clone(), meeting.clone(), path));
+            }
+        }
+        summary.protocols += functions.len();
+        summary.failed += asyncrun!(functions, crawl.concurrency);
+
+        let mut functions = Vec::new();
+        for meeting in touched {
+            let path = match &meeting.stenogram_link {
+                Some(link) => stenogram_text_path(link, meeting.session, meeting.num)?,
+                None => continue,
+            };
+            if path.exists() {
+                functions.push(stenogram::parse_stenogram(ctx.clone(), meeting, path));
+            }
+        }
+        summary.stenograms += functions.len();
+        summary.failed += asyncrun!(functions, crawl.concurrency);
+    }
+
+    if summary.votes > 0 {
+        analysis::refresh_summaries(ctx).await?;
+    }
+
+    if summary.failed > 0 {
+        warn!("{} fetch(es) failed in this cycle, see `crawl_report`", summary.failed);
+    }
+    Ok(summary)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn meeting(id: i32, stenogram_link: Option<&str>) -> Meetings {
+        Meetings {
+            id,
+            num: id,
+            meeting_type: "Rytinis".to_owned(),
+            from: None,
+            to: None,
+            session: 100,
+            protocol_link: None,
+            stenogram_link: stenogram_link.map(str::to_owned),
+            video_comment: None,
+            video_link: None,
+        }
+    }
+
+    #[test]
+    fn detects_new_changed_and_unchanged_meetings() {
+        let before: HashMap<i32, Meetings> = [meeting(1, None), meeting(2, None)].into_iter().map(|meeting| (meeting.id, meeting)).collect();
+        let after = vec![meeting(1, None), meeting(2, Some("https://e-seimas.lrs.lt/2")), meeting(3, None)];
+        let changes = MeetingChanges::between(&before, after, &[]);
+        assert_eq!(changes.new, 1);
+        assert_eq!(changes.changed, 1);
+        assert_eq!(changes.touched, vec![meeting(2, Some("https://e-seimas.lrs.lt/2")), meeting(3, None)]);
+        assert_eq!(changes.fetch_ids, vec![2, 3]);
+    }
+
+    #[test]
+    fn retries_meetings_without_data() {
+        let before: HashMap<i32, Meetings> = [meeting(1, None), meeting(2, None)].into_iter().map(|meeting| (meeting.id, meeting)).collect();
+        let after = vec![meeting(1, None), meeting(2, None), meeting(3, None)];
+        // 2 is missing but unchanged, 3 is both new and missing, 4 belongs to another parliament.
+        let changes = MeetingChanges::between(&before, after, &[2, 3, 4]);
+        assert_eq!(changes.new, 1);
+        assert_eq!(changes.changed, 0);
+        assert_eq!(changes.touched, vec![meeting(3, None)]);
+        assert_eq!(changes.fetch_ids, vec![3, 2]);
+    }
+}